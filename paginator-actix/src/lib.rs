@@ -1,7 +1,29 @@
-use actix_web::{body::BoxBody, HttpRequest, HttpResponse, Responder};
-use paginator_rs::{PaginationParams, PaginatorResponse, PaginatorResponseMeta, SortDirection};
+use actix_web::{
+    body::BoxBody,
+    dev::Payload,
+    error::QueryPayloadError,
+    http::{header, StatusCode},
+    web::{self, Bytes},
+    FromRequest, HttpMessage, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use futures_util::StreamExt;
+use paginator_rs::{
+    meta_headers, parse_fields, parse_query, response_links, ErrorCode, ExportBody, ExportOptions,
+    PaginationConfig, PaginationLinks, PaginationParams, PaginatorError, PaginatorResponse,
+    PaginatorResponseMeta, PaginatorResult, ParseMode, ProblemDetails, ResponseFormat,
+    SortDirection, ValidationError, PROBLEM_CONTENT_TYPE,
+};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::{ready, Future, Ready};
 
+/// Paging, sorting and fields from the query string.
+///
+/// As an extractor it reads them from the params `PaginationMiddleware`
+/// parsed, and otherwise parses the query itself, with the middleware's
+/// config when one is installed and the lenient defaults otherwise. Queries
+/// that fail to parse are rejected with a `400 Bad Request` problem. Take
+/// `Pagination` to also get filters, search and cursors.
 #[derive(Debug, Clone, Deserialize)]
 pub struct PaginationQuery {
     #[serde(default = "default_page")]
@@ -10,6 +32,8 @@ pub struct PaginationQuery {
     pub per_page: u32,
     pub sort_by: Option<String>,
     pub sort_direction: Option<String>,
+    /// Comma-separated fields to return for each row.
+    pub fields: Option<String>,
 }
 
 fn default_page() -> u32 {
@@ -22,49 +46,175 @@ fn default_per_page() -> u32 {
 
 impl PaginationQuery {
     pub fn into_params(self) -> PaginationParams {
-        let sort_direction = self
-            .sort_direction
-            .and_then(|s| match s.to_lowercase().as_str() {
-                "asc" => Some(SortDirection::Asc),
-                "desc" => Some(SortDirection::Desc),
-                _ => None,
-            });
-
-        PaginationParams {
-            page: self.page.max(1),
-            per_page: self.per_page.clamp(1, 100),
-            sort_by: self.sort_by,
-            sort_direction,
-            filters: Vec::new(),
-            search: None,
-            disable_total_count: false,
-            cursor: None,
-        }
+        self.as_params()
     }
 
     pub fn as_params(&self) -> PaginationParams {
-        let sort_direction =
-            self.sort_direction
-                .as_ref()
-                .and_then(|s| match s.to_lowercase().as_str() {
-                    "asc" => Some(SortDirection::Asc),
-                    "desc" => Some(SortDirection::Desc),
-                    _ => None,
-                });
-
-        PaginationParams {
-            page: self.page.max(1),
-            per_page: self.per_page.clamp(1, 100),
+        // Lenient parsing clamps or drops what it cannot use and never fails.
+        self.to_params(ParseMode::Lenient).unwrap_or_default()
+    }
+
+    /// Converts the query into `PaginationParams`; in `ParseMode::Strict`,
+    /// out-of-range pages and unknown sort directions are errors.
+    pub fn to_params(&self, mode: ParseMode) -> PaginatorResult<PaginationParams> {
+        let sort_direction = match self.sort_direction.as_deref() {
+            Some(direction) => mode.sort_direction(direction)?,
+            None => None,
+        };
+
+        Ok(PaginationParams {
+            page: mode.page(self.page)?,
+            per_page: mode.per_page(self.per_page)?,
             sort_by: self.sort_by.clone(),
             sort_direction,
             filters: Vec::new(),
             search: None,
-            disable_total_count: false,
-            cursor: None,
+            fields: self.fields.as_deref().map(parse_fields),
+            ..Default::default()
+        })
+    }
+
+    fn from_params(params: &PaginationParams) -> Self {
+        Self {
+            page: params.page,
+            per_page: params.per_page,
+            sort_by: params.sort_by.clone(),
+            sort_direction: params.sort_direction.as_ref().map(|direction| {
+                match direction {
+                    SortDirection::Asc => "asc",
+                    SortDirection::Desc => "desc",
+                }
+                .to_string()
+            }),
+            fields: params.fields.as_ref().map(|fields| fields.join(",")),
         }
     }
 }
 
+impl FromRequest for PaginationQuery {
+    type Error = Problem;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(request_params(req).map(|params| PaginationQuery::from_params(&params)))
+    }
+}
+
+/// The complete pagination params of a request: paging, sorting, filters,
+/// search, fields, cursor and count options.
+///
+/// Reads the params `PaginationMiddleware` stored in the request extensions
+/// and otherwise parses the query like `PaginationQuery`, rejecting queries
+/// that fail to parse with a `400 Bad Request` problem.
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    pub params: PaginationParams,
+}
+
+impl FromRequest for Pagination {
+    type Error = Problem;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(request_params(req).map(|params| Pagination { params }))
+    }
+}
+
+/// The params `PaginationMiddleware` parsed, or the query parsed with its
+/// config, if installed, or the lenient defaults.
+fn request_params(req: &HttpRequest) -> Result<PaginationParams, Problem> {
+    if let Some(params) = req.extensions().get::<PaginationParams>() {
+        return Ok(params.clone());
+    }
+    let config = req
+        .extensions()
+        .get::<PaginationConfig>()
+        .cloned()
+        .unwrap_or_default();
+    parse_query_string(req.query_string(), &config)
+}
+
+/// Parses the query string with `parse_query`, failing with a `400 Bad
+/// Request` problem instead of ignoring parameters it cannot use: unknown
+/// keys, malformed filters, unknown operators or sort directions, and `page`
+/// or `per_page` out of range.
+///
+/// Uses the defaults and limits of `PaginationMiddleware`'s config when the
+/// middleware is installed, but is always strict.
+#[derive(Debug, Clone)]
+pub struct StrictPaginationQuery(pub PaginationParams);
+
+impl FromRequest for StrictPaginationQuery {
+    type Error = Problem;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let config = req
+            .extensions()
+            .get::<PaginationConfig>()
+            .cloned()
+            .unwrap_or_default()
+            .mode(ParseMode::Strict);
+        ready(parse_query_string(req.query_string(), &config).map(StrictPaginationQuery))
+    }
+}
+
+fn parse_query_string(query: &str, config: &PaginationConfig) -> Result<PaginationParams, Problem> {
+    let pairs = web::Query::<Vec<(String, String)>>::from_query(query).map_err(invalid_query)?;
+    Ok(parse_query(
+        pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+        config,
+    )?)
+}
+
+fn invalid_query(err: QueryPayloadError) -> PaginatorError {
+    ValidationError::new(
+        ErrorCode::InvalidQuery,
+        format!("Invalid query params: {}", err),
+    )
+    .into()
+}
+
+/// A `PaginatorError` rendered as an RFC 7807 `application/problem+json`
+/// response, with the status derived from the error's code.
+///
+/// Handlers can return `Result<PaginatedJson<T>, Problem>` and use `?` on
+/// backend calls.
+#[derive(Debug)]
+pub struct Problem(pub PaginatorError);
+
+impl From<PaginatorError> for Problem {
+    fn from(error: PaginatorError) -> Self {
+        Self(error)
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ResponseError for Problem {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.0.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        HttpResponse::build(self.status_code())
+            .content_type(PROBLEM_CONTENT_TYPE)
+            .body(ProblemDetails::from(&self.0).to_json())
+    }
+}
+
+/// Renders query string decoding failures as problems, for use with
+/// `web::QueryConfig::default().error_handler(query_error_handler)`.
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    Problem(invalid_query(err)).into()
+}
+
 #[derive(Debug)]
 pub struct PaginatedJson<T> {
     response: PaginatorResponse<T>,
@@ -79,6 +229,7 @@ where
             response: PaginatorResponse {
                 data,
                 meta: PaginatorResponseMeta::new(params.page, params.per_page, total),
+                links: None,
             },
         }
     }
@@ -94,7 +245,10 @@ where
 {
     type Body = BoxBody;
 
-    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+    fn respond_to(mut self, req: &HttpRequest) -> HttpResponse<Self::Body> {
+        if self.response.links.is_none() {
+            self.response.links = request_links(req, &self.response.meta);
+        }
         let mut response = HttpResponse::Ok();
 
         if let Some(total) = self.response.meta.total {
@@ -105,11 +259,77 @@ where
         }
         response.insert_header(("X-Current-Page", self.response.meta.page.to_string()));
         response.insert_header(("X-Per-Page", self.response.meta.per_page.to_string()));
+        // Lets `PaginationMiddleware` add the remaining headers and links.
+        response.extensions_mut().insert(self.response.meta.clone());
 
         response.json(&self.response)
     }
 }
 
+/// Paginated response serialized as JSON, MessagePack, CSV or NDJSON,
+/// whichever the request's `Accept` header prefers.
+///
+/// Responds with a `406 Not Acceptable` problem when none of them is
+/// acceptable. The pagination headers are always set; for CSV and NDJSON,
+/// whose bodies hold only the rows, they are the sole carrier of the meta.
+#[derive(Debug)]
+pub struct Negotiated<T> {
+    response: PaginatorResponse<T>,
+}
+
+impl<T> Negotiated<T>
+where
+    T: Serialize,
+{
+    pub fn from_response(response: PaginatorResponse<T>) -> Self {
+        Self { response }
+    }
+}
+
+impl<T> Responder for Negotiated<T>
+where
+    T: Serialize,
+{
+    type Body = BoxBody;
+
+    fn respond_to(mut self, req: &HttpRequest) -> HttpResponse<Self::Body> {
+        if self.response.links.is_none() {
+            self.response.links = request_links(req, &self.response.meta);
+        }
+        let accept = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok());
+        let Some(format) = ResponseFormat::from_accept(accept) else {
+            return Problem(ResponseFormat::not_acceptable()).error_response();
+        };
+        let body = match format.encode(&self.response) {
+            Ok(body) => body,
+            Err(e) => return Problem(e).error_response(),
+        };
+
+        let mut response = HttpResponse::Ok();
+        for header in meta_headers(&self.response.meta) {
+            response.insert_header(header);
+        }
+        response.extensions_mut().insert(self.response.meta.clone());
+        response
+            .insert_header((header::VARY, "accept"))
+            .content_type(format.content_type())
+            .body(body)
+    }
+}
+
+/// Links for a response to `req`, from the params `PaginationMiddleware`
+/// parsed or, without the middleware, the query string parsed leniently.
+fn request_links(req: &HttpRequest, meta: &PaginatorResponseMeta) -> Option<PaginationLinks> {
+    let params = match req.extensions().get::<PaginationParams>() {
+        Some(params) => params.clone(),
+        None => parse_query_string(req.query_string(), &PaginationConfig::default()).ok()?,
+    };
+    Some(response_links(req.path(), &params, meta))
+}
+
 pub fn create_paginated_response<T>(
     data: Vec<T>,
     params: &PaginationParams,
@@ -121,15 +341,93 @@ where
     PaginatedJson::new(data, params, total)
 }
 
+/// Streams every row matching `params` as a CSV or NDJSON download.
+///
+/// `fetch` runs the backend query for one keyset chunk, e.g. a closure calling
+/// `paginate_query` with the chunk's params. Chunks are fetched as the client
+/// reads the body. An error after the first chunk aborts the response, so the
+/// client sees a truncated download rather than an error status; build the
+/// response with `start` to report first-chunk errors with a status.
+pub struct ExportResponse(ExportBody);
+
+impl ExportResponse {
+    pub fn new<T, F, Fut>(params: &PaginationParams, options: ExportOptions, fetch: F) -> Self
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+    {
+        Self(ExportBody::new(params, options, fetch))
+    }
+
+    /// Like `new`, but runs the first chunk's query before responding, so
+    /// its errors can still be returned as a `Problem`.
+    pub async fn start<T, F, Fut>(
+        params: &PaginationParams,
+        options: ExportOptions,
+        fetch: F,
+    ) -> Result<Self, PaginatorError>
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+    {
+        ExportBody::start(params, options, fetch).await.map(Self)
+    }
+}
+
+impl From<ExportBody> for ExportResponse {
+    fn from(body: ExportBody) -> Self {
+        Self(body)
+    }
+}
+
+impl Responder for ExportResponse {
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        HttpResponse::Ok()
+            .content_type(self.0.content_type())
+            .insert_header((header::CONTENT_DISPOSITION, self.0.content_disposition()))
+            .streaming(self.0.into_stream().map(|chunk| chunk.map(Bytes::from)))
+    }
+}
+
 pub mod middleware {
+    use super::parse_query_string;
     use actix_web::{
         dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-        Error,
+        http::header::{HeaderName, HeaderValue, LINK},
+        Error, HttpMessage,
     };
     use futures_util::future::LocalBoxFuture;
+    use paginator_rs::{
+        link_header, meta_headers, page_links, PaginationConfig, PaginatorResponseMeta,
+    };
     use std::future::{ready, Ready};
+    use std::rc::Rc;
+
+    /// Parses each request's query string once with its `PaginationConfig`
+    /// and stores the `PaginationParams` in the request extensions, where the
+    /// `Pagination` and `PaginationQuery` extractors read them back.
+    ///
+    /// Requests that fail to parse, in strict mode or with an invalid `tz` or
+    /// `near`, still reach the handler; the extractors reject them with a
+    /// `400 Bad Request` problem, and routes without them are unaffected.
+    /// Responses from `PaginatedJson` and `Negotiated` get the pagination
+    /// headers, including cursors, and a `Link` header.
+    #[derive(Clone, Debug, Default)]
+    pub struct PaginationMiddleware {
+        config: Rc<PaginationConfig>,
+    }
 
-    pub struct PaginationMiddleware;
+    impl PaginationMiddleware {
+        pub fn new(config: PaginationConfig) -> Self {
+            Self {
+                config: Rc::new(config),
+            }
+        }
+    }
 
     impl<S, B> Transform<S, ServiceRequest> for PaginationMiddleware
     where
@@ -144,12 +442,16 @@ pub mod middleware {
         type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
         fn new_transform(&self, service: S) -> Self::Future {
-            ready(Ok(PaginationMiddlewareService { service }))
+            ready(Ok(PaginationMiddlewareService {
+                service,
+                config: Rc::clone(&self.config),
+            }))
         }
     }
 
     pub struct PaginationMiddlewareService<S> {
         service: S,
+        config: Rc<PaginationConfig>,
     }
 
     impl<S, B> Service<ServiceRequest> for PaginationMiddlewareService<S>
@@ -165,10 +467,41 @@ pub mod middleware {
         forward_ready!(service);
 
         fn call(&self, req: ServiceRequest) -> Self::Future {
+            // Queries that fail to parse are left to the extractors, which
+            // parse them again with the stored config and reject them, so
+            // routes without pagination still see their own query params.
+            if let Ok(params) = parse_query_string(req.query_string(), &self.config) {
+                req.extensions_mut().insert(params);
+            }
+            req.extensions_mut()
+                .insert(PaginationConfig::clone(&self.config));
+
+            let path = req.path().to_string();
+            let query = req.query_string().to_string();
             let fut = self.service.call(req);
 
             Box::pin(async move {
-                let res = fut.await?;
+                let mut res = fut.await?;
+                let meta = res
+                    .response()
+                    .extensions()
+                    .get::<PaginatorResponseMeta>()
+                    .cloned();
+                if let Some(meta) = meta {
+                    let headers = res.headers_mut();
+                    for (name, value) in meta_headers(&meta) {
+                        if let (Ok(name), Ok(value)) = (
+                            HeaderName::from_bytes(name.as_bytes()),
+                            HeaderValue::from_str(&value),
+                        ) {
+                            headers.insert(name, value);
+                        }
+                    }
+                    let links = page_links(&path, Some(&query), &meta);
+                    if let Ok(value) = HeaderValue::from_str(&link_header(&links)) {
+                        headers.insert(LINK, value);
+                    }
+                }
                 Ok(res)
             })
         }
@@ -0,0 +1,67 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, Response},
+    response::IntoResponse,
+};
+use paginator_rs::{
+    ExportBody, ExportOptions, PaginationParams, PaginatorError, PaginatorResponse,
+};
+use serde::Serialize;
+use std::future::Future;
+
+/// Streams every row matching `params` as a CSV or NDJSON download.
+///
+/// `fetch` runs the backend query for one keyset chunk, e.g. a closure calling
+/// `paginate_query` with the chunk's params. Chunks are fetched as the client
+/// reads the body. An error after the first chunk aborts the response, so the
+/// client sees a truncated download rather than an error status; build the
+/// response with `start` to report first-chunk errors with a status.
+pub struct ExportResponse(ExportBody);
+
+impl ExportResponse {
+    pub fn new<T, F, Fut>(params: &PaginationParams, options: ExportOptions, fetch: F) -> Self
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+    {
+        Self(ExportBody::new(params, options, fetch))
+    }
+
+    /// Like `new`, but runs the first chunk's query before responding, so
+    /// its errors can still be returned as a `Problem`.
+    pub async fn start<T, F, Fut>(
+        params: &PaginationParams,
+        options: ExportOptions,
+        fetch: F,
+    ) -> Result<Self, PaginatorError>
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+    {
+        ExportBody::start(params, options, fetch).await.map(Self)
+    }
+}
+
+impl From<ExportBody> for ExportResponse {
+    fn from(body: ExportBody) -> Self {
+        Self(body)
+    }
+}
+
+impl IntoResponse for ExportResponse {
+    fn into_response(self) -> Response<Body> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(self.0.content_type()),
+        );
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&self.0.content_disposition()).unwrap(),
+        );
+
+        (headers, Body::from_stream(self.0.into_stream())).into_response()
+    }
+}
@@ -1,8 +1,14 @@
+mod export;
 mod link;
-mod parser;
+mod negotiate;
+mod problem;
 mod query;
 mod response;
 
+pub use export::ExportResponse;
 pub use link::create_link_header;
-pub use query::{PaginationQuery, PaginationQueryParams};
+pub use negotiate::{AcceptFormat, Negotiated};
+pub use paginator_rs::{parse_filter, parse_filter_with, parse_near, try_parse_filter_with};
+pub use problem::Problem;
+pub use query::{PaginationQuery, PaginationQueryParams, StrictPaginationQuery};
 pub use response::PaginatedJson;
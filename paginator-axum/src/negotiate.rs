@@ -0,0 +1,88 @@
+use crate::problem::Problem;
+use axum::{
+    body::Body,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, HeaderValue, Response},
+    response::IntoResponse,
+    Extension,
+};
+use paginator_rs::{
+    meta_headers, response_links, PaginationParams, PaginatorResponse, ResponseFormat,
+};
+use serde::Serialize;
+
+/// Extracts the response format preferred by the request's `Accept` header.
+///
+/// Rejects with a `406 Not Acceptable` problem when none of JSON,
+/// MessagePack, CSV or NDJSON is acceptable.
+#[derive(Debug, Clone, Copy)]
+pub struct AcceptFormat(pub ResponseFormat);
+
+impl<S> FromRequestParts<S> for AcceptFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = Problem;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let accept = parts
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok());
+
+        ResponseFormat::from_accept(accept)
+            .map(AcceptFormat)
+            .ok_or_else(|| Problem(ResponseFormat::not_acceptable()))
+    }
+}
+
+/// Paginated response serialized in the format chosen by `AcceptFormat`.
+///
+/// The pagination headers are always set; for CSV and NDJSON, whose bodies
+/// hold only the rows, they are the sole carrier of the meta.
+#[derive(Debug)]
+pub struct Negotiated<T> {
+    format: ResponseFormat,
+    response: PaginatorResponse<T>,
+}
+
+impl<T> Negotiated<T>
+where
+    T: Serialize,
+{
+    pub fn new(format: ResponseFormat, response: PaginatorResponse<T>) -> Self {
+        Self { format, response }
+    }
+
+    /// Fills the `links` object, as `PaginatedJson::with_links` does.
+    pub fn with_links(mut self, path: &str, params: &PaginationParams) -> Self {
+        self.response.links = Some(response_links(path, params, &self.response.meta));
+        self
+    }
+}
+
+impl<T> IntoResponse for Negotiated<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response<Body> {
+        let body = match self.format.encode(&self.response) {
+            Ok(body) => body,
+            Err(e) => return Problem(e).into_response(),
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in meta_headers(&self.response.meta) {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(name, value);
+            }
+        }
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(self.format.content_type()),
+        );
+        headers.insert(header::VARY, HeaderValue::from_static("accept"));
+
+        (headers, Extension(self.response.meta), body).into_response()
+    }
+}
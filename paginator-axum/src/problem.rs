@@ -0,0 +1,37 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Response, StatusCode},
+    response::IntoResponse,
+};
+use paginator_rs::{PaginatorError, ProblemDetails, PROBLEM_CONTENT_TYPE};
+
+/// A `PaginatorError` rendered as an RFC 7807 `application/problem+json`
+/// response, with the status derived from the error's code.
+///
+/// Handlers can return `Result<PaginatedJson<T>, Problem>` and use `?` on
+/// backend calls.
+#[derive(Debug)]
+pub struct Problem(pub PaginatorError);
+
+impl From<PaginatorError> for Problem {
+    fn from(error: PaginatorError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response<Body> {
+        let problem = ProblemDetails::from(&self.0);
+        let status =
+            StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (
+            status,
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(PROBLEM_CONTENT_TYPE),
+            )],
+            problem.to_json(),
+        )
+            .into_response()
+    }
+}
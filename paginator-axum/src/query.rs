@@ -1,11 +1,20 @@
-use crate::parser::parse_filter;
+use crate::problem::Problem;
 use axum::{
     extract::{FromRequestParts, Query},
-    http::{request::Parts, StatusCode},
+    http::request::Parts,
+};
+use paginator_rs::{
+    parse_query, ErrorCode, PaginationConfig, PaginationParams, PaginatorResult, ParseMode,
+    ProblemDetails, ValidationError,
 };
-use paginator_rs::{Filter, PaginationParams, SearchParams, SortDirection};
 use serde::{Deserialize, Serialize};
 
+/// Pagination parameters from the query string.
+///
+/// Behind a `paginator_tower::PaginationLayer`, returns the params the layer
+/// parsed, or rejects the request with the problem it stored when the query
+/// failed to parse. Queries the layer skipped are parsed with its config,
+/// strict mode included, and queries without a layer leniently.
 #[derive(Debug, Clone)]
 pub struct PaginationQuery(pub PaginationParams);
 
@@ -22,6 +31,17 @@ pub struct PaginationQueryParams {
     pub filter: Vec<String>,
     pub search: Option<String>,
     pub search_fields: Option<String>,
+    /// Comma-separated fields to return for each row.
+    pub fields: Option<String>,
+    /// IANA time zone relative date filters resolve in, UTC by default.
+    pub tz: Option<String>,
+    /// `field:lon,lat`; adds the distance from that point to every row and
+    /// sorts by it unless `sort_by` says otherwise.
+    pub near: Option<String>,
+    /// An encoded `Cursor` to page from.
+    pub cursor: Option<String>,
+    /// `exact`, `estimated`, `capped:<n>` or `none`.
+    pub count: Option<String>,
 }
 
 fn default_page() -> u32 {
@@ -32,64 +52,103 @@ fn default_per_page() -> u32 {
     20
 }
 
+impl PaginationQueryParams {
+    /// Converts the decoded query into `PaginationParams` with `parse_query`.
+    ///
+    /// In `ParseMode::Strict`, out-of-range pages, unknown sort directions
+    /// and filters that fail to parse are errors; otherwise they are clamped
+    /// or dropped.
+    pub fn into_params(self, mode: ParseMode) -> PaginatorResult<PaginationParams> {
+        let page = self.page.to_string();
+        let per_page = self.per_page.to_string();
+        let mut pairs = vec![("page", page.as_str()), ("per_page", per_page.as_str())];
+        pairs.extend(self.filter.iter().map(|filter| ("filter", filter.as_str())));
+        let optional = [
+            ("sort_by", &self.sort_by),
+            ("sort_direction", &self.sort_direction),
+            ("search", &self.search),
+            ("search_fields", &self.search_fields),
+            ("fields", &self.fields),
+            ("tz", &self.tz),
+            ("near", &self.near),
+            ("cursor", &self.cursor),
+            ("count", &self.count),
+        ];
+        pairs.extend(
+            optional
+                .iter()
+                .filter_map(|(key, value)| value.as_deref().map(|value| (*key, value))),
+        );
+
+        parse_query(pairs, &PaginationConfig::new().mode(mode))
+    }
+}
+
+/// Decodes the query string of `parts` with `parse_query`, using the
+/// `PaginationConfig` a `PaginationLayer` stored in the extensions, if any,
+/// with `mode` in place of its own when given.
+fn extract(parts: &Parts, mode: Option<ParseMode>) -> Result<PaginationParams, Problem> {
+    let Query(pairs): Query<Vec<(String, String)>> =
+        Query::try_from_uri(&parts.uri).map_err(|e| {
+            Problem(
+                ValidationError::new(
+                    ErrorCode::InvalidQuery,
+                    format!("Invalid query params: {}", e.body_text()),
+                )
+                .into(),
+            )
+        })?;
+
+    let mut config = parts
+        .extensions
+        .get::<PaginationConfig>()
+        .cloned()
+        .unwrap_or_default();
+    if let Some(mode) = mode {
+        config = config.mode(mode);
+    }
+    Ok(parse_query(
+        pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+        &config,
+    )?)
+}
+
 impl<S> FromRequestParts<S> for PaginationQuery
 where
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, String);
+    type Rejection = Problem;
 
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let Query(params): Query<PaginationQueryParams> =
-            Query::from_request_parts(parts, state).await.map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    format!("Invalid query params: {}", e),
-                )
-            })?;
-
-        let sort_direction = params
-            .sort_direction
-            .and_then(|s| match s.to_lowercase().as_str() {
-                "asc" => Some(SortDirection::Asc),
-                "desc" => Some(SortDirection::Desc),
-                _ => None,
-            });
-
-        let filters: Vec<Filter> = params
-            .filter
-            .iter()
-            .filter_map(|f| parse_filter(f))
-            .collect();
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(params) = parts.extensions.get::<PaginationParams>() {
+            return Ok(PaginationQuery(params.clone()));
+        }
+        if let Some(problem) = parts.extensions.get::<ProblemDetails>() {
+            return Err(Problem(problem.clone().into()));
+        }
+        extract(parts, None).map(PaginationQuery)
+    }
+}
 
-        let search = if let Some(query) = params.search {
-            let fields: Vec<String> = params
-                .search_fields
-                .map(|f| f.split(',').map(|s| s.trim().to_string()).collect())
-                .unwrap_or_default();
+/// Like `PaginationQuery`, but rejects the request with a `400 Bad Request`
+/// problem instead of ignoring parameters it cannot use: unknown keys,
+/// malformed filters, unknown operators or sort directions, and `page` or
+/// `per_page` out of range.
+///
+/// Uses the defaults and limits of a `PaginationLayer`'s config, but is
+/// always strict.
+#[derive(Debug, Clone)]
+pub struct StrictPaginationQuery(pub PaginationParams);
 
-            if !fields.is_empty() {
-                Some(SearchParams {
-                    query,
-                    fields,
-                    case_sensitive: false,
-                    exact_match: false,
-                })
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+impl<S> FromRequestParts<S> for StrictPaginationQuery
+where
+    S: Send + Sync,
+{
+    type Rejection = Problem;
 
-        Ok(PaginationQuery(PaginationParams {
-            page: params.page.max(1),
-            per_page: params.per_page.clamp(1, 100),
-            sort_by: params.sort_by,
-            sort_direction,
-            filters,
-            search,
-            disable_total_count: false,
-            cursor: None,
-        }))
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        extract(parts, Some(ParseMode::Strict)).map(StrictPaginationQuery)
     }
 }
@@ -1,9 +1,9 @@
 use axum::{
     http::{HeaderMap, HeaderValue, Response},
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
-use paginator_rs::{PaginationParams, PaginatorResponse, PaginatorResponseMeta};
+use paginator_rs::{response_links, PaginationParams, PaginatorResponse, PaginatorResponseMeta};
 use serde::Serialize;
 
 #[derive(Debug)]
@@ -17,12 +17,22 @@ where
         Self(PaginatorResponse {
             data,
             meta: PaginatorResponseMeta::new(params.page, params.per_page, total),
+            links: None,
         })
     }
 
     pub fn from_response(response: PaginatorResponse<T>) -> Self {
         Self(response)
     }
+
+    /// Fills the `links` object for a request to `path` with `params`.
+    ///
+    /// Responders cannot see the request in axum, so take the path from
+    /// `OriginalUri`, which keeps the prefix of nested routers.
+    pub fn with_links(mut self, path: &str, params: &PaginationParams) -> Self {
+        self.0.links = Some(response_links(path, params, &self.0.meta));
+        self
+    }
 }
 
 impl<T> IntoResponse for PaginatedJson<T>
@@ -55,6 +65,7 @@ where
 
         let json_response = Json(&self.0);
 
-        (headers, json_response).into_response()
+        // Lets a `PaginationLayer` add the remaining headers and links.
+        (headers, Extension(self.0.meta.clone()), json_response).into_response()
     }
 }
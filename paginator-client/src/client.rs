@@ -0,0 +1,242 @@
+use crate::link::next_url;
+use crate::retry::RetryPolicy;
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use paginator_rs::{
+    query_pairs, PaginationParams, PaginatorError, PaginatorResponse, PaginatorResult,
+    ProblemDetails,
+};
+use reqwest::header::{LINK, RETRY_AFTER};
+use reqwest::{Response, Url};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// One page of a paginated endpoint.
+#[derive(Debug)]
+pub struct Page<T> {
+    pub response: PaginatorResponse<T>,
+    /// The URL of the following page; `None` on the last page.
+    pub next: Option<Url>,
+}
+
+/// Rows of every page, in order. Ends after the first error.
+pub type ItemStream<T> = BoxStream<'static, PaginatorResult<T>>;
+
+/// Every page, in order. Ends after the first error.
+pub type PageStream<T> = BoxStream<'static, PaginatorResult<Page<T>>>;
+
+type RateLimitHook = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>;
+type RetryHook = Arc<dyn Fn(u32, Duration, &PaginatorError) + Send + Sync>;
+
+/// Reads paginated endpoints served with `PaginatorResponse` bodies.
+///
+/// Requests carry `PaginationParams` in the query format `parse_query`
+/// reads. `pages` and `stream` follow the server's `next` links, its
+/// `next_cursor` or its page numbers until the last page.
+#[derive(Clone, Default)]
+pub struct PaginatorClient {
+    http: reqwest::Client,
+    retry: RetryPolicy,
+    rate_limit: Option<RateLimitHook>,
+    on_retry: Option<RetryHook>,
+}
+
+impl fmt::Debug for PaginatorClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PaginatorClient")
+            .field("retry", &self.retry)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PaginatorClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends requests with `http`, e.g. one configured with a timeout or
+    /// default headers.
+    pub fn with_client(http: reqwest::Client) -> Self {
+        Self {
+            http,
+            ..Self::default()
+        }
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Awaits `hook` before every request, retries included, e.g. to take a
+    /// token from a rate limiter.
+    pub fn rate_limit<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.rate_limit = Some(Arc::new(move || Box::pin(hook())));
+        self
+    }
+
+    /// Calls `hook` with the attempt number, the delay and the error before
+    /// each retry.
+    pub fn on_retry<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(u32, Duration, &PaginatorError) + Send + Sync + 'static,
+    {
+        self.on_retry = Some(Arc::new(hook));
+        self
+    }
+
+    /// Fetches the page of `endpoint` described by `params`.
+    pub async fn fetch<T>(
+        &self,
+        endpoint: &str,
+        params: &PaginationParams,
+    ) -> PaginatorResult<Page<T>>
+    where
+        T: DeserializeOwned,
+    {
+        self.fetch_url(page_url(endpoint, params)?).await
+    }
+
+    /// Fetches the page at `url`, such as a `Page::next`.
+    pub async fn fetch_url<T>(&self, url: Url) -> PaginatorResult<Page<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut attempt = 0;
+        loop {
+            if let Some(hook) = &self.rate_limit {
+                hook().await;
+            }
+            let (error, retry_after) = match self.http.get(url.clone()).send().await {
+                Ok(response) if response.status().is_success() => {
+                    return read_page(url, response).await
+                }
+                Ok(response) => {
+                    let retryable = RetryPolicy::retries_status(response.status().as_u16());
+                    let retry_after = retry_after(&response);
+                    let error = status_error(&url, response).await;
+                    if !retryable {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => (request_error(&url, e), None),
+                Err(e) => return Err(request_error(&url, e)),
+            };
+
+            attempt += 1;
+            if attempt > self.retry.max_retries {
+                return Err(error);
+            }
+            let delay = self.retry.delay(attempt, retry_after);
+            if let Some(hook) = &self.on_retry {
+                hook(attempt, delay, &error);
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Every page of `endpoint`, starting at the one described by `params`.
+    ///
+    /// Stops after an empty page or a `next` link pointing back at the same
+    /// URL, so a misbehaving server cannot loop it forever.
+    pub fn pages<T>(&self, endpoint: &str, params: &PaginationParams) -> PageStream<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let client = self.clone();
+        stream::unfold(Some(page_url(endpoint, params)), move |state| {
+            let client = client.clone();
+            async move {
+                let url = match state? {
+                    Ok(url) => url,
+                    Err(e) => return Some((Err(e), None)),
+                };
+                match client.fetch_url::<T>(url.clone()).await {
+                    Ok(page) => {
+                        let next = page
+                            .next
+                            .clone()
+                            .filter(|next| *next != url && !page.response.data.is_empty());
+                        Some((Ok(page), next.map(Ok)))
+                    }
+                    Err(e) => Some((Err(e), None)),
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /// The rows of every page of `endpoint`, as `pages` fetches them.
+    pub fn stream<T>(&self, endpoint: &str, params: &PaginationParams) -> ItemStream<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.pages(endpoint, params)
+            .map_ok(|page| stream::iter(page.response.data.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+}
+
+/// `endpoint` with `params` appended as query pairs. Pairs already in
+/// `endpoint`, such as an API key, are kept.
+pub fn page_url(endpoint: &str, params: &PaginationParams) -> PaginatorResult<Url> {
+    let mut url = Url::parse(endpoint)
+        .map_err(|e| PaginatorError::Custom(format!("Invalid URL '{}': {}", endpoint, e)))?;
+    url.query_pairs_mut().extend_pairs(query_pairs(params));
+    Ok(url)
+}
+
+async fn read_page<T>(url: Url, response: Response) -> PaginatorResult<Page<T>>
+where
+    T: DeserializeOwned,
+{
+    let link = response
+        .headers()
+        .get(LINK)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.bytes().await.map_err(|e| request_error(&url, e))?;
+    let response: PaginatorResponse<T> = serde_json::from_slice(&body).map_err(|e| {
+        PaginatorError::SerializationError(format!("Invalid page from {}: {}", url, e))
+    })?;
+    let next = next_url(&url, link.as_deref(), &response);
+    Ok(Page { response, next })
+}
+
+/// The error for a failed response, from its problem document when the
+/// server rejected the request.
+async fn status_error(url: &Url, response: Response) -> PaginatorError {
+    let status = response.status();
+    if status.is_client_error() {
+        if let Ok(problem) = response.json::<ProblemDetails>().await {
+            return problem.into();
+        }
+    }
+    PaginatorError::Custom(format!("GET {} returned {}", url, status))
+}
+
+fn request_error(url: &Url, error: reqwest::Error) -> PaginatorError {
+    PaginatorError::Custom(format!("GET {} failed: {}", url, error))
+}
+
+/// A `Retry-After` given in seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
@@ -0,0 +1,6 @@
+mod client;
+mod link;
+mod retry;
+
+pub use client::{page_url, ItemStream, Page, PageStream, PaginatorClient};
+pub use retry::RetryPolicy;
@@ -0,0 +1,66 @@
+use paginator_rs::PaginatorResponse;
+use reqwest::Url;
+
+/// The URL of the page after the one fetched from `url`.
+///
+/// Prefers the `next` link of the `Link` header, then the one in the body's
+/// `links`, both relative to `url`. Without links, `url` is repeated with
+/// `cursor` set to `next_cursor`, or with the next `page` while `has_next`
+/// holds.
+pub(crate) fn next_url<T>(
+    url: &Url,
+    link_header: Option<&str>,
+    response: &PaginatorResponse<T>,
+) -> Option<Url> {
+    let body_link = response
+        .links
+        .as_ref()
+        .and_then(|links| links.next.as_deref());
+    if let Some(href) = link_header.and_then(next_link).or(body_link) {
+        return url.join(href).ok();
+    }
+
+    let meta = &response.meta;
+    let (key, value) = match &meta.next_cursor {
+        Some(cursor) => ("cursor", cursor.clone()),
+        None if meta.has_next => ("page", (meta.page + 1).to_string()),
+        None => return None,
+    };
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| !matches!(name.as_ref(), "page" | "cursor"))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    let mut next = url.clone();
+    next.query_pairs_mut()
+        .clear()
+        .append_pair(key, &value)
+        .extend_pairs(kept);
+    Some(next)
+}
+
+/// The target of the `rel="next"` link in an RFC 8288 `Link` header.
+fn next_link(header: &str) -> Option<&str> {
+    let mut rest = header;
+    while let Some(start) = rest.find('<') {
+        let end = start + rest[start..].find('>')?;
+        let href = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+        let params = &rest[..rest.find('<').unwrap_or(rest.len())];
+        let is_next = params.split(';').any(|param| {
+            param
+                .trim()
+                .trim_end_matches(',')
+                .strip_prefix("rel=")
+                .is_some_and(|rel| {
+                    rel.trim_matches('"')
+                        .split_whitespace()
+                        .any(|r| r == "next")
+                })
+        });
+        if is_next {
+            return Some(href);
+        }
+    }
+    None
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+/// How often and how long to wait before retrying a failed page request.
+///
+/// Connection errors, timeouts, `429 Too Many Requests` and `502`, `503` and
+/// `504` responses are retried. Delays double from `backoff` up to
+/// `max_backoff`, unless the response names one in `Retry-After`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that fails on the first error.
+    pub fn never() -> Self {
+        Self::default().max_retries(0)
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// The delay before retry `attempt`, counting from 1. A server's
+    /// `Retry-After` is honoured even beyond `max_backoff`.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after.unwrap_or_else(|| {
+            let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+            self.backoff.saturating_mul(factor).min(self.max_backoff)
+        })
+    }
+
+    pub(crate) fn retries_status(status: u16) -> bool {
+        matches!(status, 429 | 502 | 503 | 504)
+    }
+}
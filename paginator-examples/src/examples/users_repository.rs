@@ -189,6 +189,7 @@ impl PaginatorTrait<UsersData> for Vec<UsersData> {
         Ok(PaginatorResponse {
             data: paginated_data,
             meta: PaginatorResponseMeta::new(params.page, params.per_page, total),
+            links: None,
         })
     }
 }
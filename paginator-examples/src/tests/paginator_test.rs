@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 pub mod tests {
     use crate::users_repository::UsersData;
     use paginator_rs::{FilterValue, PaginationParams, PaginatorBuilder, PaginatorTrait};
@@ -590,4 +591,2657 @@ pub mod tests {
         assert_eq!(result.data[0].id, 81);
         assert_eq!(result.data[19].id, 100);
     }
+
+    #[test]
+    fn test_count_fingerprint_ignores_page_and_sort() {
+        use paginator_rs::count_fingerprint;
+
+        let page_one = PaginatorBuilder::new()
+            .page(1)
+            .filter_eq("status", FilterValue::String("active".into()))
+            .build();
+        let page_two = PaginatorBuilder::new()
+            .page(2)
+            .sort_by("name")
+            .filter_eq("status", FilterValue::String("active".into()))
+            .build();
+        let other_filter = PaginatorBuilder::new()
+            .filter_eq("status", FilterValue::String("inactive".into()))
+            .build();
+
+        let query = "SELECT * FROM users";
+        assert_eq!(
+            count_fingerprint(query, &page_one),
+            count_fingerprint(query, &page_two)
+        );
+        assert_ne!(
+            count_fingerprint(query, &page_one),
+            count_fingerprint(query, &other_filter)
+        );
+        assert_ne!(
+            count_fingerprint(query, &page_one),
+            count_fingerprint("SELECT * FROM orders", &page_one)
+        );
+    }
+
+    #[test]
+    fn test_count_fingerprint_keys_relative_dates_and_options() {
+        use paginator_rs::chrono::{Duration, TimeZone, Utc};
+        use paginator_rs::{
+            count_fingerprint, count_fingerprint_with, parse_filter_with, parse_time_zone,
+            DateContext,
+        };
+
+        let at = Utc.with_ymd_and_hms(2024, 3, 15, 20, 30, 0).unwrap();
+        let jakarta = parse_time_zone("Asia/Jakarta").unwrap();
+        let params_at = |dates: DateContext, expr: &str| {
+            PaginationParams::default().with_filter(
+                parse_filter_with(&format!("created_at:gte:{}", expr), &dates).unwrap(),
+            )
+        };
+
+        let query = "SELECT * FROM users";
+        let first = params_at(DateContext::at(at, jakarta), "now-7d");
+        let later = params_at(
+            DateContext::at(at + Duration::seconds(5), jakarta),
+            "now-7d",
+        );
+        assert_ne!(first.filters[0].value, later.filters[0].value);
+        assert_eq!(
+            count_fingerprint(query, &first),
+            count_fingerprint(query, &later)
+        );
+
+        let utc = params_at(DateContext::at(at, Default::default()), "now-7d");
+        let absolute = params_at(DateContext::at(at, jakarta), "2024-03-08T20:30:00Z");
+        for other in [&utc, &absolute] {
+            assert_ne!(
+                count_fingerprint(query, &first),
+                count_fingerprint(query, other)
+            );
+        }
+
+        assert_eq!(
+            count_fingerprint(query, &first),
+            count_fingerprint_with(query, &first, &())
+        );
+        assert_ne!(
+            count_fingerprint_with(query, &first, &Vec::<String>::new()),
+            count_fingerprint_with(query, &first, &vec!["attrs".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_count_cache_ttl_size_and_tags() {
+        use paginator_rs::{CountCache, TotalKind};
+        use std::time::Duration;
+
+        let cache = CountCache::new(Duration::from_secs(60), 2);
+        cache.insert(1, (10, TotalKind::Exact), &["users"]);
+        cache.insert(2, (20, TotalKind::Exact), &["orders"]);
+        assert_eq!(cache.get(1), Some((10, TotalKind::Exact)));
+
+        cache.insert(3, (30, TotalKind::Exact), &["users", "orders"]);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(1), None);
+
+        cache.invalidate_tag("users");
+        assert_eq!(cache.get(3), None);
+        assert_eq!(cache.get(2), Some((20, TotalKind::Exact)));
+
+        let expired = CountCache::new(Duration::ZERO, 10);
+        expired.insert(1, (10, TotalKind::Exact), &["users"]);
+        assert_eq!(expired.get(1), None);
+    }
+
+    #[test]
+    fn test_backward_cursor_reverses_order_and_rows() {
+        use paginator_rs::{CursorValue, PaginatorResponse, SortDirection, TotalKind};
+
+        let params = PaginatorBuilder::new()
+            .per_page(2)
+            .sort_by("id")
+            .sort_asc()
+            .cursor_before("id", CursorValue::Int(10))
+            .build();
+        assert_eq!(params.order_direction(), SortDirection::Desc);
+        assert_eq!(params.cursor_operator(), "<");
+
+        // Rows arrive nearest-first in the reversed scan, plus a lookahead row
+        let result = PaginatorResponse::from_rows(vec![9, 8, 7], &params, None);
+        assert_eq!(result.data, vec![8, 9]);
+        assert!(result.meta.has_prev);
+        assert!(result.meta.has_next);
+
+        let first_page = PaginatorResponse::from_rows(vec![2, 1], &params, None);
+        assert_eq!(first_page.data, vec![1, 2]);
+        assert!(!first_page.meta.has_prev);
+        assert!(first_page.meta.has_next);
+
+        // Nothing before the cursor: only an exact total shows rows after it.
+        let empty = PaginatorResponse::<i32>::from_rows(vec![], &params, None);
+        assert!(!empty.meta.has_prev && !empty.meta.has_next);
+        let empty =
+            PaginatorResponse::<i32>::from_rows(vec![], &params, Some((4, TotalKind::Exact)));
+        assert!(!empty.meta.has_prev && empty.meta.has_next);
+
+        let forward = PaginatorBuilder::new()
+            .per_page(2)
+            .sort_by("id")
+            .cursor_after("id", CursorValue::Int(0))
+            .build();
+        let all_rows =
+            PaginatorResponse::from_rows(vec![1, 2], &forward, Some((2, TotalKind::Exact)));
+        assert!(!all_rows.meta.has_prev && !all_rows.meta.has_next);
+        let past_end =
+            PaginatorResponse::<i32>::from_rows(vec![], &forward, Some((2, TotalKind::Exact)));
+        assert!(past_end.meta.has_prev && !past_end.meta.has_next);
+    }
+
+    #[test]
+    fn test_query_plan_serializes_binds_in_order() {
+        use paginator_sqlx::{mysql, postgres, sqlite};
+        use sea_orm::EntityTrait;
+
+        let params = PaginatorBuilder::new()
+            .per_page(10)
+            .filter_eq("name", FilterValue::String("alice".into()))
+            .filter_gt("age", FilterValue::Int(30))
+            .build();
+        let base = "SELECT * FROM users";
+
+        let plan = postgres::plan(base, &params).unwrap();
+        assert_eq!(
+            serde_json::to_value(&plan).unwrap(),
+            json!({
+                "count": {
+                    "sql": "SELECT COUNT(*) FROM (SELECT * FROM users) AS _base WHERE 1=1 \
+                            AND name = $1 AND age > $2",
+                    "binds": ["alice", 30]
+                },
+                "data": {
+                    "sql": "SELECT * FROM (SELECT * FROM users) AS _base WHERE 1=1 \
+                            AND name = $1 AND age > $2 LIMIT $3 OFFSET $4",
+                    "binds": ["alice", 30, 10, 0]
+                }
+            })
+        );
+
+        // The same statements with `?` placeholders.
+        for plan in [
+            mysql::plan(base, &params).unwrap(),
+            sqlite::plan(base, &params).unwrap(),
+        ] {
+            assert_eq!(
+                plan.data.sql,
+                "SELECT * FROM (SELECT * FROM users) AS _base WHERE 1=1 \
+                 AND name = ? AND age > ? LIMIT ? OFFSET ?"
+            );
+            assert_eq!(
+                serde_json::to_value(&plan.data.binds).unwrap(),
+                json!(["alice", 30, 10, 0])
+            );
+        }
+
+        let params = PaginatorBuilder::new()
+            .per_page(10)
+            .disable_total_count()
+            .build();
+        let plan = postgres::plan(base, &params).unwrap();
+        assert!(plan.count.is_none());
+        assert_eq!(plan.data.sql, "SELECT * FROM users LIMIT $1 OFFSET $2");
+        // The lookahead row shows up in the bound limit.
+        assert_eq!(
+            serde_json::to_value(&plan.data.binds).unwrap(),
+            json!([11, 0])
+        );
+
+        let params = PaginatorBuilder::new()
+            .per_page(10)
+            .filter_eq("name", FilterValue::String("alice".into()))
+            .build();
+        let plan = paginator_sea_orm::plan(
+            user_entity::Entity::find(),
+            sea_orm::DbBackend::Postgres,
+            &params,
+        )
+        .unwrap();
+        let select =
+            r#"SELECT "users"."id", "users"."name", "users"."age" FROM "users" WHERE "name" = $1"#;
+        assert_eq!(
+            serde_json::to_value(&plan).unwrap(),
+            json!({
+                "count": {
+                    "sql": format!(r#"SELECT COUNT(*) AS num_items FROM ({}) AS "_count""#, select),
+                    "binds": ["alice"]
+                },
+                "data": {
+                    "sql": format!("{} LIMIT $2 OFFSET $3", select),
+                    "binds": ["alice", 10, 0]
+                }
+            })
+        );
+
+        // SurrealQL inlines the values, so nothing is bound.
+        let plan = paginator_surrealdb::plan("SELECT * FROM users", &params).unwrap();
+        assert_eq!(
+            serde_json::to_value(&plan).unwrap(),
+            json!({
+                "count": {
+                    "sql": "SELECT count() FROM users WHERE name = 'alice' GROUP ALL",
+                    "binds": []
+                },
+                "data": {
+                    "sql": "SELECT * FROM users WHERE name = 'alice' LIMIT 10 START 0",
+                    "binds": []
+                }
+            })
+        );
+    }
+
+    mod user_entity {
+        use sea_orm::entity::prelude::*;
+
+        #[derive(Clone, Debug, PartialEq, DeriveEntityModel, serde::Serialize)]
+        #[sea_orm(table_name = "users")]
+        pub struct Model {
+            #[sea_orm(primary_key)]
+            pub id: i64,
+            pub name: String,
+            pub age: i32,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    #[test]
+    fn test_span_fields_redact_filter_values() {
+        use paginator_rs::{cursor_mode, describe_filters, CursorValue};
+
+        let params = PaginatorBuilder::new()
+            .filter_eq("status", FilterValue::String("active".into()))
+            .filter_gt("age", FilterValue::Int(18))
+            .cursor_after("id", CursorValue::Int(42))
+            .build();
+
+        assert_eq!(describe_filters(&params, false), "status eq ?, age gt ?");
+        assert_eq!(
+            describe_filters(&params, true),
+            "status eq 'active', age gt 18"
+        );
+        assert_eq!(cursor_mode(&params), "after");
+        assert_eq!(cursor_mode(&PaginationParams::default()), "none");
+    }
+
+    #[test]
+    fn test_export_stream_walks_keyset_chunks() {
+        use futures::{executor::block_on, StreamExt};
+        use paginator_rs::{
+            export_stream, CursorValue, ExportFormat, ExportOptions, PaginatorResponse,
+        };
+
+        let users: Vec<UsersData> = (1..=7)
+            .map(|id| UsersData::new(id, format!("User, {}", id), format!("u{}@x.io", id)))
+            .collect();
+        let fetch = move |params: PaginationParams| {
+            let after = match params.cursor.as_ref().map(|c| &c.value) {
+                Some(CursorValue::Int(id)) => *id as u32,
+                _ => 0,
+            };
+            let rows: Vec<UsersData> = users
+                .iter()
+                .filter(|u| u.id > after)
+                .take(params.fetch_limit() as usize)
+                .cloned()
+                .collect();
+            async move { Ok(PaginatorResponse::from_rows(rows, &params, None)) }
+        };
+
+        let params = PaginatorBuilder::new().per_page(20).build();
+        let options = ExportOptions::new(ExportFormat::Csv)
+            .filename("users")
+            .chunk_size(3)
+            .max_rows(Some(5));
+        assert_eq!(
+            options.content_disposition(),
+            "attachment; filename=\"users.csv\""
+        );
+
+        let chunks: Vec<Vec<u8>> = block_on(
+            export_stream(&params, &options, fetch)
+                .map(|chunk| chunk.unwrap())
+                .collect(),
+        );
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            String::from_utf8(chunks.concat()).unwrap(),
+            "id,name,email\r\n\
+             1,\"User, 1\",u1@x.io\r\n\
+             2,\"User, 2\",u2@x.io\r\n\
+             3,\"User, 3\",u3@x.io\r\n\
+             4,\"User, 4\",u4@x.io\r\n\
+             5,\"User, 5\",u5@x.io\r\n"
+        );
+    }
+
+    #[test]
+    fn test_response_format_negotiation() {
+        use paginator_rs::{meta_headers, ResponseFormat};
+
+        assert_eq!(
+            ResponseFormat::from_accept(None),
+            Some(ResponseFormat::Json)
+        );
+        assert_eq!(
+            ResponseFormat::from_accept(Some("text/csv;q=0.5, application/msgpack")),
+            Some(ResponseFormat::MessagePack)
+        );
+        assert_eq!(
+            ResponseFormat::from_accept(Some("application/json;q=0, text/*")),
+            Some(ResponseFormat::Csv)
+        );
+        assert_eq!(ResponseFormat::from_accept(Some("application/xml")), None);
+
+        let users = vec![
+            UsersData::new(1, "John".into(), "john@doe.com".into()),
+            UsersData::new(2, "Jane".into(), "jane@doe.com".into()),
+        ];
+        let response = users.paginate(&PaginationParams::new(1, 1)).unwrap();
+
+        let csv = ResponseFormat::Csv.encode(&response).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,name,email\r\n1,John,john@doe.com\r\n"
+        );
+        let ndjson = ResponseFormat::Ndjson.encode(&response).unwrap();
+        assert_eq!(
+            String::from_utf8(ndjson).unwrap(),
+            "{\"id\":1,\"name\":\"John\",\"email\":\"john@doe.com\"}\n"
+        );
+
+        let headers = meta_headers(&response.meta);
+        assert!(headers.contains(&("X-Total-Count", "2".to_string())));
+        assert!(headers.contains(&("X-Has-Next", "true".to_string())));
+        assert!(!ResponseFormat::Csv.has_envelope());
+    }
+
+    #[test]
+    fn test_sparse_fieldset_validation_and_pruning() {
+        use paginator_rs::{parse_fields, select_fields, Paginator};
+
+        let params = Paginator::new()
+            .per_page(1)
+            .fields(parse_fields(" email, id,,"))
+            .build();
+        assert_eq!(
+            params.fields,
+            Some(vec!["email".to_string(), "id".to_string()])
+        );
+        assert!(params.validate_fields(&["id", "name", "email"]).is_ok());
+        assert!(params.validate_fields(&["id", "name"]).is_err());
+        assert!(PaginationParams::new(1, 1)
+            .with_fields(Vec::<String>::new())
+            .validate_fields(&["id"])
+            .is_err());
+
+        let users = vec![UsersData::new(1, "John".into(), "john@doe.com".into())];
+        let response = select_fields(users.paginate(&params).unwrap(), &params).unwrap();
+        assert_eq!(
+            serde_json::to_value(&response.data).unwrap(),
+            json!([{ "id": 1, "email": "john@doe.com" }])
+        );
+
+        let all = select_fields(
+            users.paginate(&PaginationParams::new(1, 1)).unwrap(),
+            &PaginationParams::new(1, 1),
+        )
+        .unwrap();
+        assert_eq!(all.data[0].fields(), vec!["id", "name", "email"]);
+    }
+
+    #[test]
+    fn test_relative_dates_resolve_in_request_time_zone() {
+        use paginator_rs::chrono::{TimeZone, Utc};
+        use paginator_rs::{parse_time_zone, DateContext, Filter, FilterOperator, RelativeDate};
+
+        // 2024-03-15 20:30 UTC is already the 16th in Jakarta (UTC+7).
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 20, 30, 0).unwrap();
+        let jakarta = DateContext::at(now, parse_time_zone("Asia/Jakarta").unwrap());
+        let utc = DateContext::at(now, Default::default());
+
+        assert_eq!(
+            utc.resolve("now-7d"),
+            Utc.with_ymd_and_hms(2024, 3, 8, 20, 30, 0).single()
+        );
+        assert_eq!(
+            jakarta.resolve("startOf:day"),
+            Utc.with_ymd_and_hms(2024, 3, 15, 17, 0, 0).single()
+        );
+        assert_eq!(
+            jakarta.resolve("startOf:month-1M"),
+            Utc.with_ymd_and_hms(2024, 1, 31, 17, 0, 0).single()
+        );
+        assert_eq!(
+            utc.resolve("startOf:week+1d"),
+            Utc.with_ymd_and_hms(2024, 3, 12, 0, 0, 0).single()
+        );
+        assert!(RelativeDate::parse("nowhere").is_none());
+        assert!(RelativeDate::parse("now-7").is_none());
+        assert!(parse_time_zone("Mars/Olympus").is_err());
+
+        let filter = Filter::new(
+            "created_at",
+            FilterOperator::Gte,
+            FilterValue::DateTime(utc.resolve("startOf:year").unwrap()),
+        );
+        assert_eq!(
+            filter.to_sql_where(),
+            "created_at >= '2024-01-01T00:00:00Z'"
+        );
+        assert_eq!(
+            filter.to_surrealql_where(),
+            "created_at >= <datetime> '2024-01-01T00:00:00Z'"
+        );
+    }
+
+    #[test]
+    fn test_filter_values_round_trip_and_only_relative_dates_resolve() {
+        use paginator_rs::chrono::{TimeZone, Utc};
+        use paginator_rs::{parse_filter_with, parse_time_zone, DateContext};
+
+        let at = Utc.with_ymd_and_hms(2024, 3, 15, 20, 30, 0).unwrap();
+        for value in [
+            FilterValue::DateTime(at),
+            FilterValue::String("nowhere".into()),
+            FilterValue::Int(7),
+        ] {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(serde_json::from_str::<FilterValue>(&json).unwrap(), value);
+        }
+
+        let dates = DateContext::at(at, parse_time_zone("Asia/Jakarta").unwrap());
+        for text in ["nowhere", "now-7days", "now -1d", "Now", "snow", "now+"] {
+            let filter = parse_filter_with(&format!("status:eq:{}", text), &dates).unwrap();
+            assert_eq!(filter.value, FilterValue::String(text.into()), "{}", text);
+        }
+        for (expr, expected) in [
+            ("now", at),
+            (
+                "now-7d",
+                Utc.with_ymd_and_hms(2024, 3, 8, 20, 30, 0).unwrap(),
+            ),
+            (
+                "now+1h-30m",
+                Utc.with_ymd_and_hms(2024, 3, 15, 21, 0, 0).unwrap(),
+            ),
+        ] {
+            let filter = parse_filter_with(&format!("created_at:gte:{}", expr), &dates).unwrap();
+            assert_eq!(filter.value, FilterValue::DateTime(expected), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_relative_dates_only_resolve_in_range_filters() {
+        use paginator_rs::{parse_query, PaginationConfig};
+
+        let params = parse_query(
+            [
+                ("filter", "name:eq:now"),
+                ("filter", "status:in:startOf:day,now-1d"),
+                ("filter", "created_at:lt:now"),
+                ("filter", "created_at:between:startOf:month,now"),
+                ("filter", "updated_at:eq:2024-03-15T20:30:00Z"),
+            ],
+            &PaginationConfig::default(),
+        )
+        .unwrap();
+        let values: Vec<&FilterValue> = params.filters.iter().map(|f| &f.value).collect();
+        assert_eq!(values[0], &FilterValue::String("now".into()));
+        assert_eq!(
+            values[1],
+            &FilterValue::Array(vec![
+                FilterValue::String("startOf:day".into()),
+                FilterValue::String("now-1d".into()),
+            ])
+        );
+        assert!(matches!(values[2], FilterValue::DateTime(_)));
+        assert!(matches!(
+            values[3].elements(),
+            [FilterValue::DateTime(_), FilterValue::DateTime(_)]
+        ));
+        assert!(matches!(values[4], FilterValue::DateTime(_)));
+        assert!(params.filters[0].date_source.is_none());
+        assert!(params.filters[2].date_source.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_text_stays_a_string_outside_comparisons() {
+        use paginator_rs::{parse_query, PaginationConfig};
+        use paginator_sea_orm::PaginateSeaOrm;
+        use sea_orm::{EntityTrait, SqlxSqliteConnector};
+
+        let stamp = "2024-03-15T20:30:00Z";
+        let parse = |filter: String| {
+            parse_query([("filter", filter.as_str())], &PaginationConfig::default()).unwrap()
+        };
+        for operator in ["like", "contains", "starts_with"] {
+            let params = parse(format!("name:{}:{}", operator, stamp));
+            assert_eq!(params.filters[0].value, FilterValue::String(stamp.into()));
+        }
+        let params = parse(format!("name:in:{},item-2", stamp));
+        assert_eq!(
+            params.filters[0].value,
+            FilterValue::Array(vec![
+                FilterValue::String(stamp.into()),
+                FilterValue::String("item-2".into()),
+            ])
+        );
+        let params = parse(format!("created_at:gte:{}", stamp));
+        assert!(matches!(params.filters[0].value, FilterValue::DateTime(_)));
+
+        // A text column holding a timestamp matches it as text.
+        let pool = sqlite_items(3).await;
+        sqlx::query("UPDATE items SET name = ? WHERE id = 1")
+            .bind(stamp)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let db = SqlxSqliteConnector::from_sqlx_sqlite_pool(pool.clone());
+        for filter in [
+            format!("name:starts_with:{}", stamp),
+            format!("name:like:{}%", stamp),
+            format!("name:in:{},item-2", stamp),
+        ] {
+            let params = parse(filter);
+            let page = paginator_sqlx::sqlite::paginate_query::<_, (i64, String)>(
+                &pool,
+                "SELECT id, name FROM items",
+                &params,
+            )
+            .await
+            .unwrap();
+            assert_eq!(page.data[0], (1, stamp.to_string()));
+            let page = item_entity::Entity::find()
+                .paginate_with(&db, &params)
+                .await
+                .unwrap();
+            assert_eq!(page.data[0].name, stamp);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_actix_extractor_resolves_dates_in_tz() {
+        use actix_web::{test, web, App};
+        use paginator_actix::{middleware::PaginationMiddleware, Pagination};
+
+        async fn handler(pagination: Pagination) -> String {
+            match &pagination.params.filters[0].value {
+                FilterValue::DateTime(at) => at.to_rfc3339(),
+                other => format!("{:?}", other),
+            }
+        }
+
+        let uri = "/?filter=created_at:gte:startOf:day&tz=Asia/Jakarta";
+        let plain = test::init_service(App::new().route("/", web::get().to(handler))).await;
+        let wrapped = test::init_service(
+            App::new()
+                .wrap(PaginationMiddleware::default())
+                .route("/", web::get().to(handler)),
+        )
+        .await;
+        for body in [
+            test::call_and_read_body(&plain, test::TestRequest::get().uri(uri).to_request()).await,
+            test::call_and_read_body(&wrapped, test::TestRequest::get().uri(uri).to_request())
+                .await,
+        ] {
+            let at: paginator_rs::chrono::DateTime<paginator_rs::chrono::Utc> =
+                std::str::from_utf8(&body).unwrap().parse().unwrap();
+            assert_eq!(at.format("%H:%M").to_string(), "17:00");
+        }
+    }
+
+    #[rocket::get("/dates")]
+    fn rocket_dates(pagination: paginator_rocket::Pagination) -> String {
+        match &pagination.params.filters[0].value {
+            FilterValue::DateTime(at) => at.to_rfc3339(),
+            other => format!("{:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rocket_guard_resolves_dates_in_tz() {
+        use rocket::local::asynchronous::Client;
+
+        let rocket = rocket::build().mount("/", rocket::routes![rocket_dates]);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client
+            .get("/dates?filter=created_at:gte:startOf:day&tz=Asia/Jakarta")
+            .dispatch()
+            .await;
+        let body = response.into_string().await.unwrap();
+        let at: paginator_rs::chrono::DateTime<paginator_rs::chrono::Utc> = body.parse().unwrap();
+        assert_eq!(at.format("%H:%M").to_string(), "17:00");
+    }
+
+    #[test]
+    fn test_json_field_paths() {
+        use paginator_rs::{FieldPath, PathSegment};
+
+        let path = FieldPath::parse("attrs[0].sku").unwrap();
+        assert_eq!(path.column(), "attrs");
+        assert_eq!(
+            path.segments(),
+            &[PathSegment::Index(0), PathSegment::Key("sku".into())]
+        );
+        assert_eq!(path.segment_texts(), vec!["0", "sku"]);
+        assert_eq!(path.json_path(), "$[0].\"sku\"");
+
+        let path = FieldPath::parse("attrs.shipping-class").unwrap();
+        assert_eq!(path.json_path(), "$.\"shipping-class\"");
+        assert!(FieldPath::parse("attrs").unwrap().segments().is_empty());
+
+        for invalid in [
+            "",
+            ".color",
+            "attrs.",
+            "attrs[x]",
+            "attrs[0",
+            "attrs.color'--",
+        ] {
+            assert!(FieldPath::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_paths_plan_per_dialect() {
+        use paginator_rs::BindValue;
+        use paginator_sqlx::{mysql, postgres, sqlite, PaginateOptions};
+        use sea_orm::{DbBackend, EntityTrait};
+
+        let params = PaginatorBuilder::new()
+            .per_page(10)
+            .filter_eq("attrs.color", FilterValue::String("red".into()))
+            .filter_gte("attrs[0].qty", FilterValue::Int(2))
+            .filter_like("attrs.sku", "A-%")
+            .build();
+        let base = "SELECT * FROM items";
+        let options = PaginateOptions::new().json_columns(["attrs"]);
+        let text = |s: &str| BindValue::String(s.into());
+        let paged = [BindValue::Int(10), BindValue::Int(0)];
+
+        let postgres_binds = [
+            vec![text("color"), text("red"), text("0"), text("qty")],
+            vec![BindValue::Int(2), text("sku"), text("A-%")],
+            paged.to_vec(),
+        ]
+        .concat();
+        let postgres_where = "jsonb_extract_path(attrs, $1) = to_jsonb($2) \
+             AND jsonb_extract_path(attrs, $3, $4) >= to_jsonb($5) \
+             AND jsonb_extract_path_text(attrs, $6) LIKE $7";
+        let plan = postgres::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            format!(
+                "SELECT * FROM (SELECT * FROM items) AS _base WHERE 1=1 AND {} \
+                 LIMIT $8 OFFSET $9",
+                postgres_where
+            )
+        );
+        assert_eq!(plan.data.binds, postgres_binds);
+
+        // MySQL and SQLite bind JSON path expressions instead of segments.
+        let json_binds = [
+            vec![text("$.\"color\""), text("red")],
+            vec![text("$[0].\"qty\""), BindValue::Int(2)],
+            vec![text("$.\"sku\""), text("A-%")],
+            paged.to_vec(),
+        ]
+        .concat();
+        let plan = mysql::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM (SELECT * FROM items) AS _base WHERE 1=1 \
+             AND JSON_EXTRACT(attrs, ?) = ? AND JSON_EXTRACT(attrs, ?) >= ? \
+             AND JSON_UNQUOTE(JSON_EXTRACT(attrs, ?)) LIKE ? LIMIT ? OFFSET ?"
+        );
+        assert_eq!(plan.data.binds, json_binds);
+        let plan = sqlite::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM (SELECT * FROM items) AS _base WHERE 1=1 \
+             AND json_extract(attrs, ?) = ? AND json_extract(attrs, ?) >= ? \
+             AND json_extract(attrs, ?) LIKE ? LIMIT ? OFFSET ?"
+        );
+        assert_eq!(plan.data.binds, json_binds);
+
+        let options = paginator_sea_orm::PaginateOptions::new().json_columns(["attrs"]);
+        let plan = |backend| {
+            paginator_sea_orm::plan_with_options(
+                item_entity::Entity::find(),
+                backend,
+                &params,
+                &options,
+            )
+            .unwrap()
+        };
+        let select = r#"SELECT "items"."id", "items"."name" FROM "items" WHERE "#;
+        let data = plan(DbBackend::Postgres).data;
+        assert_eq!(
+            data.sql,
+            format!(
+                "{}{} LIMIT $8 OFFSET $9",
+                select,
+                postgres_where.replace("(attrs", "(\"attrs\"")
+            )
+        );
+        assert_eq!(data.binds, postgres_binds);
+        let data = plan(DbBackend::MySql).data;
+        assert_eq!(
+            data.sql,
+            "SELECT `items`.`id`, `items`.`name` FROM `items` \
+             WHERE JSON_EXTRACT(`attrs`, ?) = ? AND JSON_EXTRACT(`attrs`, ?) >= ? \
+             AND JSON_UNQUOTE(JSON_EXTRACT(`attrs`, ?)) LIKE ? LIMIT ? OFFSET ?"
+        );
+        assert_eq!(data.binds, json_binds);
+        let data = plan(DbBackend::Sqlite).data;
+        assert_eq!(
+            data.sql,
+            format!(
+                "{}json_extract(\"attrs\", ?) = ? AND json_extract(\"attrs\", ?) >= ? \
+                 AND json_extract(\"attrs\", ?) LIKE ? LIMIT ? OFFSET ?",
+                select
+            )
+        );
+        assert_eq!(data.binds, json_binds);
+
+        // The SQLite statement runs against JSON text.
+        let pool = sqlite_items(0).await;
+        sqlx::query("ALTER TABLE items ADD COLUMN attrs TEXT")
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (id, attrs) in [
+            (1, r#"{"color": "red", "sku": "A-1", "0": 9}"#),
+            (2, r#"[{"qty": 3}]"#),
+            (3, r#"{"color": "blue", "sku": "A-3"}"#),
+        ] {
+            sqlx::query("INSERT INTO items (id, name, attrs) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(format!("item-{}", id))
+                .bind(attrs)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let options = PaginateOptions::new().json_columns(["attrs"]);
+        for (filter, expected) in [
+            (("attrs.color", FilterValue::String("red".into())), vec![1]),
+            (("attrs[0].qty", FilterValue::Int(2)), vec![2]),
+        ] {
+            let params = PaginatorBuilder::new()
+                .filter_gte(filter.0, filter.1)
+                .build();
+            let page = sqlite::paginate_query_with_options::<_, (i64, String, String)>(
+                &pool,
+                "SELECT id, name, attrs FROM items",
+                &params,
+                &options,
+            )
+            .await
+            .unwrap();
+            let ids: Vec<i64> = page.data.iter().map(|(id, _, _)| *id).collect();
+            assert_eq!(ids, expected, "{}", filter.0);
+        }
+    }
+
+    #[test]
+    fn test_array_operators() {
+        use paginator_rs::{FilterOperator, Paginator};
+
+        let params = Paginator::new()
+            .filter()
+            .contains_all(
+                "tags",
+                vec![
+                    FilterValue::String("rust".into()),
+                    FilterValue::String("sql".into()),
+                ],
+            )
+            .contains_any("tags", vec![FilterValue::Int(1)])
+            .length_gte("tags", 2)
+            .apply()
+            .build();
+
+        let [all, any, length] = params.filters.as_slice() else {
+            panic!("expected three filters");
+        };
+        assert_eq!(all.operator, FilterOperator::ContainsAll);
+        assert!(length.operator.is_array_operator());
+        assert_eq!(length.operator.length_comparison(), Some(">="));
+        assert_eq!(
+            FilterValue::String("solo".into()).elements(),
+            &[FilterValue::String("solo".into())]
+        );
+
+        assert_eq!(all.to_sql_where(), "tags @> ARRAY['rust', 'sql']");
+        assert_eq!(any.to_sql_where(), "tags && ARRAY[1]");
+        assert_eq!(length.to_sql_where(), "cardinality(tags) >= 2");
+        assert_eq!(all.to_surrealql_where(), "tags CONTAINSALL ['rust', 'sql']");
+        assert_eq!(any.to_surrealql_where(), "tags CONTAINSANY [1]");
+        assert_eq!(length.to_surrealql_where(), "array::len(tags) >= 2");
+    }
+
+    #[tokio::test]
+    async fn test_array_operators_plan_per_dialect() {
+        use paginator_rs::{BindValue, Paginator};
+        use paginator_sqlx::{mysql, postgres, sqlite, PaginateOptions};
+        use sea_orm::{DbBackend, EntityTrait};
+
+        let params = Paginator::new()
+            .per_page(10)
+            .filter()
+            .contains_all(
+                "tags",
+                vec![
+                    FilterValue::String("rust".into()),
+                    FilterValue::String("sql".into()),
+                ],
+            )
+            .contains_any(
+                "attrs.tags",
+                vec![FilterValue::String("a".into()), FilterValue::Int(1)],
+            )
+            .length_gte("tags", 2)
+            .length_lt("attrs.tags", 3)
+            .apply()
+            .build();
+        let base = "SELECT * FROM items";
+        let options = PaginateOptions::new().json_columns(["attrs"]);
+        let text = |s: &str| BindValue::String(s.into());
+        let postgres_binds = vec![
+            text("rust"),
+            text("sql"),
+            text("tags"),
+            text("a"),
+            text("tags"),
+            BindValue::Int(1),
+            BindValue::Int(2),
+            text("tags"),
+            BindValue::Int(3),
+            BindValue::Int(10),
+            BindValue::Int(0),
+        ];
+        let json_binds = vec![
+            text("rust"),
+            text("sql"),
+            text("$.\"tags\""),
+            text("a"),
+            BindValue::Int(1),
+            BindValue::Int(2),
+            text("$.\"tags\""),
+            BindValue::Int(3),
+            BindValue::Int(10),
+            BindValue::Int(0),
+        ];
+
+        let plan = postgres::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM (SELECT * FROM items) AS _base WHERE 1=1 \
+             AND ($1 = ANY(tags) AND $2 = ANY(tags)) \
+             AND (jsonb_extract_path(attrs, $3) @> jsonb_build_array($4) \
+             OR jsonb_extract_path(attrs, $5) @> jsonb_build_array($6)) \
+             AND cardinality(tags) >= $7 \
+             AND jsonb_array_length(jsonb_extract_path(attrs, $8)) < $9 LIMIT $10 OFFSET $11"
+        );
+        assert_eq!(plan.data.binds, postgres_binds);
+        let plan = mysql::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM (SELECT * FROM items) AS _base WHERE 1=1 \
+             AND JSON_CONTAINS(tags, JSON_ARRAY(?, ?)) \
+             AND JSON_OVERLAPS(JSON_EXTRACT(attrs, ?), JSON_ARRAY(?, ?)) \
+             AND JSON_LENGTH(tags) >= ? AND JSON_LENGTH(JSON_EXTRACT(attrs, ?)) < ? \
+             LIMIT ? OFFSET ?"
+        );
+        assert_eq!(plan.data.binds, json_binds);
+        let plan = sqlite::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM (SELECT * FROM items) AS _base WHERE 1=1 \
+             AND (EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?) \
+             AND EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?)) \
+             AND EXISTS (SELECT 1 FROM json_each(json_extract(attrs, ?)) WHERE value IN (?, ?)) \
+             AND json_array_length(tags) >= ? AND json_array_length(json_extract(attrs, ?)) < ? \
+             LIMIT ? OFFSET ?"
+        );
+        assert_eq!(plan.data.binds, json_binds);
+
+        let options = paginator_sea_orm::PaginateOptions::new().json_columns(["attrs"]);
+        let plan = |backend| {
+            paginator_sea_orm::plan_with_options(
+                item_entity::Entity::find(),
+                backend,
+                &params,
+                &options,
+            )
+            .unwrap()
+        };
+        let data = plan(DbBackend::Postgres).data;
+        assert_eq!(
+            data.sql,
+            r#"SELECT "items"."id", "items"."name" FROM "items" WHERE $1 = ANY("tags") AND $2 = ANY("tags") AND ((jsonb_extract_path("attrs", $3) @> jsonb_build_array($4)) OR (jsonb_extract_path("attrs", $5) @> jsonb_build_array($6))) AND (cardinality("tags") >= $7) AND (jsonb_array_length(jsonb_extract_path("attrs", $8)) < $9) LIMIT $10 OFFSET $11"#
+        );
+        assert_eq!(data.binds, postgres_binds);
+        let data = plan(DbBackend::MySql).data;
+        assert_eq!(
+            data.sql,
+            "SELECT `items`.`id`, `items`.`name` FROM `items` \
+             WHERE (JSON_CONTAINS(`tags`, JSON_ARRAY(?, ?))) \
+             AND (JSON_OVERLAPS(JSON_EXTRACT(`attrs`, ?), JSON_ARRAY(?, ?))) \
+             AND (JSON_LENGTH(`tags`) >= ?) AND (JSON_LENGTH(JSON_EXTRACT(`attrs`, ?)) < ?) \
+             LIMIT ? OFFSET ?"
+        );
+        assert_eq!(data.binds, json_binds);
+        let data = plan(DbBackend::Sqlite).data;
+        assert_eq!(
+            data.sql,
+            r#"SELECT "items"."id", "items"."name" FROM "items" WHERE (EXISTS (SELECT 1 FROM json_each("tags") WHERE value = ?)) AND (EXISTS (SELECT 1 FROM json_each("tags") WHERE value = ?)) AND (EXISTS (SELECT 1 FROM json_each(json_extract("attrs", ?)) WHERE value IN (?, ?))) AND (json_array_length("tags") >= ?) AND (json_array_length(json_extract("attrs", ?)) < ?) LIMIT ? OFFSET ?"#
+        );
+        assert_eq!(data.binds, json_binds);
+
+        // The SQLite statement runs against JSON arrays.
+        let pool = sqlite_items(0).await;
+        sqlx::query("ALTER TABLE items ADD COLUMN tags TEXT")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("ALTER TABLE items ADD COLUMN attrs TEXT")
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (id, tags, attrs) in [
+            (1, r#"["rust", "sql"]"#, r#"{"tags": ["a"]}"#),
+            (
+                2,
+                r#"["rust", "sql", "web"]"#,
+                r#"{"tags": ["b", "c", "d"]}"#,
+            ),
+            (3, r#"["rust"]"#, r#"{"tags": [1]}"#),
+            (4, r#"["sql", "rust"]"#, r#"{"tags": [1, "b"]}"#),
+        ] {
+            sqlx::query("INSERT INTO items (id, name, tags, attrs) VALUES (?, ?, ?, ?)")
+                .bind(id)
+                .bind(format!("item-{}", id))
+                .bind(tags)
+                .bind(attrs)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let page = sqlite::paginate_query_with_options::<_, (i64, String)>(
+            &pool,
+            "SELECT id, name, tags, attrs FROM items",
+            &params,
+            &PaginateOptions::new().json_columns(["attrs"]),
+        )
+        .await
+        .unwrap();
+        let ids: Vec<i64> = page.data.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![1, 4]);
+    }
+
+    #[test]
+    fn test_string_match_operators_escape_like_patterns() {
+        use paginator_rs::{escape_like, Paginator};
+
+        assert_eq!(escape_like("50%_off!"), "50!%!_off!!");
+
+        let params = Paginator::new()
+            .filter()
+            .starts_with("code", "50%")
+            .ends_with("email", "_x")
+            .ieq("name", "John")
+            .regex("sku", "^AB-[0-9]+$")
+            .apply()
+            .build();
+
+        let [starts, ends, ieq, regex] = params.filters.as_slice() else {
+            panic!("expected four filters");
+        };
+        assert_eq!(starts.like_pattern().as_deref(), Some("50!%%"));
+        assert_eq!(ends.like_pattern().as_deref(), Some("%!_x"));
+        assert_eq!(ieq.like_pattern(), None);
+
+        assert_eq!(starts.to_sql_where(), "code LIKE '50!%%' ESCAPE '!'");
+        assert_eq!(ieq.to_sql_where(), "LOWER(name) = LOWER('John')");
+        assert_eq!(regex.to_sql_where(), "sku ~ '^AB-[0-9]+$'");
+        assert_eq!(ends.to_surrealql_where(), "string::ends_with(email, '_x')");
+        assert_eq!(
+            regex.to_surrealql_where(),
+            "string::matches(sku, <regex> '^AB-[0-9]+$')"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_string_match_operators_plan_per_dialect() {
+        use paginator_rs::{escape_surrealql, BindValue, Paginator};
+        use paginator_sqlx::{mysql, postgres, sqlite};
+        use sea_orm::{DbBackend, EntityTrait};
+
+        let params = Paginator::new()
+            .per_page(10)
+            .filter()
+            .starts_with("code", "50%")
+            .ends_with("name", r"it's \")
+            .ieq("name", r"O\'Neil")
+            .regex("sku", r"^b\d+$")
+            .apply()
+            .build();
+        let base = "SELECT * FROM items";
+        let binds = vec![
+            BindValue::String("50!%%".into()),
+            BindValue::String(r"%it's \".into()),
+            BindValue::String(r"O\'Neil".into()),
+            BindValue::String(r"^b\d+$".into()),
+            BindValue::Int(10),
+            BindValue::Int(0),
+        ];
+
+        let plan = postgres::plan(base, &params).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM (SELECT * FROM items) AS _base WHERE 1=1 \
+             AND code LIKE $1 ESCAPE '!' AND name LIKE $2 ESCAPE '!' \
+             AND LOWER(name) = LOWER($3) AND sku ~ $4 LIMIT $5 OFFSET $6"
+        );
+        assert_eq!(plan.data.binds, binds);
+        for plan in [
+            mysql::plan(base, &params).unwrap(),
+            sqlite::plan(base, &params).unwrap(),
+        ] {
+            assert_eq!(
+                plan.data.sql,
+                "SELECT * FROM (SELECT * FROM items) AS _base WHERE 1=1 \
+                 AND code LIKE ? ESCAPE '!' AND name LIKE ? ESCAPE '!' \
+                 AND LOWER(name) = LOWER(?) AND sku REGEXP ? LIMIT ? OFFSET ?"
+            );
+            assert_eq!(plan.data.binds, binds);
+        }
+
+        let plan = |backend| {
+            paginator_sea_orm::plan(item_entity::Entity::find(), backend, &params).unwrap()
+        };
+        let data = plan(DbBackend::Postgres).data;
+        assert_eq!(
+            data.sql,
+            r#"SELECT "items"."id", "items"."name" FROM "items" WHERE "code" LIKE $1 ESCAPE '!' AND "name" LIKE $2 ESCAPE '!' AND LOWER("name") = LOWER($3) AND ("sku" ~ $4) LIMIT $5 OFFSET $6"#
+        );
+        assert_eq!(data.binds, binds);
+        let data = plan(DbBackend::MySql).data;
+        assert_eq!(
+            data.sql,
+            "SELECT `items`.`id`, `items`.`name` FROM `items` WHERE `code` LIKE ? ESCAPE '!' \
+             AND `name` LIKE ? ESCAPE '!' AND LOWER(`name`) = LOWER(?) AND (`sku` REGEXP ?) \
+             LIMIT ? OFFSET ?"
+        );
+        assert_eq!(data.binds, binds);
+        let data = plan(DbBackend::Sqlite).data;
+        assert_eq!(
+            data.sql,
+            r#"SELECT "items"."id", "items"."name" FROM "items" WHERE "code" LIKE ? ESCAPE '!' AND "name" LIKE ? ESCAPE '!' AND LOWER("name") = LOWER(?) AND ("sku" REGEXP ?) LIMIT ? OFFSET ?"#
+        );
+        assert_eq!(data.binds, binds);
+
+        // SurrealQL inlines the values with backslash escapes, so neither a
+        // trailing `\` nor `\'` ends the string early.
+        assert_eq!(escape_surrealql(r"it's \"), r"it\'s \\");
+        assert_eq!(escape_surrealql(r"O\'Neil"), r"O\\\'Neil");
+        let plan = paginator_surrealdb::plan("SELECT * FROM items", &params).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            r"SELECT * FROM items WHERE string::starts_with(code, '50%') AND string::ends_with(name, 'it\'s \\') AND string::lowercase(name) = string::lowercase('O\\\'Neil') AND string::matches(sku, <regex> '^b\\d+$') LIMIT 10 START 0"
+        );
+
+        // The SQLite statement matches the values literally.
+        let pool = sqlite_items(0).await;
+        sqlx::query("ALTER TABLE items ADD COLUMN code TEXT")
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (id, name, code) in [
+            (1, r"it's \", "50%"),
+            (2, r"O\'Neil", "50% off"),
+            (3, r"o\'neil", "500"),
+            (4, r"it's", "50%x"),
+        ] {
+            sqlx::query("INSERT INTO items (id, name, code) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(name)
+                .bind(code)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        for (params, expected) in [
+            (
+                Paginator::new().filter().starts_with("code", "50%").apply(),
+                vec![1, 2, 4],
+            ),
+            (
+                Paginator::new()
+                    .filter()
+                    .ends_with("name", r"it's \")
+                    .apply(),
+                vec![1],
+            ),
+            (
+                Paginator::new().filter().ieq("name", r"O\'NEIL").apply(),
+                vec![2, 3],
+            ),
+        ] {
+            let page = sqlite::paginate_query::<_, (i64, String)>(
+                &pool,
+                "SELECT id, name, code FROM items",
+                &params.sort().asc("id").build(),
+            )
+            .await
+            .unwrap();
+            let ids: Vec<i64> = page.data.iter().map(|(id, _)| *id).collect();
+            assert_eq!(ids, expected);
+        }
+    }
+
+    #[test]
+    fn test_geo_filters_and_distance_sort() {
+        use paginator_rs::{
+            DistanceSort, FilterOperator, GeoBox, GeoPoint, Paginator, DISTANCE_COLUMN,
+        };
+
+        let origin = GeoPoint::new(13.4, 52.52);
+        let params = Paginator::new()
+            .filter()
+            .within_radius("location", origin, 5000.0)
+            .within_box(
+                "location",
+                GeoBox::new(GeoPoint::new(13.0, 52.0), GeoPoint::new(14.0, 53.0)),
+            )
+            .apply()
+            .sort()
+            .nearest("location", origin)
+            .build();
+
+        assert_eq!(params.sort_by.as_deref(), Some(DISTANCE_COLUMN));
+        assert_eq!(
+            params.distance_sort,
+            Some(DistanceSort::new("location", origin))
+        );
+
+        let [radius, bbox] = params.filters.as_slice() else {
+            panic!("expected two filters");
+        };
+        assert!(bbox.operator.is_geo_operator());
+        assert_eq!(radius.radius(), Some((origin, 5000.0)));
+        assert_eq!(
+            radius.to_sql_where(),
+            "ST_DWithin(location::geography, ST_GeogFromText('POINT(13.4 52.52)'), 5000)"
+        );
+        assert_eq!(
+            bbox.to_sql_where(),
+            "ST_Within(location::geometry, ST_GeomFromText('POLYGON((13 52, 14 52, 14 53, 13 53, 13 52))', 4326))"
+        );
+        assert_eq!(
+            radius.to_surrealql_where(),
+            "geo::distance(location, (13.4, 52.52)) <= 5000"
+        );
+
+        // Polygons travel as arrays of points and are closed on rendering.
+        let json = r#"{"field":"area","operator":"withinpolygon","value":[{"lon":0,"lat":0},{"lon":1,"lat":0},{"lon":1,"lat":1}]}"#;
+        let polygon: paginator_rs::Filter = serde_json::from_str(json).unwrap();
+        assert_eq!(polygon.operator, FilterOperator::WithinPolygon);
+        assert_eq!(
+            polygon.to_surrealql_where(),
+            "area INSIDE { type: 'Polygon', coordinates: [[[0, 0], [1, 0], [1, 1], [0, 0]]] }"
+        );
+    }
+
+    #[test]
+    fn test_problem_details_from_errors() {
+        use paginator_rs::{
+            ErrorCode, PaginatorError, ProblemDetails, ResponseFormat, ValidationError,
+        };
+
+        let error: PaginatorError =
+            ValidationError::new(ErrorCode::InvalidSort, "Cannot sort by 'password'")
+                .parameter("sort_by")
+                .allowed(["name", "created_at"])
+                .into();
+        assert_eq!(error.status(), 400);
+        assert_eq!(
+            serde_json::to_value(ProblemDetails::from(&error)).unwrap(),
+            json!({
+                "type": "about:blank",
+                "title": "Bad Request",
+                "status": 400,
+                "detail": "Cannot sort by 'password'",
+                "code": "invalid_sort",
+                "parameter": "sort_by",
+                "allowed": ["name", "created_at"],
+            })
+        );
+
+        let problem = ProblemDetails::from(&PaginatorError::InvalidPerPage(500));
+        assert_eq!(problem.code, ErrorCode::InvalidPerPage);
+        assert_eq!(problem.parameter.as_deref(), Some("per_page"));
+
+        let problem = ProblemDetails::from(&ResponseFormat::not_acceptable());
+        assert_eq!(problem.status, 406);
+        assert_eq!(problem.parameter.as_deref(), Some("Accept"));
+        assert!(problem.allowed.contains(&"text/csv".to_string()));
+
+        // Server errors don't leak their message.
+        let problem = ProblemDetails::from(&PaginatorError::Custom(
+            "relation \"users\" does not exist".to_string(),
+        ));
+        assert_eq!(problem.status, 500);
+        assert_eq!(problem.code, ErrorCode::Internal);
+        assert_eq!(problem.detail, "The request could not be completed");
+    }
+
+    #[test]
+    fn test_strict_parse_mode_rejects_what_lenient_drops() {
+        use paginator_rs::{ErrorCode, PaginatorError, ParseMode, SortDirection};
+
+        let lenient = ParseMode::default();
+        assert_eq!(lenient.page(0).unwrap(), 1);
+        assert_eq!(lenient.per_page(500).unwrap(), 100);
+        assert_eq!(lenient.sort_direction("up").unwrap(), None);
+        assert!(lenient.check_keys(["pgae"], &["page"]).is_ok());
+
+        let strict = ParseMode::Strict;
+        assert_eq!(
+            strict.sort_direction("DESC").unwrap(),
+            Some(SortDirection::Desc)
+        );
+        assert!(matches!(
+            strict.page(0),
+            Err(PaginatorError::InvalidPage(0))
+        ));
+        assert!(matches!(
+            strict.per_page(500),
+            Err(PaginatorError::InvalidPerPage(500))
+        ));
+
+        let Err(PaginatorError::Validation(error)) = strict.sort_direction("up") else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(error.code, ErrorCode::InvalidSort);
+        assert_eq!(error.parameter.as_deref(), Some("sort_direction"));
+        assert_eq!(error.allowed, ["asc", "desc"]);
+
+        let Err(PaginatorError::Validation(error)) =
+            strict.check_keys(["page", "pgae"], &["page", "per_page"])
+        else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(error.code, ErrorCode::InvalidParameter);
+        assert_eq!(error.parameter.as_deref(), Some("pgae"));
+        assert_eq!(error.allowed, ["page", "per_page"]);
+    }
+
+    #[test]
+    fn test_parse_query_and_page_links() {
+        use paginator_rs::{
+            link_header, page_links, parse_query, CountStrategy, Cursor, CursorDirection,
+            CursorValue, PaginationConfig, PaginatorResponseMeta, ParseMode,
+        };
+
+        let cursor = Cursor::new("id".into(), CursorValue::Int(40), CursorDirection::After)
+            .encode()
+            .unwrap();
+        let pairs = [
+            ("page", "2"),
+            ("filter", "status:eq:active"),
+            ("filter", "age:gt"),
+            ("search", "jo"),
+            ("search_fields", "name,email"),
+            ("cursor", cursor.as_str()),
+            ("count", "capped:1000"),
+        ];
+        let config = PaginationConfig::new().default_per_page(10);
+        let params = parse_query(pairs, &config).unwrap();
+
+        assert_eq!((params.page, params.per_page), (2, 10));
+        assert_eq!(params.filters.len(), 1);
+        assert_eq!(params.search.unwrap().fields, ["name", "email"]);
+        assert_eq!(params.cursor.unwrap().value, CursorValue::Int(40));
+        assert_eq!(params.count_strategy, CountStrategy::Capped(1000));
+
+        let strict = config.mode(ParseMode::Strict);
+        let error = parse_query(pairs, &strict).unwrap_err();
+        let problem = paginator_rs::ProblemDetails::from(&error);
+        assert_eq!(problem.parameter.as_deref(), Some("filter[1]"));
+        assert!(
+            parse_query([("count", "none")], &strict)
+                .unwrap()
+                .disable_total_count
+        );
+
+        let mut meta = PaginatorResponseMeta::new(2, 10, 45);
+        let links = page_links("/users", Some("page=2&per_page=10&sort_by=name"), &meta);
+        assert_eq!(
+            link_header(&links),
+            "</users?page=1&per_page=10&sort_by=name>; rel=\"first\", \
+             </users?page=1&per_page=10&sort_by=name>; rel=\"prev\", \
+             </users?page=3&per_page=10&sort_by=name>; rel=\"next\", \
+             </users?page=5&per_page=10&sort_by=name>; rel=\"last\""
+        );
+
+        // Cursors replace the page in prev/next and are percent-encoded.
+        meta.next_cursor = Some("ab+/=".to_string());
+        let links = page_links("/users", None, &meta);
+        assert_eq!(links[2].rel, "next");
+        assert_eq!(links[2].href, "/users?cursor=ab%2B%2F%3D&per_page=10");
+    }
+
+    #[test]
+    fn test_pagination_config_limits_per_page() {
+        use paginator_rs::{parse_query, ErrorCode, PaginationConfig, ParseMode};
+
+        let config = PaginationConfig::new()
+            .default_per_page(80)
+            .max_per_page(50);
+        let params = parse_query([], &config).unwrap();
+        assert_eq!(params.per_page, 50);
+        let params = parse_query([("per_page", "80")], &config).unwrap();
+        assert_eq!(params.per_page, 50);
+
+        let strict = config.mode(ParseMode::Strict);
+        assert_eq!(
+            parse_query([("per_page", "50")], &strict).unwrap().per_page,
+            50
+        );
+        let error = parse_query([("per_page", "80")], &strict).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidPerPage);
+        assert_eq!(
+            error.to_string(),
+            "Invalid per_page value: 80. Must be between 1 and 50"
+        );
+    }
+
+    #[test]
+    fn test_tower_layer_parses_and_decorates() {
+        use http::{Request, Response};
+        use paginator_rs::{PaginationConfig, PaginatorResponseMeta, ParseMode, ProblemDetails};
+        use paginator_tower::PaginationLayer;
+        use tower::{service_fn, Layer, ServiceExt};
+
+        let layer = PaginationLayer::new(PaginationConfig::new().mode(ParseMode::Strict));
+        let service = layer.layer(service_fn(|req: Request<()>| async move {
+            let mut response = Response::new(Vec::<u8>::new());
+            if let Some(params) = req.extensions().get::<PaginationParams>() {
+                response.extensions_mut().insert(PaginatorResponseMeta::new(
+                    params.page,
+                    params.per_page,
+                    45,
+                ));
+            }
+            if let Some(problem) = req.extensions().get::<ProblemDetails>() {
+                *response.body_mut() = problem.to_json();
+            }
+            Ok::<_, std::convert::Infallible>(response)
+        }));
+
+        let request = Request::get("/users?page=2&per_page=20").body(()).unwrap();
+        let response = futures::executor::block_on(service.clone().oneshot(request)).unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["x-total-count"], "45");
+        assert_eq!(
+            response.headers()["link"],
+            "</users?page=1&per_page=20>; rel=\"first\", \
+             </users?page=1&per_page=20>; rel=\"prev\", \
+             </users?page=3&per_page=20>; rel=\"next\", \
+             </users?page=3&per_page=20>; rel=\"last\""
+        );
+
+        // A rejected query reaches the inner service with its problem.
+        let request = Request::get("/users?page=2&pgae=2").body(()).unwrap();
+        let response = futures::executor::block_on(service.clone().oneshot(request)).unwrap();
+        assert!(response.headers().get("x-total-count").is_none());
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["parameter"], "pgae");
+
+        // Queries without pagination keys are not parsed at all.
+        let request = Request::get("/users?pgae=2").body(()).unwrap();
+        let response = futures::executor::block_on(service.oneshot(request)).unwrap();
+        assert!(response.headers().get("x-total-count").is_none());
+        assert!(response.body().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tower_layer_passes_through_routes_without_pagination() {
+        use axum::{
+            body::Body,
+            extract::Query,
+            http::{Request, StatusCode},
+            routing::get,
+            Router,
+        };
+        use paginator_axum::PaginationQuery;
+        use paginator_rs::{PaginationConfig, ParseMode};
+        use paginator_tower::PaginationLayer;
+        use std::collections::HashMap;
+        use tower::ServiceExt;
+
+        let app =
+            Router::new()
+                .route(
+                    "/search",
+                    get(|Query(query): Query<HashMap<String, String>>| async move {
+                        query["q"].clone()
+                    }),
+                )
+                .route(
+                    "/users",
+                    get(|PaginationQuery(params): PaginationQuery| async move {
+                        params.page.to_string()
+                    }),
+                )
+                .layer(PaginationLayer::new(
+                    PaginationConfig::new().mode(ParseMode::Strict),
+                ));
+
+        let mut outcomes = Vec::new();
+        for uri in ["/search?q=alice", "/users?page=2", "/users?page=2&q=alice"] {
+            let response = app
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            outcomes.push((status, body));
+        }
+        assert_eq!(outcomes[0], (StatusCode::OK, "alice".into()));
+        assert_eq!(outcomes[1], (StatusCode::OK, "2".into()));
+        assert_eq!(outcomes[2].0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_tower_layer_mode_applies_to_queries_it_skips() {
+        use axum::{
+            body::Body,
+            http::{Request, StatusCode},
+            routing::get,
+            Router,
+        };
+        use paginator_axum::PaginationQuery;
+        use paginator_rs::{PaginationConfig, ParseMode};
+        use paginator_tower::PaginationLayer;
+        use tower::ServiceExt;
+
+        let route = || {
+            Router::new().route(
+                "/users",
+                get(|PaginationQuery(params): PaginationQuery| async move {
+                    params.page.to_string()
+                }),
+            )
+        };
+        let strict = route().layer(PaginationLayer::new(
+            PaginationConfig::new().mode(ParseMode::Strict),
+        ));
+        let lenient = route().layer(PaginationLayer::new(PaginationConfig::new()));
+
+        let typo = || Request::get("/users?pgae=2").body(Body::empty()).unwrap();
+        let response = strict.oneshot(typo()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = lenient.oneshot(typo()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = route().oneshot(typo()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_response_links_from_params() {
+        use paginator_rs::{
+            parse_query, query_pairs, response_links, Cursor, CursorDirection, CursorValue,
+            PaginationConfig, PaginatorResponse, PaginatorResponseMeta,
+        };
+
+        let pairs = [
+            ("page", "2"),
+            ("per_page", "10"),
+            ("sort_by", "name"),
+            ("filter", "status:in:active,a&b"),
+            ("filter", "deleted_at:is_null"),
+            ("filter", "created_at:gte:2024-01-01T00:00:00Z"),
+            ("search", "al x"),
+            ("search_fields", "name,email"),
+            ("count", "none"),
+        ];
+        let params = parse_query(pairs, &PaginationConfig::new()).unwrap();
+        let written = query_pairs(&params);
+        let reparsed = parse_query(
+            written.iter().map(|(key, value)| (*key, value.as_str())),
+            &PaginationConfig::new(),
+        )
+        .unwrap();
+        assert_eq!(reparsed.filters, params.filters);
+        assert_eq!(reparsed.search, params.search);
+        assert!(reparsed.disable_total_count);
+        assert!(matches!(params.filters[2].value, FilterValue::DateTime(_)));
+
+        let meta = PaginatorResponseMeta::new(2, 10, 25);
+        let links = response_links("/users", &params, &meta);
+        let rest = "sort_by=name&filter=status:in:active,a%26b&filter=deleted_at:is_null\
+                    &filter=created_at:gte:2024-01-01T00:00:00Z&search=al%20x\
+                    &search_fields=name,email&count=none";
+        assert_eq!(
+            links.self_link,
+            format!("/users?page=2&per_page=10&{}", rest)
+        );
+        assert_eq!(
+            links.prev,
+            Some(format!("/users?page=1&per_page=10&{}", rest))
+        );
+        assert_eq!(
+            links.last,
+            Some(format!("/users?page=3&per_page=10&{}", rest))
+        );
+
+        // A cursor request links to itself by cursor, and cursors page.
+        let cursor = Cursor::new(
+            "id".to_string(),
+            CursorValue::Int(5),
+            CursorDirection::After,
+        );
+        let params = PaginationParams {
+            cursor: Some(cursor.clone()),
+            ..PaginationParams::new(1, 10)
+        };
+        let mut meta = PaginatorResponseMeta::new_without_total(1, 10, true);
+        meta.next_cursor = Some("next+/=".to_string());
+        let response = PaginatorResponse {
+            data: vec![1],
+            meta: meta.clone(),
+            links: None,
+        }
+        .with_links(response_links("/users", &params, &meta));
+        let links = response.links.as_ref().unwrap();
+        assert!(links.self_link.starts_with("/users?cursor="));
+        assert_eq!(
+            links.next.as_deref(),
+            Some("/users?cursor=next%2B%2F%3D&per_page=10")
+        );
+        assert_eq!(links.last, None);
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["links"]["first"], "/users?page=1&per_page=10");
+        assert!(json["links"]["self"].is_string());
+        assert!(json["links"].get("prev").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_client_follows_pages_from_axum() {
+        use axum::{http::StatusCode, response::IntoResponse, routing::get, Router};
+        use futures::TryStreamExt;
+        use paginator_axum::{PaginatedJson, PaginationQuery, StrictPaginationQuery};
+        use paginator_client::{PaginatorClient, RetryPolicy};
+        use paginator_rs::{ErrorCode, PaginationConfig, PaginatorError};
+        use paginator_tower::PaginationLayer;
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        fn page_of(params: &PaginationParams) -> Vec<u32> {
+            let start = (params.page - 1) * params.per_page + 1;
+            (start..=23).take(params.per_page as usize).collect()
+        }
+
+        let flaky_calls = Arc::new(AtomicU32::new(0));
+        let calls = flaky_calls.clone();
+        let app = Router::new()
+            .route(
+                "/users",
+                get(|PaginationQuery(params): PaginationQuery| async move {
+                    assert_eq!(params.sort_by.as_deref(), Some("id"));
+                    PaginatedJson::new(page_of(&params), &params, 23)
+                })
+                .layer(PaginationLayer::new(PaginationConfig::new())),
+            )
+            .route(
+                "/flaky",
+                get(move |PaginationQuery(params): PaginationQuery| async move {
+                    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return (StatusCode::SERVICE_UNAVAILABLE, [("retry-after", "0")])
+                            .into_response();
+                    }
+                    PaginatedJson::new(page_of(&params), &params, 23).into_response()
+                }),
+            )
+            .route(
+                "/strict",
+                get(
+                    |StrictPaginationQuery(params): StrictPaginationQuery| async move {
+                        PaginatedJson::new(page_of(&params), &params, 23)
+                    },
+                ),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // Link headers from the layer lead through all three pages.
+        let client = PaginatorClient::new();
+        let params = PaginatorBuilder::new().per_page(10).sort_by("id").build();
+        let rows: Vec<u32> = client
+            .stream::<u32>(&format!("{}/users", base), &params)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(rows, (1..=23).collect::<Vec<_>>());
+
+        // Page numbers alone are followed too, and a 503 is retried.
+        let requests = Arc::new(AtomicU32::new(0));
+        let retries = Arc::new(AtomicU32::new(0));
+        let (counted, retried) = (requests.clone(), retries.clone());
+        let client = PaginatorClient::new()
+            .retry(RetryPolicy::new().backoff(Duration::from_millis(1)))
+            .rate_limit(move || {
+                counted.fetch_add(1, Ordering::SeqCst);
+                async {}
+            })
+            .on_retry(move |attempt, delay, _| {
+                assert_eq!((attempt, delay), (1, Duration::ZERO));
+                retried.fetch_add(1, Ordering::SeqCst);
+            });
+        let pages: Vec<_> = client
+            .pages::<u32>(&format!("{}/flaky", base), &PaginationParams::new(1, 10))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(pages.len(), 3);
+        assert!(pages[2].next.is_none());
+        assert_eq!(retries.load(Ordering::SeqCst), 1);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+        // Problems come back as the errors the server reported.
+        let error = client
+            .fetch::<u32>(
+                &format!("{}/strict", base),
+                &PaginationParams {
+                    per_page: 500,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PaginatorError::Validation(_)));
+        assert_eq!(error.code(), ErrorCode::InvalidPerPage);
+    }
+
+    #[tokio::test]
+    async fn test_paginated_source_swaps_backends() {
+        use async_trait::async_trait;
+        use paginator_rs::{
+            Cursor, CursorDirection, CursorValue, ErrorCode, PaginatedSource, PaginatorResponse,
+            PaginatorResult,
+        };
+        use std::sync::Arc;
+
+        struct Evens;
+
+        #[async_trait]
+        impl PaginatedSource<u32> for Evens {
+            async fn fetch_page(
+                &self,
+                params: &PaginationParams,
+            ) -> PaginatorResult<PaginatorResponse<u32>> {
+                (0..10)
+                    .map(|n| n * 2)
+                    .collect::<Vec<_>>()
+                    .fetch_page(params)
+                    .await
+            }
+        }
+
+        async fn second_page(source: &dyn PaginatedSource<u32>) -> Vec<u32> {
+            source
+                .fetch_page(&PaginationParams::new(2, 3))
+                .await
+                .unwrap()
+                .data
+        }
+
+        let mock: Vec<u32> = (1..=7).collect();
+        assert_eq!(second_page(&mock).await, vec![4, 5, 6]);
+        let page = mock.fetch_page(&PaginationParams::new(3, 3)).await.unwrap();
+        assert_eq!(page.data, vec![7]);
+        assert_eq!(page.meta.total, Some(7));
+        assert!(!page.meta.has_next);
+
+        let shared: Arc<dyn PaginatedSource<u32>> = Arc::new(Evens);
+        assert_eq!(second_page(&shared).await, vec![6, 8, 10]);
+
+        let params = PaginationParams {
+            page: 0,
+            ..Default::default()
+        };
+        assert!(mock.fetch_page(&params).await.is_err());
+
+        // Params a Vec cannot apply are rejected instead of ignored.
+        let params = PaginatorBuilder::new()
+            .filter_gt("id", FilterValue::Int(3))
+            .build();
+        let error = mock.fetch_page(&params).await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidFilter);
+        let params = PaginatorBuilder::new().sort_by("id").build();
+        let error = mock.fetch_page(&params).await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidSort);
+        let mut params = PaginationParams::new(1, 3);
+        params.cursor = Some(Cursor::new(
+            "id".to_string(),
+            CursorValue::Int(3),
+            CursorDirection::After,
+        ));
+        let error = mock.fetch_page(&params).await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::InvalidCursor);
+    }
+
+    /// An in-memory SQLite pool with an `items` table of `rows` rows, named
+    /// `item-1`, `item-2`, ...
+    async fn sqlite_items(rows: i64) -> sqlx::SqlitePool {
+        // One connection, so every query sees the same in-memory database.
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        for id in 1..=rows {
+            sqlx::query("INSERT INTO items (id, name) VALUES (?, ?)")
+                .bind(id)
+                .bind(format!("item-{}", id))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_window_count_and_concurrent_totals() {
+        use paginator_sqlx::sqlite::paginate_query_with_options;
+        use paginator_sqlx::PaginateOptions;
+
+        let pool = sqlite_items(25).await;
+        let base = "SELECT id, name FROM items";
+        let params = PaginatorBuilder::new()
+            .page(2)
+            .per_page(10)
+            .sort_by("id")
+            .build();
+
+        for options in [
+            PaginateOptions::new(),
+            PaginateOptions::new().window_count(true),
+            PaginateOptions::new().concurrent(true),
+        ] {
+            let page =
+                paginate_query_with_options::<_, (i64, String)>(&pool, base, &params, &options)
+                    .await
+                    .unwrap();
+            assert_eq!(page.meta.total, Some(25));
+            assert_eq!(page.data.first(), Some(&(11, "item-11".to_string())));
+            assert_eq!(page.data.len(), 10);
+        }
+
+        // A window page past the end has no rows to carry the total, so it
+        // falls back to a COUNT.
+        let past_end = PaginatorBuilder::new().page(5).per_page(10).build();
+        let options = PaginateOptions::new().window_count(true);
+        let page =
+            paginate_query_with_options::<_, (i64, String)>(&pool, base, &past_end, &options)
+                .await
+                .unwrap();
+        assert!(page.data.is_empty());
+        assert_eq!(page.meta.total, Some(25));
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_deferred_join_and_cte_statements() {
+        use paginator_rs::BindValue;
+        use paginator_sqlx::{mysql, postgres, sqlite, PaginateOptions};
+
+        let base = "SELECT id, name FROM items";
+        let params = PaginatorBuilder::new()
+            .page(3)
+            .per_page(5)
+            .sort_by("name")
+            .sort_desc()
+            .build();
+        let options = PaginateOptions::new().deferred_join("id");
+        let deferred = |placeholders: [&str; 2]| {
+            format!(
+                "SELECT _paginator_rows.* FROM (SELECT id, name FROM items) AS _paginator_rows \
+                 INNER JOIN (SELECT id FROM (SELECT id, name FROM items) AS _paginator_keys_src \
+                 ORDER BY name DESC, id DESC LIMIT {} OFFSET {}) AS _paginator_keys \
+                 ON _paginator_rows.id = _paginator_keys.id \
+                 ORDER BY _paginator_rows.name DESC, _paginator_rows.id DESC",
+                placeholders[0], placeholders[1]
+            )
+        };
+        let binds = vec![BindValue::Int(5), BindValue::Int(10)];
+
+        let plan = postgres::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(plan.data.sql, deferred(["$1", "$2"]));
+        assert_eq!(plan.data.binds, binds);
+        let plan = mysql::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(plan.data.sql, deferred(["?", "?"]));
+        assert_eq!(plan.data.binds, binds);
+        let plan = sqlite::plan_with_options(base, &params, &options).unwrap();
+        assert_eq!(plan.data.sql, deferred(["?", "?"]));
+        assert_eq!(plan.data.binds, binds);
+
+        let pool = sqlite_items(20).await;
+        let page =
+            sqlite::paginate_query_with_options::<_, (i64, String)>(&pool, base, &params, &options)
+                .await
+                .unwrap();
+        let ids: Vec<i64> = page.data.iter().map(|(id, _)| *id).collect();
+        // Names sort as text: item-9, ..., item-3, item-20, item-2, item-19, ...
+        assert_eq!(ids, vec![18, 17, 16, 15, 14]);
+
+        // Distance sorts page directly, as without a deferred join.
+        let nearest = PaginationParams::new(3, 5).with_distance_sort(
+            paginator_rs::DistanceSort::new("location", paginator_rs::GeoPoint::new(13.4, 52.52)),
+        );
+        let plan = postgres::plan_with_options(base, &nearest, &options).unwrap();
+        assert_eq!(plan.data, postgres::plan(base, &nearest).unwrap().data);
+        assert_eq!(
+            plan.data.sql,
+            "SELECT *, ST_Distance(location::geography, ST_GeogFromText($1)) AS distance \
+             FROM (SELECT id, name FROM items) AS _base WHERE 1=1 \
+             ORDER BY distance ASC LIMIT $2 OFFSET $3"
+        );
+
+        // The estimate explains the filtered rows without their distances.
+        let estimated = nearest.with_count_strategy(paginator_rs::CountStrategy::Estimated);
+        let count = postgres::plan(base, &estimated).unwrap().count.unwrap();
+        assert_eq!(count.sql, "EXPLAIN SELECT id, name FROM items");
+        assert!(count.binds.is_empty());
+
+        // Filtered `WITH` base queries are filtered in a leading CTE, so the
+        // statement stays valid; a trailing `;` is dropped.
+        let cte = "WITH recent AS (SELECT * FROM items WHERE id > 5) SELECT id, name FROM recent;";
+        let params = PaginatorBuilder::new()
+            .per_page(5)
+            .sort_by("id")
+            .filter_lte("id", FilterValue::Int(12))
+            .build();
+        let plan = sqlite::plan(cte, &params).unwrap();
+        let filtered =
+            "WITH _paginator_filtered AS (SELECT * FROM (WITH recent AS (SELECT * FROM items \
+             WHERE id > 5) SELECT id, name FROM recent) AS _base WHERE 1=1 AND id <= ?) ";
+        assert_eq!(
+            plan.count.unwrap().sql,
+            format!("{}SELECT COUNT(*) FROM _paginator_filtered", filtered)
+        );
+        assert_eq!(
+            plan.data.sql,
+            format!(
+                "{}SELECT * FROM _paginator_filtered ORDER BY id ASC LIMIT ? OFFSET ?",
+                filtered
+            )
+        );
+
+        for options in [
+            PaginateOptions::new(),
+            PaginateOptions::new().window_count(true),
+            PaginateOptions::new().deferred_join("id"),
+        ] {
+            let page = sqlite::paginate_query_with_options::<_, (i64, String)>(
+                &pool, cte, &params, &options,
+            )
+            .await
+            .unwrap();
+            assert_eq!(page.meta.total, Some(7));
+            let ids: Vec<i64> = page.data.iter().map(|(id, _)| *id).collect();
+            assert_eq!(ids, vec![6, 7, 8, 9, 10]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_paginate_on_connection_and_transaction() {
+        use paginator_sqlx::sqlite::{paginate_query_on, paginate_query_on_with_options};
+        use paginator_sqlx::PaginateOptions;
+
+        let pool = sqlite_items(12).await;
+        let base = "SELECT id, name FROM items";
+        let params = PaginatorBuilder::new()
+            .page(2)
+            .per_page(5)
+            .sort_by("id")
+            .build();
+
+        let page = paginate_query_on::<_, (i64, String)>(&pool, base, &params)
+            .await
+            .unwrap();
+        assert_eq!(page.meta.total, Some(12));
+        assert_eq!(page.data.first(), Some(&(6, "item-6".to_string())));
+
+        // Both statements run inside the transaction and see its writes.
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("DELETE FROM items WHERE id <= 5")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        for options in [
+            PaginateOptions::new(),
+            PaginateOptions::new().window_count(true),
+            PaginateOptions::new().concurrent(true),
+        ] {
+            let page = paginate_query_on_with_options::<_, (i64, String)>(
+                &mut *tx, base, &params, &options,
+            )
+            .await
+            .unwrap();
+            assert_eq!(page.meta.total, Some(7));
+            let ids: Vec<i64> = page.data.iter().map(|(id, _)| *id).collect();
+            assert_eq!(ids, vec![11, 12]);
+        }
+        tx.rollback().await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        let page = paginate_query_on::<_, (i64, String)>(&mut *conn, base, &params)
+            .await
+            .unwrap();
+        assert_eq!(page.meta.total, Some(12));
+        assert_eq!(page.data.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_export_breaks_sort_ties_on_the_key_field() {
+        use futures::StreamExt;
+        use paginator_rs::{export_stream, ExportFormat, ExportOptions, PaginatorError};
+        use sqlx::{sqlite::SqliteRow, Row};
+
+        #[derive(serde::Serialize)]
+        struct Item {
+            id: i64,
+            name: String,
+        }
+
+        impl<'r> sqlx::FromRow<'r, SqliteRow> for Item {
+            fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+                Ok(Self {
+                    id: row.try_get("id")?,
+                    name: row.try_get("name")?,
+                })
+            }
+        }
+
+        let pool = sqlite_items(10).await;
+        sqlx::query("UPDATE items SET name = 'group-' || (id % 3)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let fetch_pool = pool.clone();
+        let fetch = move |params: PaginationParams| {
+            let pool = fetch_pool.clone();
+            async move {
+                paginator_sqlx::sqlite::paginate_query::<_, Item>(
+                    &pool,
+                    "SELECT id, name FROM items",
+                    &params,
+                )
+                .await
+            }
+        };
+        let params = PaginatorBuilder::new().sort_by("name").build();
+        let options = ExportOptions::new(ExportFormat::Ndjson).chunk_size(2);
+        let chunks: Vec<Vec<u8>> = export_stream(&params, &options, fetch)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        let ids: Vec<i64> = String::from_utf8(chunks.concat())
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["id"]
+                    .as_i64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(ids, vec![3, 6, 9, 1, 4, 7, 10, 2, 5, 8]);
+
+        // A failing chunk is yielded as an error and ends the stream.
+        let mut calls = 0;
+        let fetch = move |params: PaginationParams| {
+            calls += 1;
+            let pool = pool.clone();
+            async move {
+                if calls > 1 {
+                    return Err(PaginatorError::Custom("connection lost".to_string()));
+                }
+                paginator_sqlx::sqlite::paginate_query::<_, Item>(
+                    &pool,
+                    "SELECT id, name FROM items",
+                    &params,
+                )
+                .await
+            }
+        };
+        let chunks: Vec<_> = export_stream(&params, &options, fetch).collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].is_ok());
+        assert!(matches!(chunks[1], Err(PaginatorError::Custom(ref e)) if e == "connection lost"));
+    }
+
+    #[tokio::test]
+    async fn test_export_pages_over_datetime_and_nullable_sort_fields() {
+        use futures::StreamExt;
+        use paginator_rs::chrono::{DateTime, Duration, TimeZone, Utc};
+        use paginator_rs::{CursorValue, ExportBody, ExportFormat, ExportOptions};
+        use sqlx::{sqlite::SqliteRow, Row};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(serde::Serialize)]
+        struct Event {
+            id: i64,
+            created_at: DateTime<Utc>,
+            closed_at: Option<i64>,
+        }
+
+        impl<'r> sqlx::FromRow<'r, SqliteRow> for Event {
+            fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+                Ok(Self {
+                    id: row.try_get("id")?,
+                    created_at: row.try_get("created_at")?,
+                    closed_at: row.try_get("closed_at")?,
+                })
+            }
+        }
+
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE events (id INTEGER PRIMARY KEY, created_at TEXT NOT NULL, closed_at INTEGER)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // Later ids were created earlier; odd ids are still open.
+        let start = Utc.with_ymd_and_hms(2024, 3, 15, 8, 0, 0).unwrap();
+        for id in 1..=7i64 {
+            sqlx::query("INSERT INTO events (id, created_at, closed_at) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(start - Duration::hours(id))
+                .bind((id % 2 == 0).then_some(100 - id))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let cursors = Arc::new(Mutex::new(Vec::new()));
+        let fetch = {
+            let cursors = cursors.clone();
+            move |params: PaginationParams| {
+                cursors
+                    .lock()
+                    .unwrap()
+                    .push(params.cursor.as_ref().map(|c| c.value.clone()));
+                let pool = pool.clone();
+                async move {
+                    paginator_sqlx::sqlite::paginate_query::<_, Event>(
+                        &pool,
+                        "SELECT id, created_at, closed_at FROM events",
+                        &params,
+                    )
+                    .await
+                }
+            }
+        };
+        let export_ids = |sort_by: &'static str| {
+            let fetch = fetch.clone();
+            async move {
+                let params = PaginatorBuilder::new().sort_by(sort_by).build();
+                let options = ExportOptions::new(ExportFormat::Ndjson).chunk_size(2);
+                let chunks: Vec<Vec<u8>> = ExportBody::start(&params, options, fetch)
+                    .await
+                    .unwrap()
+                    .into_stream()
+                    .map(|chunk| chunk.unwrap())
+                    .collect()
+                    .await;
+                String::from_utf8(chunks.concat())
+                    .unwrap()
+                    .lines()
+                    .map(|line| {
+                        serde_json::from_str::<serde_json::Value>(line).unwrap()["id"]
+                            .as_i64()
+                            .unwrap()
+                    })
+                    .collect::<Vec<i64>>()
+            }
+        };
+
+        assert_eq!(export_ids("created_at").await, vec![7, 6, 5, 4, 3, 2, 1]);
+        let seen: Vec<Option<CursorValue>> = cursors.lock().unwrap().drain(..).collect();
+        assert_eq!(seen.len(), 4);
+        assert_eq!(seen[0], None);
+        assert_eq!(
+            seen[1],
+            Some(CursorValue::DateTime(start - Duration::hours(6)))
+        );
+
+        // SQLite sorts the open events' NULLs first; the export carries on by
+        // offset past them.
+        assert_eq!(export_ids("closed_at").await, vec![1, 3, 5, 7, 6, 4, 2]);
+
+        // An unknown key field fails before anything is sent.
+        let params = PaginatorBuilder::new().sort_by("created_at").build();
+        let options = ExportOptions::new(ExportFormat::Csv).key_field("uuid");
+        let error = ExportBody::start(&params, options, fetch)
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("no such column: uuid"));
+    }
+
+    #[test]
+    fn test_tie_breaker_cursor_statements() {
+        use paginator_rs::{BindValue, CursorDirection, CursorValue};
+        use sea_orm::{DbBackend, EntityTrait};
+
+        let params = PaginatorBuilder::new()
+            .per_page(5)
+            .sort_by("name")
+            .tie_breaker("id")
+            .build();
+        let params = PaginationParams {
+            cursor: Some(
+                paginator_rs::Cursor::new(
+                    "name".into(),
+                    CursorValue::String("b".into()),
+                    CursorDirection::After,
+                )
+                .with_tie_value(CursorValue::Int(7)),
+            ),
+            ..params
+        };
+
+        let plan = paginator_sqlx::postgres::plan("SELECT * FROM users", &params).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM (SELECT * FROM users) AS _cursor_base \
+             WHERE (name > $1 OR (name = $2 AND id > $3)) ORDER BY name ASC, id ASC LIMIT $4"
+        );
+        assert_eq!(
+            plan.data.binds,
+            vec![
+                BindValue::String("b".into()),
+                BindValue::String("b".into()),
+                BindValue::Int(7),
+                BindValue::Int(6),
+            ]
+        );
+
+        let plan = paginator_sea_orm::plan(user_entity::Entity::find(), DbBackend::Sqlite, &params)
+            .unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT \"users\".\"id\", \"users\".\"name\", \"users\".\"age\" FROM \"users\" \
+             WHERE \"name\" > ? OR (\"name\" = ? AND \"id\" > ?) \
+             ORDER BY \"name\" ASC, \"id\" ASC LIMIT ?"
+        );
+
+        let plan = paginator_surrealdb::plan("SELECT * FROM users", &params).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM users WHERE (name > 'b' OR (name = 'b' AND id > 7)) \
+             ORDER BY name ASC, id ASC LIMIT 6"
+        );
+
+        // Without a tie value the cursor compares the sort field alone, but
+        // pages still order by the tie breaker.
+        let mut params = params;
+        params.cursor = None;
+        let plan = paginator_sqlx::sqlite::plan("SELECT * FROM users", &params).unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT * FROM users ORDER BY name ASC, id ASC LIMIT ? OFFSET ?"
+        );
+    }
+
+    fn field_allowlist() -> paginator_rs::PaginationConfig {
+        paginator_rs::PaginationConfig::new().allowed_fields(["id", "name"])
+    }
+
+    #[test]
+    fn test_parse_query_enforces_field_allowlist() {
+        use paginator_rs::{parse_query, ErrorCode, PaginatorError, ParseMode};
+
+        let params = parse_query([("fields", "name,id")], &field_allowlist()).unwrap();
+        assert_eq!(params.fields, Some(vec!["name".into(), "id".into()]));
+
+        for mode in [ParseMode::Lenient, ParseMode::Strict] {
+            let error = parse_query([("fields", "id,password")], &field_allowlist().mode(mode))
+                .unwrap_err();
+            let PaginatorError::Validation(error) = error else {
+                panic!("expected a validation error");
+            };
+            assert_eq!(error.code, ErrorCode::InvalidField);
+            assert_eq!(error.parameter.as_deref(), Some("fields"));
+            assert_eq!(error.allowed, vec!["id", "name"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_axum_extractor_enforces_field_allowlist() {
+        use axum::{body::Body, http::Request, routing::get, Router};
+        use paginator_axum::PaginationQuery;
+        use paginator_tower::PaginationLayer;
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|PaginationQuery(params): PaginationQuery| async move {
+                    params.fields.unwrap_or_default().join(",")
+                }),
+            )
+            .layer(PaginationLayer::new(field_allowlist()));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/?fields=id,name")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let response = app
+            .oneshot(
+                Request::get("/?fields=id,password")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_actix_extractor_enforces_field_allowlist() {
+        use actix_web::{test, web, App};
+        use paginator_actix::{middleware::PaginationMiddleware, StrictPaginationQuery};
+
+        let app = test::init_service(
+            App::new()
+                .wrap(PaginationMiddleware::new(field_allowlist()))
+                .route(
+                    "/",
+                    web::get().to(|query: StrictPaginationQuery| async move {
+                        query.0.fields.unwrap_or_default().join(",")
+                    }),
+                ),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/?fields=id,name")
+            .to_request();
+        let body = test::call_and_read_body(&app, request).await;
+        assert_eq!(body, "id,name");
+        let request = test::TestRequest::get()
+            .uri("/?fields=id,password")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_actix_middleware_passes_through_routes_without_pagination() {
+        use actix_web::{test, web, App};
+        use paginator_actix::{middleware::PaginationMiddleware, PaginationQuery};
+        use paginator_rs::{PaginationConfig, ParseMode};
+
+        let app = test::init_service(
+            App::new()
+                .wrap(PaginationMiddleware::new(
+                    PaginationConfig::default().mode(ParseMode::Strict),
+                ))
+                .route(
+                    "/search",
+                    web::get().to(|query: web::Query<Vec<(String, String)>>| async move {
+                        query.0[0].1.clone()
+                    }),
+                )
+                .route(
+                    "/users",
+                    web::get().to(|query: PaginationQuery| async move {
+                        query.into_params().page.to_string()
+                    }),
+                ),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/search?q=alice").to_request();
+        let body = test::call_and_read_body(&app, request).await;
+        assert_eq!(body, "alice");
+        let request = test::TestRequest::get().uri("/users?page=2").to_request();
+        let body = test::call_and_read_body(&app, request).await;
+        assert_eq!(body, "2");
+        let request = test::TestRequest::get().uri("/users?q=alice").to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), 400);
+    }
+
+    #[rocket::get("/fields")]
+    fn rocket_fields(pagination: paginator_rocket::Pagination) -> String {
+        pagination.params.fields.unwrap_or_default().join(",")
+    }
+
+    #[tokio::test]
+    async fn test_rocket_guard_enforces_field_allowlist() {
+        use paginator_rocket::catchers;
+        use rocket::local::asynchronous::Client;
+
+        let rocket = rocket::build()
+            .manage(field_allowlist())
+            .mount("/", rocket::routes![rocket_fields])
+            .register("/", catchers());
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client.get("/fields?fields=id,name").dispatch().await;
+        assert_eq!(response.into_string().await.as_deref(), Some("id,name"));
+        let response = client.get("/fields?fields=id,password").dispatch().await;
+        assert_eq!(response.status().code, 400);
+        let problem: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(problem["code"], "invalid_field");
+    }
+
+    #[tokio::test]
+    async fn test_sea_orm_rejects_unsupported_filter_values() {
+        use paginator_rs::{ErrorCode, Filter, FilterOperator, PaginatorError};
+        use paginator_sea_orm::PaginateSeaOrm;
+        use sea_orm::{DbBackend, EntityTrait, SqlxSqliteConnector};
+
+        let db = SqlxSqliteConnector::from_sqlx_sqlite_pool(sqlite_items(3).await);
+        for (operator, value) in [
+            (FilterOperator::In, FilterValue::Int(1)),
+            (FilterOperator::NotIn, FilterValue::String("item-1".into())),
+            (FilterOperator::Like, FilterValue::Int(1)),
+            (FilterOperator::Contains, FilterValue::Bool(true)),
+            (
+                FilterOperator::Between,
+                FilterValue::Array(vec![FilterValue::Int(1)]),
+            ),
+            (
+                FilterOperator::Eq,
+                FilterValue::Array(vec![FilterValue::Int(1)]),
+            ),
+        ] {
+            let mut params = PaginationParams::new(1, 10);
+            params.filters = vec![Filter::new("id", operator, value)];
+
+            let error =
+                paginator_sea_orm::plan(item_entity::Entity::find(), DbBackend::Sqlite, &params)
+                    .unwrap_err();
+            assert_eq!(error.code(), ErrorCode::InvalidFilter);
+            let error = item_entity::Entity::find()
+                .paginate_with(&db, &params)
+                .await
+                .unwrap_err();
+            let PaginatorError::Validation(error) = error else {
+                panic!("expected a validation error");
+            };
+            assert_eq!(error.parameter.as_deref(), Some("filter[0]"));
+        }
+    }
+
+    #[test]
+    fn test_sea_orm_selects_distance_column() {
+        use paginator_rs::{BindValue, DistanceSort, GeoPoint};
+        use paginator_sea_orm::{plan_with_options, PaginateOptions};
+        use sea_orm::{DbBackend, EntityTrait};
+
+        let params = PaginationParams::new(1, 5)
+            .with_distance_sort(DistanceSort::new("location", GeoPoint::new(13.4, 52.52)));
+        let distance = |n: u32| {
+            format!(
+                "ST_Distance(\"location\"::geography, ST_GeogFromText(${}))",
+                n
+            )
+        };
+
+        let plan = plan_with_options(
+            item_entity::Entity::find(),
+            DbBackend::Postgres,
+            &params,
+            &PaginateOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            plan.data.sql,
+            format!(
+                "SELECT \"items\".\"id\", \"items\".\"name\", {} AS \"distance\" \
+                 FROM \"items\" WHERE TRUE ORDER BY {} ASC LIMIT $3 OFFSET $4",
+                distance(1),
+                distance(2)
+            )
+        );
+        assert_eq!(
+            plan.data.binds[0],
+            BindValue::String("POINT(13.4 52.52)".into())
+        );
+
+        // Projections keep the distance as well.
+        let plan = plan_with_options(
+            item_entity::Entity::find(),
+            DbBackend::Postgres,
+            &params.with_fields(["name"]),
+            &PaginateOptions::new().project_fields(true),
+        )
+        .unwrap();
+        assert!(plan.data.sql.starts_with(&format!(
+            "SELECT \"name\" AS \"name\", {} AS \"distance\"",
+            distance(1)
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_sea_orm_projects_fields() {
+        use paginator_sea_orm::{paginate_json, plan_with_options, PaginateOptions};
+        use sea_orm::{DbBackend, EntityTrait, SqlxSqliteConnector};
+
+        let params = PaginatorBuilder::new()
+            .per_page(2)
+            .filter_gt("id", FilterValue::Int(3))
+            .build()
+            .with_fields(["name"]);
+        let options = PaginateOptions::new().project_fields(true);
+
+        let plan = plan_with_options(
+            item_entity::Entity::find(),
+            DbBackend::Sqlite,
+            &params,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            plan.data.sql,
+            "SELECT \"name\" AS \"name\" FROM \"items\" WHERE \"id\" > ? LIMIT ? OFFSET ?"
+        );
+
+        let db = SqlxSqliteConnector::from_sqlx_sqlite_pool(sqlite_items(6).await);
+        let page = paginate_json(item_entity::Entity::find(), &db, &params, &options)
+            .await
+            .unwrap();
+        assert_eq!(page.meta.total, Some(3));
+        assert_eq!(
+            page.data,
+            vec![
+                serde_json::json!({"name": "item-4"}),
+                serde_json::json!({"name": "item-5"}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sea_orm_cursor_pages_count_the_whole_filtered_set() {
+        use paginator_rs::CursorValue;
+        use paginator_sea_orm::PaginateSeaOrm;
+        use sea_orm::{DbBackend, EntityTrait, SqlxSqliteConnector};
+
+        let db = SqlxSqliteConnector::from_sqlx_sqlite_pool(sqlite_items(7).await);
+        let ids = |data: &[item_entity::Model]| data.iter().map(|row| row.id).collect::<Vec<_>>();
+
+        // The last page forward still has rows behind the cursor.
+        let params = PaginatorBuilder::new()
+            .per_page(3)
+            .sort_by("id")
+            .cursor_after("id", CursorValue::Int(5))
+            .build();
+        let page = item_entity::Entity::find()
+            .paginate_with(&db, &params)
+            .await
+            .unwrap();
+        assert_eq!(ids(&page.data), vec![6, 7]);
+        assert_eq!(page.meta.total, Some(7));
+        assert!(page.meta.has_prev);
+        assert!(!page.meta.has_next);
+
+        // So does the first page backward, ahead of it.
+        let params = PaginatorBuilder::new()
+            .per_page(3)
+            .sort_by("id")
+            .cursor_before("id", CursorValue::Int(3))
+            .build();
+        let page = item_entity::Entity::find()
+            .paginate_with(&db, &params)
+            .await
+            .unwrap();
+        assert_eq!(ids(&page.data), vec![1, 2]);
+        assert_eq!(page.meta.total, Some(7));
+        assert!(!page.meta.has_prev);
+        assert!(page.meta.has_next);
+
+        let plan = paginator_sea_orm::plan(item_entity::Entity::find(), DbBackend::Sqlite, &params)
+            .unwrap();
+        assert_eq!(
+            plan.count.unwrap().sql,
+            "SELECT COUNT(*) AS num_items FROM (SELECT \"items\".\"id\", \"items\".\"name\" \
+             FROM \"items\" WHERE TRUE) AS \"_count\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sea_orm_window_count_json_rows_omit_total_column() {
+        use paginator_sea_orm::{paginate_json, PaginateOptions};
+        use sea_orm::{EntityTrait, SqlxSqliteConnector};
+
+        let db = SqlxSqliteConnector::from_sqlx_sqlite_pool(sqlite_items(7).await);
+        let params = PaginatorBuilder::new()
+            .page(2)
+            .per_page(3)
+            .sort_by("id")
+            .build();
+        let options = PaginateOptions::new().window_count(true);
+
+        let page = paginate_json(item_entity::Entity::find(), &db, &params, &options)
+            .await
+            .unwrap();
+        assert_eq!(page.meta.total, Some(7));
+        assert_eq!(
+            page.data,
+            vec![
+                serde_json::json!({"id": 4, "name": "item-4"}),
+                serde_json::json!({"id": 5, "name": "item-5"}),
+                serde_json::json!({"id": 6, "name": "item-6"}),
+            ]
+        );
+        assert!(page
+            .data
+            .iter()
+            .all(|row| row.get("_paginator_total").is_none()));
+    }
+
+    mod item_entity {
+        use sea_orm::entity::prelude::*;
+
+        #[derive(Clone, Debug, PartialEq, DeriveEntityModel, serde::Serialize)]
+        #[sea_orm(table_name = "items")]
+        pub struct Model {
+            #[sea_orm(primary_key)]
+            pub id: i64,
+            pub name: String,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_reads_match_plain_pages() {
+        use paginator_sea_orm::{PaginateSeaOrm, PaginateSeaOrmSnapshot};
+        use paginator_sqlx::sqlite::{paginate_query_snapshot, paginate_query_with_options};
+        use sea_orm::{EntityTrait, SqlxSqliteConnector};
+
+        let pool = sqlite_items(25).await;
+        let params = PaginatorBuilder::new()
+            .page(3)
+            .per_page(10)
+            .sort_by("id")
+            .filter_gt("id", FilterValue::Int(2))
+            .build();
+
+        // `concurrent` would need a second connection; snapshots ignore it.
+        let options = paginator_sqlx::PaginateOptions::new().concurrent(true);
+        let base = "SELECT id, name FROM items";
+        let plain = paginate_query_with_options::<_, (i64, String)>(&pool, base, &params, &options)
+            .await
+            .unwrap();
+        let snapshot = paginate_query_snapshot::<(i64, String)>(&pool, base, &params, &options)
+            .await
+            .unwrap();
+        assert_eq!(snapshot.meta.total, Some(23));
+        assert_eq!(snapshot.data, plain.data);
+        assert_eq!(snapshot.data.first(), Some(&(23, "item-23".to_string())));
+
+        let db = SqlxSqliteConnector::from_sqlx_sqlite_pool(pool);
+        let options = paginator_sea_orm::PaginateOptions::new().concurrent(true);
+        let plain = item_entity::Entity::find()
+            .paginate_with(&db, &params)
+            .await
+            .unwrap();
+        let snapshot = item_entity::Entity::find()
+            .paginate_snapshot(&db, &params, &options)
+            .await
+            .unwrap();
+        assert_eq!(snapshot.meta.total, Some(23));
+        assert_eq!(snapshot.data, plain.data);
+        let ids: Vec<i64> = snapshot.data.iter().map(|item| item.id).collect();
+        assert_eq!(ids, vec![23, 24, 25]);
+    }
 }
@@ -1,12 +1,29 @@
-use paginator_rs::{PaginationParams, PaginatorResponse, PaginatorResponseMeta, SortDirection};
+use paginator_rs::{
+    link_header, meta_headers, page_links, parse_query, response_links, ErrorCode, ExportBody,
+    ExportOptions, PaginationConfig, PaginationLinks, PaginationParams, PaginatorError,
+    PaginatorResponse, PaginatorResponseMeta, ParseMode, ProblemDetails, ResponseFormat,
+    ValidationError, PROBLEM_CONTENT_TYPE,
+};
 use rocket::{
-    http::Header,
+    fairing::{Fairing, Info, Kind},
+    futures::StreamExt,
+    http::{Header, Status},
     request::{self, FromRequest, Request},
-    response::{self, Responder},
+    response::{self, Responder, Response},
     serde::json::Json,
+    Catcher,
 };
 use serde::Serialize;
+use std::future::Future;
+use std::io::{self, Cursor};
+use tokio_util::io::StreamReader;
 
+/// Pagination parameters parsed from the query string with `parse_query`:
+/// paging, sorting, filters, search, fields, cursor and count options.
+///
+/// Reads its `PaginationConfig` from managed state, falling back to the
+/// lenient defaults. In strict mode the guard fails with a `400 Bad Request`
+/// that the handlers from `catchers()` render as a problem.
 #[derive(Debug, Clone)]
 pub struct Pagination {
     pub params: PaginationParams,
@@ -14,56 +31,178 @@ pub struct Pagination {
 
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for Pagination {
-    type Error = &'static str;
+    type Error = Problem;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let config = managed_config(req);
+        outcome(req, &config, |params| Pagination { params })
+    }
+}
+
+/// Like `Pagination`, but always strict: fails with a `400 Bad Request`
+/// problem instead of ignoring parameters it cannot use, such as unknown
+/// keys, malformed filters, unparseable numbers and `page` or `per_page` out
+/// of range.
+///
+/// Take it as `Result<StrictPagination, Problem>` to respond with the
+/// problem document, or register `catchers()`.
+#[derive(Debug, Clone)]
+pub struct StrictPagination {
+    pub params: PaginationParams,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for StrictPagination {
+    type Error = Problem;
 
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        let query = req.uri().query();
-
-        let mut page = 1u32;
-        let mut per_page = 20u32;
-        let mut sort_by: Option<String> = None;
-        let mut sort_direction: Option<SortDirection> = None;
-
-        if let Some(query) = query {
-            for (key, value) in query.segments() {
-                match key {
-                    "page" => {
-                        if let Ok(p) = value.parse::<u32>() {
-                            page = p.max(1);
-                        }
-                    }
-                    "per_page" => {
-                        if let Ok(pp) = value.parse::<u32>() {
-                            per_page = pp.clamp(1, 100);
-                        }
-                    }
-                    "sort_by" => {
-                        sort_by = Some(value.to_string());
-                    }
-                    "sort_direction" => {
-                        sort_direction = match value.to_lowercase().as_str() {
-                            "asc" => Some(SortDirection::Asc),
-                            "desc" => Some(SortDirection::Desc),
-                            _ => None,
-                        };
-                    }
-                    _ => {}
-                }
-            }
+        let config = managed_config(req).mode(ParseMode::Strict);
+        outcome(req, &config, |params| StrictPagination { params })
+    }
+}
+
+fn managed_config(req: &Request<'_>) -> PaginationConfig {
+    req.rocket()
+        .state::<PaginationConfig>()
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn outcome<T>(
+    req: &Request<'_>,
+    config: &PaginationConfig,
+    guard: impl FnOnce(PaginationParams) -> T,
+) -> request::Outcome<T, Problem> {
+    match parse_request(req, config) {
+        Ok(params) => {
+            req.local_cache(|| RecordedParams(Some(params.clone())));
+            request::Outcome::Success(guard(params))
+        }
+        Err(e) => {
+            req.local_cache(|| RecordedProblem(Some(ProblemDetails::from(&e))));
+            request::Outcome::Error((Status::BadRequest, Problem(e)))
         }
+    }
+}
 
-        request::Outcome::Success(Pagination {
-            params: PaginationParams {
-                page,
-                per_page,
-                sort_by,
-                sort_direction,
-                filters: Vec::new(),
-                search: None,
-                disable_total_count: false,
-                cursor: None,
-            },
-        })
+fn parse_request(
+    req: &Request<'_>,
+    config: &PaginationConfig,
+) -> Result<PaginationParams, PaginatorError> {
+    let pairs = req
+        .uri()
+        .query()
+        .into_iter()
+        .flat_map(|query| query.segments());
+    parse_query(pairs, config)
+}
+
+/// Links for a response to `req`, from the params a pagination guard parsed
+/// or, without one, the query string parsed leniently.
+fn request_links(req: &Request<'_>, meta: &PaginatorResponseMeta) -> Option<PaginationLinks> {
+    let params = match &req.local_cache(|| RecordedParams(None)).0 {
+        Some(params) => params.clone(),
+        None => parse_request(req, &managed_config(req).mode(ParseMode::Lenient)).ok()?,
+    };
+    Some(response_links(req.uri().path().as_str(), &params, meta))
+}
+
+/// The params of a successful pagination guard, for the `links` object.
+struct RecordedParams(Option<PaginationParams>);
+
+/// The problem of a failed pagination guard, for `catchers()`.
+struct RecordedProblem(Option<ProblemDetails>);
+
+/// The meta of a paginated response, for `PaginationFairing`.
+struct RecordedMeta(Option<PaginatorResponseMeta>);
+
+/// Catchers rendering `400 Bad Request` as `application/problem+json`.
+///
+/// Pagination guard failures report their code and parameter; other bad
+/// requests get a generic `invalid_query` problem.
+pub fn catchers() -> Vec<Catcher> {
+    rocket::catchers![bad_request]
+}
+
+#[rocket::catch(400)]
+fn bad_request(req: &Request<'_>) -> ProblemResponse {
+    let recorded = &req.local_cache(|| RecordedProblem(None)).0;
+    ProblemResponse(recorded.clone().unwrap_or_else(|| {
+        ProblemDetails::from(&PaginatorError::from(ValidationError::new(
+            ErrorCode::InvalidQuery,
+            "The request could not be understood",
+        )))
+    }))
+}
+
+/// Adds the pagination headers and an RFC 8288 `Link` header to responses
+/// built by `PaginatedJson` and `Negotiated`.
+///
+/// The links keep the request's other query parameters and page with
+/// `cursor` when the response carries cursors:
+///
+/// ```text
+/// Link: </users?page=1&per_page=20&filter=status:eq:active>; rel="first", ...
+/// ```
+pub struct PaginationFairing;
+
+#[rocket::async_trait]
+impl Fairing for PaginationFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Pagination headers",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let Some(meta) = &req.local_cache(|| RecordedMeta(None)).0 else {
+            return;
+        };
+
+        for (name, value) in meta_headers(meta) {
+            res.set_header(Header::new(name, value));
+        }
+        let links = page_links(
+            req.uri().path().as_str(),
+            req.uri().query().map(|query| query.as_str()),
+            meta,
+        );
+        res.set_header(Header::new("Link", link_header(&links)));
+    }
+}
+
+/// A `PaginatorError` rendered as an RFC 7807 `application/problem+json`
+/// response, with the status derived from the error's code.
+///
+/// Handlers can return `Result<PaginatedJson<T>, Problem>` and use `?` on
+/// backend calls.
+#[derive(Debug)]
+pub struct Problem(pub PaginatorError);
+
+impl From<PaginatorError> for Problem {
+    fn from(error: PaginatorError) -> Self {
+        Self(error)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for Problem {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        ProblemResponse(ProblemDetails::from(&self.0)).respond_to(req)
+    }
+}
+
+struct ProblemResponse(ProblemDetails);
+
+impl<'r> Responder<'r, 'static> for ProblemResponse {
+    fn respond_to(self, _req: &'r Request<'_>) -> response::Result<'static> {
+        let body = self.0.to_json();
+
+        Response::build()
+            .status(Status::new(self.0.status))
+            .header(Header::new("Content-Type", PROBLEM_CONTENT_TYPE))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
     }
 }
 
@@ -81,6 +220,7 @@ where
             response: PaginatorResponse {
                 data,
                 meta: PaginatorResponseMeta::new(params.page, params.per_page, total),
+                links: None,
             },
         }
     }
@@ -94,7 +234,11 @@ impl<'r, T> Responder<'r, 'static> for PaginatedJson<T>
 where
     T: Serialize,
 {
-    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+    fn respond_to(mut self, req: &'r Request<'_>) -> response::Result<'static> {
+        if self.response.links.is_none() {
+            self.response.links = request_links(req, &self.response.meta);
+        }
+        req.local_cache(|| RecordedMeta(Some(self.response.meta.clone())));
         let json = Json(&self.response);
         let mut response = json.respond_to(req)?;
 
@@ -117,6 +261,55 @@ where
     }
 }
 
+/// Paginated response serialized as JSON, MessagePack, CSV or NDJSON,
+/// whichever the request's `Accept` header prefers.
+///
+/// Responds with a `406 Not Acceptable` problem when none of them is
+/// acceptable. The pagination headers are always set; for CSV and NDJSON,
+/// whose bodies hold only the rows, they are the sole carrier of the meta.
+#[derive(Debug)]
+pub struct Negotiated<T> {
+    response: PaginatorResponse<T>,
+}
+
+impl<T> Negotiated<T>
+where
+    T: Serialize,
+{
+    pub fn from_response(response: PaginatorResponse<T>) -> Self {
+        Self { response }
+    }
+}
+
+impl<'r, T> Responder<'r, 'static> for Negotiated<T>
+where
+    T: Serialize,
+{
+    fn respond_to(mut self, req: &'r Request<'_>) -> response::Result<'static> {
+        if self.response.links.is_none() {
+            self.response.links = request_links(req, &self.response.meta);
+        }
+        let Some(format) = ResponseFormat::from_accept(req.headers().get_one("Accept")) else {
+            return Problem(ResponseFormat::not_acceptable()).respond_to(req);
+        };
+        let body = match format.encode(&self.response) {
+            Ok(body) => body,
+            Err(e) => return Problem(e).respond_to(req),
+        };
+        req.local_cache(|| RecordedMeta(Some(self.response.meta.clone())));
+
+        let mut response = Response::build();
+        for (name, value) in meta_headers(&self.response.meta) {
+            response.header(Header::new(name, value));
+        }
+        response
+            .header(Header::new("Content-Type", format.content_type()))
+            .header(Header::new("Vary", "accept"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
 pub fn create_paginated_response<T>(
     data: Vec<T>,
     params: &PaginationParams,
@@ -127,3 +320,63 @@ where
 {
     PaginatedJson::new(data, params, total)
 }
+
+/// Streams every row matching `params` as a CSV or NDJSON download.
+///
+/// `fetch` runs the backend query for one keyset chunk, e.g. a closure calling
+/// `paginate_query` with the chunk's params. Chunks are fetched as the client
+/// reads the body. Rocket cannot change the status once streaming has begun:
+/// an error ends the download early and Rocket logs it as a failed response
+/// write. Build the response with `start` to report first-chunk errors with
+/// a status.
+pub struct ExportResponse(ExportBody);
+
+impl ExportResponse {
+    pub fn new<T, F, Fut>(params: &PaginationParams, options: ExportOptions, fetch: F) -> Self
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+    {
+        Self(ExportBody::new(params, options, fetch))
+    }
+
+    /// Like `new`, but runs the first chunk's query before responding, so
+    /// its errors can still be returned as a `Problem`.
+    pub async fn start<T, F, Fut>(
+        params: &PaginationParams,
+        options: ExportOptions,
+        fetch: F,
+    ) -> Result<Self, PaginatorError>
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+    {
+        ExportBody::start(params, options, fetch).await.map(Self)
+    }
+}
+
+impl From<ExportBody> for ExportResponse {
+    fn from(body: ExportBody) -> Self {
+        Self(body)
+    }
+}
+
+impl<'r> Responder<'r, 'r> for ExportResponse {
+    fn respond_to(self, _req: &'r Request<'_>) -> response::Result<'r> {
+        let content_type = self.0.content_type();
+        let content_disposition = self.0.content_disposition();
+        // Errors reach Rocket as failed body reads, which end the response.
+        let chunks = self
+            .0
+            .into_stream()
+            .map(|chunk| chunk.map(Cursor::new).map_err(io::Error::other));
+
+        Response::build()
+            .header(Header::new("Content-Type", content_type))
+            .header(Header::new("Content-Disposition", content_disposition))
+            .streamed_body(StreamReader::new(chunks))
+            .ok()
+    }
+}
@@ -33,6 +33,7 @@ export default defineConfig({
 						{ label: 'Sorting', slug: 'core-concepts/sorting' },
 						{ label: 'Search', slug: 'core-concepts/search' },
 						{ label: 'Cursor Pagination', slug: 'core-concepts/cursor-pagination' },
+						{ label: 'Field Selection', slug: 'core-concepts/field-selection' },
 						{ label: 'Response Format', slug: 'core-concepts/response-format' },
 					],
 				},
@@ -50,6 +51,7 @@ export default defineConfig({
 						{ label: 'Axum', slug: 'web-frameworks/axum' },
 						{ label: 'Rocket', slug: 'web-frameworks/rocket' },
 						{ label: 'Actix-web', slug: 'web-frameworks/actix' },
+						{ label: 'Tower', slug: 'web-frameworks/tower' },
 					],
 				},
 				{
@@ -59,6 +61,8 @@ export default defineConfig({
 						{ label: 'Performance', slug: 'advanced/performance' },
 						{ label: 'Security', slug: 'advanced/security' },
 						{ label: 'Query Parameters', slug: 'advanced/query-parameters' },
+						{ label: 'HTTP Client', slug: 'advanced/client' },
+						{ label: 'Paginated Sources', slug: 'advanced/sources' },
 					],
 				},
 			],
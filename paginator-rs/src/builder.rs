@@ -1,6 +1,7 @@
 use paginator_utils::{
-    Cursor, CursorDirection, CursorValue, Filter, FilterOperator, FilterValue,
-    IntoPaginationParams, PaginationParams, SearchParams, SortDirection,
+    CountStrategy, Cursor, CursorDirection, CursorValue, DistanceSort, Filter, FilterOperator,
+    FilterValue, GeoBox, GeoPoint, IntoPaginationParams, PaginationParams, SearchParams,
+    SortDirection, DISTANCE_COLUMN,
 };
 use std::marker::PhantomData;
 
@@ -77,6 +78,37 @@ impl Paginator {
         self
     }
 
+    //
+    // -------------- FIELD SELECTION --------------
+    //
+    /// Return only `fields` for each row.
+    pub fn fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.params.fields = Some(fields.into_iter().map(Into::into).collect());
+        self
+    }
+
+    //
+    // -------------- COUNT STRATEGY --------------
+    //
+    pub fn count_strategy(mut self, strategy: CountStrategy) -> Self {
+        self.params.count_strategy = strategy;
+        self
+    }
+
+    /// Count at most `cap` rows; larger totals are reported as a lower bound.
+    pub fn capped_count(self, cap: u32) -> Self {
+        self.count_strategy(CountStrategy::Capped(cap))
+    }
+
+    /// Use the database planner's row estimate instead of an exact count.
+    pub fn estimated_count(self) -> Self {
+        self.count_strategy(CountStrategy::Estimated)
+    }
+
     //
     // -------------- FINAL BUILD --------------
     //
@@ -95,6 +127,7 @@ pub struct SortBuilder<P> {
     parent: P,
 }
 
+#[allow(unused_mut)]
 impl<P> SortBuilder<P> {
     fn new(parent: P) -> Self {
         Self { parent }
@@ -119,6 +152,19 @@ impl<P> SortBuilder<P> {
         p.params_mut().sort_direction = Some(SortDirection::Desc);
         p
     }
+
+    /// Nearest to `origin` first, measured on the geometry column `field`.
+    /// Rows carry the distance in metres as `DISTANCE_COLUMN`.
+    pub fn nearest(self, field: impl Into<String>, origin: GeoPoint) -> P
+    where
+        P: HasParams,
+    {
+        let mut p = self.parent;
+        p.params_mut().distance_sort = Some(DistanceSort::new(field, origin));
+        p.params_mut().sort_by = Some(DISTANCE_COLUMN.to_string());
+        p.params_mut().sort_direction = Some(SortDirection::Asc);
+        p
+    }
 }
 
 //
@@ -132,6 +178,12 @@ pub struct FilterBuilder<P = ()> {
     filters: Vec<Filter>,
 }
 
+impl Default for FilterBuilder<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FilterBuilder<()> {
     /// Create as standalone (no parent)
     pub fn new() -> Self {
@@ -227,6 +279,104 @@ impl<P> FilterBuilder<P> {
         self.push(field, FilterOperator::Contains, value)
     }
 
+    /// Array field holding every one of `values`.
+    pub fn contains_all(self, field: impl Into<String>, values: Vec<FilterValue>) -> Self {
+        self.push(
+            field,
+            FilterOperator::ContainsAll,
+            FilterValue::Array(values),
+        )
+    }
+
+    /// Array field holding at least one of `values`.
+    pub fn contains_any(self, field: impl Into<String>, values: Vec<FilterValue>) -> Self {
+        self.push(
+            field,
+            FilterOperator::ContainsAny,
+            FilterValue::Array(values),
+        )
+    }
+
+    pub fn length_eq(self, field: impl Into<String>, len: i64) -> Self {
+        self.push(field, FilterOperator::LengthEq, FilterValue::Int(len))
+    }
+
+    pub fn length_gt(self, field: impl Into<String>, len: i64) -> Self {
+        self.push(field, FilterOperator::LengthGt, FilterValue::Int(len))
+    }
+
+    pub fn length_gte(self, field: impl Into<String>, len: i64) -> Self {
+        self.push(field, FilterOperator::LengthGte, FilterValue::Int(len))
+    }
+
+    pub fn length_lt(self, field: impl Into<String>, len: i64) -> Self {
+        self.push(field, FilterOperator::LengthLt, FilterValue::Int(len))
+    }
+
+    pub fn length_lte(self, field: impl Into<String>, len: i64) -> Self {
+        self.push(field, FilterOperator::LengthLte, FilterValue::Int(len))
+    }
+
+    /// Values starting with `prefix`, matched literally.
+    pub fn starts_with(self, field: impl Into<String>, prefix: impl Into<String>) -> Self {
+        self.push(
+            field,
+            FilterOperator::StartsWith,
+            FilterValue::String(prefix.into()),
+        )
+    }
+
+    /// Values ending with `suffix`, matched literally.
+    pub fn ends_with(self, field: impl Into<String>, suffix: impl Into<String>) -> Self {
+        self.push(
+            field,
+            FilterOperator::EndsWith,
+            FilterValue::String(suffix.into()),
+        )
+    }
+
+    /// Case-insensitive equality.
+    pub fn ieq(self, field: impl Into<String>, value: impl Into<String>) -> Self {
+        self.push(
+            field,
+            FilterOperator::IEq,
+            FilterValue::String(value.into()),
+        )
+    }
+
+    pub fn regex(self, field: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.push(
+            field,
+            FilterOperator::Regex,
+            FilterValue::String(pattern.into()),
+        )
+    }
+
+    /// Geometries within `meters` of `center`.
+    pub fn within_radius(self, field: impl Into<String>, center: GeoPoint, meters: f64) -> Self {
+        self.push(
+            field,
+            FilterOperator::WithinRadius,
+            FilterValue::Array(vec![FilterValue::Point(center), FilterValue::Float(meters)]),
+        )
+    }
+
+    pub fn within_box(self, field: impl Into<String>, bbox: GeoBox) -> Self {
+        self.push(
+            field,
+            FilterOperator::WithinBox,
+            FilterValue::BoundingBox(bbox),
+        )
+    }
+
+    pub fn within_polygon(self, field: impl Into<String>, ring: Vec<GeoPoint>) -> Self {
+        self.push(
+            field,
+            FilterOperator::WithinPolygon,
+            FilterValue::Polygon(ring),
+        )
+    }
+
     /// Finish and return to parent.
     ///
     /// Note: This method is only callable when the builder was created via a fluent chain
@@ -261,6 +411,12 @@ pub struct SearchBuilder<P = ()> {
     case_sensitive: bool,
 }
 
+impl Default for SearchBuilder<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SearchBuilder<()> {
     pub fn new() -> Self {
         Self {
@@ -363,6 +519,12 @@ pub struct CursorBuilder<P = ()> {
     cursor: Option<Cursor>,
 }
 
+impl Default for CursorBuilder<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CursorBuilder<()> {
     pub fn new() -> Self {
         Self {
@@ -661,6 +823,11 @@ impl PaginatorBuilder {
         self
     }
 
+    pub fn count_strategy(mut self, strategy: CountStrategy) -> Self {
+        self.params.count_strategy = strategy;
+        self
+    }
+
     pub fn cursor(
         mut self,
         field: impl Into<String>,
@@ -681,6 +848,12 @@ impl PaginatorBuilder {
         self
     }
 
+    /// Orders rows that tie on the sort field by the unique `field`.
+    pub fn tie_breaker(mut self, field: impl Into<String>) -> Self {
+        self.params.tie_breaker = Some(field.into());
+        self
+    }
+
     pub fn cursor_from_encoded(mut self, encoded: &str) -> Result<Self, String> {
         self.params.cursor = Some(Cursor::decode(encoded)?);
         Ok(self)
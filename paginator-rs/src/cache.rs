@@ -0,0 +1,176 @@
+use paginator_utils::{PaginationParams, TotalKind};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Computes a stable fingerprint of everything that affects a total count:
+/// the base query, filters, search and count strategy.
+///
+/// Page, sort and per-page settings are deliberately left out so that every
+/// page of the same filtered list shares one cache entry. Filters holding
+/// relative dates are keyed by their `date_source`, so `now-7d` keeps hitting
+/// the entry it created until the entry expires.
+pub fn count_fingerprint(base_query: &str, params: &PaginationParams) -> u64 {
+    count_fingerprint_with(base_query, params, &())
+}
+
+/// Like `count_fingerprint`, also keyed by the backend options in
+/// `count_options` that change the count statement, such as the declared JSON
+/// columns.
+pub fn count_fingerprint_with<O>(
+    base_query: &str,
+    params: &PaginationParams,
+    count_options: &O,
+) -> u64
+where
+    O: Serialize + ?Sized,
+{
+    let filters: Vec<serde_json::Value> = params
+        .filters
+        .iter()
+        .map(|filter| match filter.date_source {
+            Some(ref source) => serde_json::json!({
+                "field": filter.field,
+                "operator": filter.operator,
+                "date_source": source,
+            }),
+            None => serde_json::json!(filter),
+        })
+        .collect();
+    let count_inputs = serde_json::json!({
+        "filters": filters,
+        "search": params.search,
+        "count_strategy": params.count_strategy,
+        "options": count_options,
+    });
+
+    let mut hash = FNV_OFFSET_BASIS;
+    for bytes in [
+        base_query.as_bytes(),
+        &[0],
+        count_inputs.to_string().as_bytes(),
+    ] {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    hash
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+struct CountCacheEntry {
+    total: (u32, TotalKind),
+    inserted_at: Instant,
+    tags: Vec<String>,
+}
+
+/// In-memory cache of total counts keyed by [`count_fingerprint`].
+///
+/// Entries expire after `ttl`, the cache holds at most `max_entries` counts,
+/// and entries can be dropped by table tag after writes. Cloning is cheap and
+/// clones share the same storage.
+///
+/// Backends take a cache through their `PaginateOptions::count_cache`,
+/// together with the tags of the tables a query reads. A write to one of
+/// those tables should be followed by `invalidate_tag`.
+#[derive(Clone)]
+pub struct CountCache {
+    entries: Arc<Mutex<HashMap<u64, CountCacheEntry>>>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl std::fmt::Debug for CountCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CountCache")
+            .field("ttl", &self.ttl)
+            .field("max_entries", &self.max_entries)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl CountCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<u64, CountCacheEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the cached total for `fingerprint` if it has not expired.
+    pub fn get(&self, fingerprint: u64) -> Option<(u32, TotalKind)> {
+        let mut entries = self.entries();
+        match entries.get(&fingerprint) {
+            Some(entry) if entry.inserted_at.elapsed() < self.ttl => Some(entry.total),
+            Some(_) => {
+                entries.remove(&fingerprint);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Stores a total, tagged with the tables it depends on.
+    ///
+    /// When the cache is full, expired entries are dropped first, then the
+    /// oldest entry.
+    pub fn insert<S: AsRef<str>>(&self, fingerprint: u64, total: (u32, TotalKind), tags: &[S]) {
+        let mut entries = self.entries();
+
+        if entries.len() >= self.max_entries && !entries.contains_key(&fingerprint) {
+            let ttl = self.ttl;
+            entries.retain(|_, entry| entry.inserted_at.elapsed() < ttl);
+
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.inserted_at)
+                    .map(|(key, _)| *key);
+                if let Some(key) = oldest {
+                    entries.remove(&key);
+                }
+            }
+        }
+
+        entries.insert(
+            fingerprint,
+            CountCacheEntry {
+                total,
+                inserted_at: Instant::now(),
+                tags: tags.iter().map(|t| t.as_ref().to_string()).collect(),
+            },
+        );
+    }
+
+    /// Drops every entry tagged with `tag`, e.g. after writing to that table.
+    pub fn invalidate_tag(&self, tag: &str) {
+        self.entries()
+            .retain(|_, entry| !entry.tags.iter().any(|t| t == tag));
+    }
+
+    pub fn invalidate(&self, fingerprint: u64) {
+        self.entries().remove(&fingerprint);
+    }
+
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt;
 
@@ -6,9 +7,104 @@ pub enum PaginatorError {
     InvalidPage(u32),
     InvalidPerPage(u32),
     SerializationError(String),
+    /// A request parameter that cannot be used, with the details a client
+    /// needs to correct it.
+    Validation(ValidationError),
     Custom(String),
 }
 
+/// Machine-readable kind of a `PaginatorError`, reported as `code` in
+/// problem responses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidPage,
+    InvalidPerPage,
+    /// The query string could not be decoded at all.
+    InvalidQuery,
+    InvalidSort,
+    InvalidFilter,
+    InvalidSearch,
+    InvalidCursor,
+    /// A field name that is unsafe or not allowed.
+    InvalidField,
+    /// Any other parameter with an invalid value, such as `tz` or `near`.
+    InvalidParameter,
+    NotAcceptable,
+    SerializationError,
+    Internal,
+}
+
+impl ErrorCode {
+    /// The HTTP status code for errors of this kind.
+    pub fn status(&self) -> u16 {
+        match self {
+            ErrorCode::NotAcceptable => 406,
+            ErrorCode::SerializationError | ErrorCode::Internal => 500,
+            _ => 400,
+        }
+    }
+}
+
+/// Details of a `PaginatorError::Validation`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub code: ErrorCode,
+    /// The offending query parameter, e.g. `sort_by` or `filter[2]`.
+    pub parameter: Option<String>,
+    pub message: String,
+    /// The values the parameter accepts, when they form a closed set.
+    pub allowed: Vec<String>,
+}
+
+impl ValidationError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            parameter: None,
+            message: message.into(),
+            allowed: Vec::new(),
+        }
+    }
+
+    pub fn parameter(mut self, parameter: impl Into<String>) -> Self {
+        self.parameter = Some(parameter.into());
+        self
+    }
+
+    pub fn allowed<I, S>(mut self, allowed: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed = allowed.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl From<ValidationError> for PaginatorError {
+    fn from(error: ValidationError) -> Self {
+        PaginatorError::Validation(error)
+    }
+}
+
+impl PaginatorError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            PaginatorError::InvalidPage(_) => ErrorCode::InvalidPage,
+            PaginatorError::InvalidPerPage(_) => ErrorCode::InvalidPerPage,
+            PaginatorError::SerializationError(_) => ErrorCode::SerializationError,
+            PaginatorError::Validation(error) => error.code,
+            PaginatorError::Custom(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// The HTTP status code for this error, derived from its `code`.
+    pub fn status(&self) -> u16 {
+        self.code().status()
+    }
+}
+
 impl fmt::Display for PaginatorError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -25,6 +121,7 @@ impl fmt::Display for PaginatorError {
             PaginatorError::SerializationError(msg) => {
                 write!(f, "Serialization error: {}", msg)
             }
+            PaginatorError::Validation(error) => write!(f, "{}", error.message),
             PaginatorError::Custom(msg) => write!(f, "{}", msg),
         }
     }
@@ -0,0 +1,365 @@
+use crate::error::PaginatorError;
+use crate::format::{write_csv_rows, write_ndjson_rows, OrderedRow};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use paginator_utils::chrono::{DateTime, NaiveDateTime, Utc};
+use paginator_utils::{
+    Cursor, CursorDirection, CursorValue, PaginationParams, PaginatorResponse, SortDirection,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+
+/// Encoded chunks of an export, one item per fetched keyset chunk.
+pub type ExportStream = BoxStream<'static, Result<Vec<u8>, PaginatorError>>;
+
+/// Output format of an export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// RFC 4180 CSV with a header row taken from the first row's fields.
+    Csv,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Settings for `export_stream` and the framework export responders.
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    format: ExportFormat,
+    filename: String,
+    key_field: String,
+    chunk_size: u32,
+    max_rows: Option<usize>,
+}
+
+impl ExportOptions {
+    pub const DEFAULT_CHUNK_SIZE: u32 = 500;
+    pub const DEFAULT_MAX_ROWS: usize = 100_000;
+
+    pub fn new(format: ExportFormat) -> Self {
+        Self {
+            format,
+            filename: "export".to_string(),
+            key_field: "id".to_string(),
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            max_rows: Some(Self::DEFAULT_MAX_ROWS),
+        }
+    }
+
+    /// Download name without extension; the format's extension is appended.
+    pub fn filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = filename.into();
+        self
+    }
+
+    /// Column the export pages over when the request has no `sort_by`, and
+    /// that breaks ties on the request's sort field. It must be unique and
+    /// appear in the serialized rows.
+    pub fn key_field(mut self, key_field: impl Into<String>) -> Self {
+        self.key_field = key_field.into();
+        self
+    }
+
+    /// Rows fetched per keyset chunk.
+    pub fn chunk_size(mut self, chunk_size: u32) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Stops the export after `max_rows` rows; `None` exports everything.
+    pub fn max_rows(mut self, max_rows: Option<usize>) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+
+    /// `Content-Disposition` header value offering the export as a download.
+    pub fn content_disposition(&self) -> String {
+        let filename: String = self
+            .filename
+            .chars()
+            .filter(|c| !c.is_control() && *c != '"' && *c != '\\')
+            .collect();
+        format!(
+            "attachment; filename=\"{}.{}\"",
+            filename,
+            self.format.extension()
+        )
+    }
+}
+
+/// An export stream with the headers describing it, which the framework
+/// crates' `ExportResponse`s send as a download.
+pub struct ExportBody {
+    stream: ExportStream,
+    options: ExportOptions,
+}
+
+impl ExportBody {
+    /// Exports the rows matching `params`; `fetch` runs the backend query for
+    /// one keyset chunk.
+    pub fn new<T, F, Fut>(params: &PaginationParams, options: ExportOptions, fetch: F) -> Self
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+    {
+        Self {
+            stream: export_stream(params, &options, fetch),
+            options,
+        }
+    }
+
+    /// Like `new`, but fetches the first chunk right away. Query errors and
+    /// sort or key fields missing from the rows are returned here, before a
+    /// response has been started, instead of cutting the download short.
+    pub async fn start<T, F, Fut>(
+        params: &PaginationParams,
+        options: ExportOptions,
+        fetch: F,
+    ) -> Result<Self, PaginatorError>
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+    {
+        let mut rest = export_stream(params, &options, fetch);
+        let stream = match rest.next().await {
+            Some(first) => stream::iter([Ok(first?)]).chain(rest).boxed(),
+            None => rest,
+        };
+        Ok(Self { stream, options })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        self.options.format().content_type()
+    }
+
+    pub fn content_disposition(&self) -> String {
+        self.options.content_disposition()
+    }
+
+    pub fn into_stream(self) -> ExportStream {
+        self.stream
+    }
+}
+
+/// Streams every row matching the filters, search and sort of `params`.
+///
+/// Rows are fetched in keyset chunks: each chunk is `params` without total
+/// count or offset, with an `After` cursor on the sort field taken from the
+/// last row of the previous chunk. The next chunk is only fetched when the
+/// consumer polls for it, so a slow client holds back the queries.
+///
+/// `ExportOptions::key_field` is the chunks' tie breaker, so a non-unique
+/// sort field neither skips nor repeats rows across chunk boundaries. Date-time
+/// strings in the rows become `CursorValue::DateTime` cursors, bound as
+/// timestamps. When the sort or key value of a chunk's last row is null, the
+/// remaining chunks continue by offset in the same order. Rows are pruned to
+/// `params.fields` when a selection was requested.
+///
+/// The first chunk fails when the sort or key field is missing from its
+/// rows, even if it is the only chunk, so the error never follows written
+/// rows.
+pub fn export_stream<T, F, Fut>(
+    params: &PaginationParams,
+    options: &ExportOptions,
+    fetch: F,
+) -> ExportStream
+where
+    T: Serialize,
+    F: FnMut(PaginationParams) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>> + Send + 'static,
+{
+    let sort_field = params
+        .sort_by
+        .clone()
+        .unwrap_or_else(|| options.key_field.clone());
+    let base = PaginationParams {
+        page: 1,
+        per_page: options.chunk_size,
+        sort_by: Some(sort_field.clone()),
+        sort_direction: Some(params.sort_direction.clone().unwrap_or(SortDirection::Asc)),
+        disable_total_count: true,
+        cursor: None,
+        tie_breaker: Some(options.key_field.clone()),
+        ..params.clone()
+    };
+
+    let state = ExportState {
+        fetch,
+        base,
+        sort_field,
+        format: options.format,
+        remaining: options.max_rows,
+        header: None,
+        emitted: 0,
+        keyset: true,
+        done: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        if state.done || state.remaining == Some(0) {
+            return None;
+        }
+        let chunk = state.next_chunk().await;
+        if chunk.is_err() {
+            state.done = true;
+        }
+        Some((chunk, state))
+    })
+    .boxed()
+}
+
+struct ExportState<F> {
+    fetch: F,
+    base: PaginationParams,
+    sort_field: String,
+    format: ExportFormat,
+    remaining: Option<usize>,
+    header: Option<Vec<String>>,
+    /// Rows fetched so far, the offset of the next chunk once paging by
+    /// offset.
+    emitted: usize,
+    keyset: bool,
+    done: bool,
+}
+
+impl<F> ExportState<F> {
+    async fn next_chunk<T, Fut>(&mut self) -> Result<Vec<u8>, PaginatorError>
+    where
+        T: Serialize,
+        F: FnMut(PaginationParams) -> Fut,
+        Fut: Future<Output = Result<PaginatorResponse<T>, PaginatorError>>,
+    {
+        let response = (self.fetch)(self.base.clone()).await?;
+        let mut rows = response.data;
+        if let Some(remaining) = self.remaining.as_mut() {
+            rows.truncate(*remaining);
+            *remaining -= rows.len();
+        }
+        if rows.is_empty() || !response.meta.has_next {
+            self.done = true;
+        }
+
+        let first = self.emitted == 0;
+        self.emitted += rows.len();
+
+        if let Some(last) = rows.last().filter(|_| first || !self.done) {
+            let cursor = if self.keyset {
+                self.cursor_after(&OrderedRow::from_serialize(last)?)?
+            } else {
+                None
+            };
+            match cursor {
+                Some(cursor) => self.base.cursor = Some(cursor),
+                // Every chunk so far was full, so the rows fetched are an
+                // exact number of pages.
+                None => {
+                    self.keyset = false;
+                    self.base.cursor = None;
+                    self.base.page = (self.emitted / self.base.per_page as usize) as u32 + 1;
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        if self.base.fields.is_some() {
+            // Pruned only after the cursor was read, so the key field does
+            // not have to be part of the selection.
+            let rows = rows
+                .iter()
+                .map(|row| {
+                    let mut row = OrderedRow::from_serialize(row)?;
+                    row.retain(|field| self.base.includes_field(field));
+                    Ok(row)
+                })
+                .collect::<Result<Vec<_>, PaginatorError>>()?;
+            self.write_rows(&rows, &mut out)?;
+        } else {
+            self.write_rows(&rows, &mut out)?;
+        }
+        Ok(out)
+    }
+
+    /// Cursor past `last`, or `None` when its sort or key value is null and
+    /// cannot be compared past.
+    fn cursor_after(&self, last: &OrderedRow) -> Result<Option<Cursor>, PaginatorError> {
+        let Some(value) = cursor_value(last, &self.sort_field)? else {
+            return Ok(None);
+        };
+        let mut cursor = Cursor::new(self.sort_field.clone(), value, CursorDirection::After);
+        if let Some(key) = self.base.tie_breaker_field() {
+            match cursor_value(last, key)? {
+                Some(tie_value) => cursor = cursor.with_tie_value(tie_value),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(cursor))
+    }
+
+    fn write_rows<R: Serialize>(
+        &mut self,
+        rows: &[R],
+        out: &mut Vec<u8>,
+    ) -> Result<(), PaginatorError> {
+        match self.format {
+            ExportFormat::Csv => write_csv_rows(rows, &mut self.header, out),
+            ExportFormat::Ndjson => write_ndjson_rows(rows, out),
+        }
+    }
+}
+
+/// Reads the keyset value of `field` from a serialized row, `None` when it
+/// is null.
+fn cursor_value(row: &OrderedRow, field: &str) -> Result<Option<CursorValue>, PaginatorError> {
+    // Qualified sort fields such as `u.id` appear unqualified in rows.
+    let column = field.rsplit('.').next().unwrap_or(field);
+    match row.get(column) {
+        Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => match n.as_i64() {
+            Some(i) => Ok(Some(CursorValue::Int(i))),
+            None => Ok(Some(CursorValue::Float(n.as_f64().unwrap_or_default()))),
+        },
+        Some(Value::String(s)) => Ok(Some(string_cursor_value(s))),
+        Some(_) => Err(PaginatorError::Custom(format!(
+            "Export field '{}' must be a number, string or date-time",
+            field
+        ))),
+        None => Err(PaginatorError::Custom(format!(
+            "Export field '{}' is missing from the rows",
+            field
+        ))),
+    }
+}
+
+/// Timestamps serialize as strings; they have to be bound as timestamps
+/// again to compare with their column. Date-times without an offset are
+/// taken as UTC.
+fn string_cursor_value(s: &str) -> CursorValue {
+    if let Ok(at) = DateTime::parse_from_rfc3339(s) {
+        return CursorValue::DateTime(at.with_timezone(&Utc));
+    }
+    match NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+        Ok(at) => CursorValue::DateTime(at.and_utc()),
+        Err(_) => CursorValue::String(s.to_string()),
+    }
+}
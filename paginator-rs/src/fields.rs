@@ -0,0 +1,50 @@
+use crate::error::PaginatorError;
+use crate::format::OrderedRow;
+use paginator_utils::{PaginationParams, PaginatorResponse};
+use serde::Serialize;
+use serde_json::Value;
+
+/// A row reduced to the fields selected by `PaginationParams::fields`,
+/// serialized as an object in the row's original field order.
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct SparseRow(OrderedRow);
+
+impl SparseRow {
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.0.get(field)
+    }
+
+    /// Names of the fields left in the row.
+    pub fn fields(&self) -> Vec<String> {
+        self.0.keys()
+    }
+}
+
+/// Prunes every row of `response` down to `params.fields`.
+///
+/// This is the fallback for backends that cannot project columns themselves,
+/// and the way to drop the extra columns a projection keeps for ordering.
+/// Rows keep all their fields when no selection was requested. Validate the
+/// selection with `PaginationParams::validate_fields` first; names the rows
+/// do not have are simply absent from the output.
+pub fn select_fields<T: Serialize>(
+    response: PaginatorResponse<T>,
+    params: &PaginationParams,
+) -> Result<PaginatorResponse<SparseRow>, PaginatorError> {
+    let data = response
+        .data
+        .iter()
+        .map(|row| {
+            let mut row = OrderedRow::from_serialize(row)?;
+            row.retain(|field| params.includes_field(field));
+            Ok(SparseRow(row))
+        })
+        .collect::<Result<_, PaginatorError>>()?;
+
+    Ok(PaginatorResponse {
+        data,
+        meta: response.meta,
+        links: response.links,
+    })
+}
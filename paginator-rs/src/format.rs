@@ -0,0 +1,265 @@
+use crate::error::{ErrorCode, PaginatorError, ValidationError};
+use paginator_utils::{PaginatorResponse, PaginatorResponseMeta, TotalKind};
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Body format of a paginated response, negotiated from the `Accept` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    MessagePack,
+    /// Rows only; the pagination meta travels in headers.
+    Csv,
+    /// Rows only; the pagination meta travels in headers.
+    Ndjson,
+}
+
+impl ResponseFormat {
+    /// Picks the preferred supported format from an `Accept` header value.
+    ///
+    /// A missing or empty header and wildcards select JSON. Returns `None`
+    /// when none of the listed media types is supported.
+    pub fn from_accept(accept: Option<&str>) -> Option<Self> {
+        let accept = match accept.map(str::trim) {
+            None | Some("") => return Some(ResponseFormat::Json),
+            Some(accept) => accept,
+        };
+
+        let mut ranges: Vec<(f32, Self)> = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let media_type = parts.next()?.trim().to_ascii_lowercase();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                let format = Self::from_media_type(&media_type)?;
+                (quality > 0.0).then_some((quality, format))
+            })
+            .collect();
+        // Stable, so equally weighted types keep the client's order.
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranges.first().map(|(_, format)| *format)
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "application/json" | "application/*" | "*/*" => Some(ResponseFormat::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(ResponseFormat::MessagePack)
+            }
+            "text/csv" | "text/*" => Some(ResponseFormat::Csv),
+            "application/x-ndjson" | "application/ndjson" => Some(ResponseFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// The error for an `Accept` header that `from_accept` cannot satisfy,
+    /// listing the supported media types.
+    pub fn not_acceptable() -> PaginatorError {
+        ValidationError::new(
+            ErrorCode::NotAcceptable,
+            "None of the accepted media types is supported",
+        )
+        .parameter("Accept")
+        .allowed([
+            "application/json",
+            "application/msgpack",
+            "text/csv",
+            "application/x-ndjson",
+        ])
+        .into()
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ResponseFormat::Json => "application/json",
+            ResponseFormat::MessagePack => "application/msgpack",
+            ResponseFormat::Csv => "text/csv; charset=utf-8",
+            ResponseFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    /// Whether the body carries the `{ data, meta }` envelope.
+    pub fn has_envelope(&self) -> bool {
+        matches!(self, ResponseFormat::Json | ResponseFormat::MessagePack)
+    }
+
+    /// Serializes `response` in this format. CSV and NDJSON bodies hold the
+    /// rows only.
+    pub fn encode<T: Serialize>(
+        &self,
+        response: &PaginatorResponse<T>,
+    ) -> Result<Vec<u8>, PaginatorError> {
+        let mut out = Vec::new();
+        match self {
+            ResponseFormat::Json => serde_json::to_writer(&mut out, response)
+                .map_err(|e| PaginatorError::SerializationError(e.to_string()))?,
+            ResponseFormat::MessagePack => rmp_serde::encode::write_named(&mut out, response)
+                .map_err(|e| PaginatorError::SerializationError(e.to_string()))?,
+            ResponseFormat::Csv => write_csv_rows(&response.data, &mut None, &mut out)?,
+            ResponseFormat::Ndjson => write_ndjson_rows(&response.data, &mut out)?,
+        }
+        Ok(out)
+    }
+}
+
+/// Pagination headers for `meta`: `X-Total-Count`, `X-Total-Pages`,
+/// `X-Total-Kind`, `X-Current-Page`, `X-Per-Page`, `X-Has-Next`, `X-Has-Prev`,
+/// `X-Next-Cursor` and `X-Prev-Cursor`, omitting those without a value.
+pub fn meta_headers(meta: &PaginatorResponseMeta) -> Vec<(&'static str, String)> {
+    let mut headers = Vec::new();
+    if let Some(total) = meta.total {
+        headers.push(("X-Total-Count", total.to_string()));
+    }
+    if let Some(total_pages) = meta.total_pages {
+        headers.push(("X-Total-Pages", total_pages.to_string()));
+    }
+    if meta.total.is_some() && meta.total_kind != TotalKind::Exact {
+        let kind = serde_json::to_value(meta.total_kind)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default();
+        headers.push(("X-Total-Kind", kind));
+    }
+    headers.push(("X-Current-Page", meta.page.to_string()));
+    headers.push(("X-Per-Page", meta.per_page.to_string()));
+    headers.push(("X-Has-Next", meta.has_next.to_string()));
+    headers.push(("X-Has-Prev", meta.has_prev.to_string()));
+    if let Some(cursor) = &meta.next_cursor {
+        headers.push(("X-Next-Cursor", cursor.clone()));
+    }
+    if let Some(cursor) = &meta.prev_cursor {
+        headers.push(("X-Prev-Cursor", cursor.clone()));
+    }
+    headers
+}
+
+/// Appends `rows` as CSV records, writing the header row first if `header`
+/// is still unset. Columns follow the field order of the first row.
+pub(crate) fn write_csv_rows<T: Serialize>(
+    rows: &[T],
+    header: &mut Option<Vec<String>>,
+    out: &mut Vec<u8>,
+) -> Result<(), PaginatorError> {
+    for row in rows {
+        let row = OrderedRow::from_serialize(row)?;
+        let header = header.get_or_insert_with(|| {
+            let header = row.keys();
+            write_csv_record(out, header.iter().map(|key| key.as_str()));
+            header
+        });
+        row.write_csv_record(header, out);
+    }
+    Ok(())
+}
+
+/// Appends `rows` as newline-delimited JSON.
+pub(crate) fn write_ndjson_rows<T: Serialize>(
+    rows: &[T],
+    out: &mut Vec<u8>,
+) -> Result<(), PaginatorError> {
+    for row in rows {
+        serde_json::to_writer(&mut *out, row)
+            .map_err(|e| PaginatorError::SerializationError(e.to_string()))?;
+        out.push(b'\n');
+    }
+    Ok(())
+}
+
+/// A serialized row with its fields in declaration order.
+pub(crate) struct OrderedRow(Vec<(String, Value)>);
+
+impl OrderedRow {
+    pub(crate) fn from_serialize<T: Serialize>(row: &T) -> Result<Self, PaginatorError> {
+        // Going through the JSON text keeps field order without requiring
+        // serde_json's `preserve_order` feature.
+        let json = serde_json::to_vec(row)
+            .map_err(|e| PaginatorError::SerializationError(e.to_string()))?;
+        serde_json::from_slice(&json).map_err(|e| PaginatorError::SerializationError(e.to_string()))
+    }
+
+    pub(crate) fn keys(&self) -> Vec<String> {
+        self.0.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Drops every field for which `keep` returns `false`.
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.0.retain(|(key, _)| keep(key));
+    }
+
+    pub(crate) fn write_csv_record(&self, header: &[String], out: &mut Vec<u8>) {
+        let cells: Vec<String> = header
+            .iter()
+            .map(|key| match self.get(key) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+            })
+            .collect();
+        write_csv_record(out, cells.iter().map(String::as_str));
+    }
+}
+
+impl Serialize for OrderedRow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderedRow {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedRowVisitor;
+
+        impl<'de> Visitor<'de> for OrderedRowVisitor {
+            type Value = OrderedRow;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a row serialized as a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OrderedRow, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(entry) = map.next_entry::<String, Value>()? {
+                    fields.push(entry);
+                }
+                Ok(OrderedRow(fields))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedRowVisitor)
+    }
+}
+
+/// Writes one CSV record, quoting cells that contain separators, quotes or
+/// line breaks.
+fn write_csv_record<'a>(out: &mut Vec<u8>, cells: impl Iterator<Item = &'a str>) {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            out.push(b'"');
+            out.extend_from_slice(cell.replace('"', "\"\"").as_bytes());
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(cell.as_bytes());
+        }
+    }
+    out.extend_from_slice(b"\r\n");
+}
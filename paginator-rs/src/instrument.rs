@@ -0,0 +1,129 @@
+use paginator_utils::{CursorDirection, PaginationParams};
+use std::future::Future;
+
+/// Span around one paginated query, used by the backend crates.
+///
+/// With the `tracing` feature enabled it records a `paginate` span with the
+/// request shape (page, per_page, filters, cursor mode) and the outcome (rows,
+/// total), plus one `paginate.phase` child span per count and data phase with
+/// its duration. Without the feature every method is a no-op.
+#[derive(Clone, Debug)]
+pub struct PaginationSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl PaginationSpan {
+    /// Opens the span. Filter values are replaced by `?` unless
+    /// `include_filter_values` is set.
+    pub fn new(
+        backend: &'static str,
+        params: &PaginationParams,
+        include_filter_values: bool,
+    ) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::info_span!(
+                "paginate",
+                backend,
+                page = params.page,
+                per_page = params.per_page,
+                filter_count = params.filters.len(),
+                filters = %describe_filters(params, include_filter_values),
+                search = params.search.is_some(),
+                cursor = cursor_mode(params),
+                rows = tracing::field::Empty,
+                total = tracing::field::Empty,
+            );
+            Self { span }
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (backend, params, include_filter_values);
+            Self {}
+        }
+    }
+
+    /// Runs `future` inside the span.
+    pub async fn run<F: Future>(&self, future: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        {
+            use tracing::Instrument;
+            future.instrument(self.span.clone()).await
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            future.await
+        }
+    }
+
+    /// Runs one phase (`"count"` or `"data"`) inside a child span and records
+    /// its duration in milliseconds.
+    pub async fn phase<F: Future>(&self, name: &'static str, future: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        {
+            use tracing::Instrument;
+            let span = tracing::info_span!(
+                parent: &self.span,
+                "paginate.phase",
+                phase = name,
+                duration_ms = tracing::field::Empty,
+            );
+            let started = std::time::Instant::now();
+            let output = future.instrument(span.clone()).await;
+            span.record("duration_ms", started.elapsed().as_secs_f64() * 1000.0);
+            output
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = name;
+            future.await
+        }
+    }
+
+    /// Records the number of rows returned and the total, if one was computed.
+    pub fn record_result(&self, rows: usize, total: Option<u32>) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("rows", rows);
+            if let Some(total) = total {
+                self.span.record("total", total);
+            }
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (rows, total);
+        }
+    }
+}
+
+/// Summarizes filters as `field operator value`, with values redacted to `?`
+/// unless `include_values` is set.
+pub fn describe_filters(params: &PaginationParams, include_values: bool) -> String {
+    params
+        .filters
+        .iter()
+        .map(|filter| {
+            let operator = serde_json::to_value(&filter.operator)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default();
+            let value = if include_values {
+                filter.value.to_sql_string()
+            } else {
+                "?".to_string()
+            };
+            format!("{} {} {}", filter.field, operator, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `"none"`, `"after"` or `"before"`, depending on the cursor of `params`.
+pub fn cursor_mode(params: &PaginationParams) -> &'static str {
+    match params.cursor.as_ref().map(|c| &c.direction) {
+        None => "none",
+        Some(CursorDirection::After) => "after",
+        Some(CursorDirection::Before) => "before",
+    }
+}
@@ -1,11 +1,34 @@
 pub use paginator_utils::*;
 
 mod builder;
+mod cache;
 mod error;
+mod export;
+mod fields;
+mod format;
+mod instrument;
+mod link;
+mod problem;
+mod query;
+mod source;
+mod strict;
 mod trait_impl;
 
 pub use builder::{
     CursorBuilder, FilterBuilder, Paginator, PaginatorBuilder, SearchBuilder, SortBuilder,
 };
-pub use error::{PaginatorError, PaginatorResult};
+pub use cache::{count_fingerprint, count_fingerprint_with, CountCache};
+pub use error::{ErrorCode, PaginatorError, PaginatorResult, ValidationError};
+pub use export::{export_stream, ExportBody, ExportFormat, ExportOptions, ExportStream};
+pub use fields::{select_fields, SparseRow};
+pub use format::{meta_headers, ResponseFormat};
+pub use instrument::{cursor_mode, describe_filters, PaginationSpan};
+pub use link::{link_header, page_links, response_links, PageLink};
+pub use problem::{ProblemDetails, PROBLEM_CONTENT_TYPE};
+pub use query::{
+    parse_filter, parse_filter_with, parse_near, parse_query, query_pairs, try_parse_filter_with,
+    PaginationConfig, QUERY_KEYS,
+};
+pub use source::PaginatedSource;
+pub use strict::ParseMode;
 pub use trait_impl::PaginatorTrait;
@@ -0,0 +1,130 @@
+use crate::query::query_pairs;
+use paginator_utils::{PaginationLinks, PaginationParams, PaginatorResponseMeta};
+
+/// A link to another page of the current request, relative to the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageLink {
+    /// `first`, `prev`, `next` or `last`.
+    pub rel: &'static str,
+    pub href: String,
+}
+
+/// Links to the first, previous, next and last pages of a request to `path`
+/// with `query`, the raw query string.
+///
+/// Every other query parameter, such as `filter` or `sort_by`, is kept. When
+/// `meta` carries cursors, `prev` and `next` page with `cursor` instead of
+/// `page`. `last` needs a page count, so it is missing without a total.
+pub fn page_links(path: &str, query: Option<&str>, meta: &PaginatorResponseMeta) -> Vec<PageLink> {
+    let kept: Vec<&str> = query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !pair.is_empty() && !matches!(key, "page" | "per_page" | "cursor")
+        })
+        .collect();
+    let href = |position: String| {
+        let mut pairs = vec![position, format!("per_page={}", meta.per_page)];
+        pairs.extend(kept.iter().map(|pair| pair.to_string()));
+        format!("{}?{}", path, pairs.join("&"))
+    };
+    let page = |page: u32| href(format!("page={}", page));
+    let cursor = |cursor: &str| href(format!("cursor={}", encode_component(cursor, b"")));
+
+    let mut links = vec![PageLink {
+        rel: "first",
+        href: page(1),
+    }];
+    let prev = match &meta.prev_cursor {
+        Some(prev) => Some(cursor(prev)),
+        None if meta.has_prev && meta.page > 1 => Some(page(meta.page - 1)),
+        None => None,
+    };
+    if let Some(href) = prev {
+        links.push(PageLink { rel: "prev", href });
+    }
+    let next = match &meta.next_cursor {
+        Some(next) => Some(cursor(next)),
+        None if meta.has_next => Some(page(meta.page + 1)),
+        None => None,
+    };
+    if let Some(href) = next {
+        links.push(PageLink { rel: "next", href });
+    }
+    if let Some(total_pages) = meta.total_pages.filter(|pages| *pages > 0) {
+        links.push(PageLink {
+            rel: "last",
+            href: page(total_pages),
+        });
+    }
+    links
+}
+
+/// Links for the `links` object of a response to `path`, written from the
+/// full `params` rather than the raw query string.
+///
+/// `self` repeats the request, by cursor when it had one. The others are
+/// those of `page_links`.
+pub fn response_links(
+    path: &str,
+    params: &PaginationParams,
+    meta: &PaginatorResponseMeta,
+) -> PaginationLinks {
+    let query = query_pairs(params)
+        .into_iter()
+        .filter(|(key, _)| !matches!(*key, "page" | "per_page" | "cursor"))
+        .map(|(key, value)| format!("{}={}", key, encode_component(&value, b":,")))
+        .collect::<Vec<_>>()
+        .join("&");
+    let position = match params.cursor.as_ref().and_then(|c| c.encode().ok()) {
+        Some(cursor) => format!("cursor={}", encode_component(&cursor, b"")),
+        None => format!("page={}", meta.page),
+    };
+    let mut self_link = format!("{}?{}&per_page={}", path, position, meta.per_page);
+    if !query.is_empty() {
+        self_link.push('&');
+        self_link.push_str(&query);
+    }
+
+    let mut links = PaginationLinks {
+        self_link,
+        first: String::new(),
+        prev: None,
+        next: None,
+        last: None,
+    };
+    for link in page_links(path, Some(&query), meta) {
+        match link.rel {
+            "first" => links.first = link.href,
+            "prev" => links.prev = Some(link.href),
+            "next" => links.next = Some(link.href),
+            _ => links.last = Some(link.href),
+        }
+    }
+    links
+}
+
+/// Formats `links` as an RFC 8288 `Link` header value.
+pub fn link_header(links: &[PageLink]) -> String {
+    links
+        .iter()
+        .map(|link| format!("<{}>; rel=\"{}\"", link.href, link.rel))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Percent-encodes everything but unreserved characters and `keep`, so base64
+/// cursors and filter values survive as query values.
+fn encode_component(value: &str, keep: &[u8]) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ if keep.contains(&byte) => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
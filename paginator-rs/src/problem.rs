@@ -0,0 +1,83 @@
+use crate::error::{ErrorCode, PaginatorError, ValidationError};
+use serde::{Deserialize, Serialize};
+
+/// Content type of `ProblemDetails` bodies.
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// An RFC 7807 problem document describing a `PaginatorError`.
+///
+/// The problem `type` is `about:blank`, so `title` is the HTTP reason
+/// phrase; `code`, `parameter` and `allowed` are extension members. Server
+/// errors keep their message out of `detail`, since it may quote database
+/// errors.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: ErrorCode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameter: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
+}
+
+impl ProblemDetails {
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+}
+
+impl From<&PaginatorError> for ProblemDetails {
+    fn from(error: &PaginatorError) -> Self {
+        let status = error.status();
+        let detail = if status >= 500 {
+            "The request could not be completed".to_string()
+        } else {
+            error.to_string()
+        };
+        let (parameter, allowed) = match error {
+            PaginatorError::InvalidPage(_) => (Some("page".to_string()), Vec::new()),
+            PaginatorError::InvalidPerPage(_) => (Some("per_page".to_string()), Vec::new()),
+            PaginatorError::Validation(error) => (error.parameter.clone(), error.allowed.clone()),
+            _ => (None, Vec::new()),
+        };
+
+        Self {
+            problem_type: "about:blank".to_string(),
+            title: reason_phrase(status).to_string(),
+            status,
+            detail,
+            code: error.code(),
+            parameter,
+            allowed,
+        }
+    }
+}
+
+/// Turns a problem received from a server back into the error it describes,
+/// so clients see the same `ErrorCode` and parameter.
+impl From<ProblemDetails> for PaginatorError {
+    fn from(problem: ProblemDetails) -> Self {
+        match problem.code {
+            ErrorCode::SerializationError => PaginatorError::SerializationError(problem.detail),
+            ErrorCode::Internal => PaginatorError::Custom(problem.detail),
+            code => PaginatorError::Validation(ValidationError {
+                code,
+                parameter: problem.parameter,
+                message: problem.detail,
+                allowed: problem.allowed,
+            }),
+        }
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        400 => "Bad Request",
+        406 => "Not Acceptable",
+        _ => "Internal Server Error",
+    }
+}
@@ -0,0 +1,623 @@
+use crate::error::{ErrorCode, PaginatorError, PaginatorResult, ValidationError};
+use crate::strict::ParseMode;
+use paginator_utils::chrono::{DateTime, SecondsFormat, Utc};
+use paginator_utils::{
+    parse_fields, parse_time_zone, CountStrategy, Cursor, DateContext, DateSource, DistanceSort,
+    Filter, FilterOperator, FilterValue, GeoBox, GeoPoint, PaginationParams, RelativeDate,
+    SearchParams, SortDirection, Tz, DISTANCE_COLUMN,
+};
+
+/// Query keys `parse_query` reads.
+pub const QUERY_KEYS: &[&str] = &[
+    "page",
+    "per_page",
+    "sort_by",
+    "sort_direction",
+    "filter",
+    "search",
+    "search_fields",
+    "fields",
+    "tz",
+    "near",
+    "cursor",
+    "count",
+];
+
+/// Settings for turning a query string into `PaginationParams`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaginationConfig {
+    pub mode: ParseMode,
+    /// Page size when the query has no `per_page`.
+    pub default_per_page: u32,
+    /// Largest `per_page` accepted, at most 100.
+    pub max_per_page: u32,
+    /// Fields `fields` may select; `None` allows any.
+    pub allowed_fields: Option<Vec<String>>,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            mode: ParseMode::Lenient,
+            default_per_page: 20,
+            max_per_page: 100,
+            allowed_fields: None,
+        }
+    }
+}
+
+impl PaginationConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the default page size, clamped to 1-100.
+    pub fn default_per_page(mut self, per_page: u32) -> Self {
+        self.default_per_page = per_page.clamp(1, 100);
+        self
+    }
+
+    /// Sets the largest page size, clamped to 1-100. Larger `per_page`
+    /// values are lowered to it, or rejected in strict mode.
+    pub fn max_per_page(mut self, per_page: u32) -> Self {
+        self.max_per_page = per_page.clamp(1, 100);
+        self
+    }
+
+    /// Restricts `fields` selections to `fields`. Other names are rejected
+    /// in either mode, since dropping them would widen the response.
+    pub fn allowed_fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_fields = Some(fields.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+/// Builds `PaginationParams` from decoded query pairs.
+///
+/// Reads the keys in `QUERY_KEYS`; `filter` may repeat, and for other keys
+/// the last value wins. `cursor` takes an encoded `Cursor`, and `count` one of
+/// `exact`, `estimated`, `capped:<n>` or `none`. Invalid `tz` and `near`
+/// values and fields outside `config.allowed_fields` are errors in either
+/// mode; everything else follows `config.mode`.
+pub fn parse_query<'a, I>(pairs: I, config: &PaginationConfig) -> PaginatorResult<PaginationParams>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mode = config.mode;
+    let pairs: Vec<(&str, &str)> = pairs.into_iter().collect();
+    mode.check_keys(pairs.iter().map(|(key, _)| *key), QUERY_KEYS)?;
+
+    let max_per_page = config.max_per_page.clamp(1, 100);
+    let mut params = PaginationParams {
+        per_page: config.default_per_page.min(max_per_page),
+        ..Default::default()
+    };
+    let mut filters = Vec::new();
+    let mut search = None;
+    let mut search_fields = None;
+    let mut tz = None;
+    let mut near = None;
+
+    for (key, value) in pairs {
+        match key {
+            "page" => {
+                if let Some(page) = parse_number(key, value, mode)? {
+                    params.page = mode.page(page)?;
+                }
+            }
+            "per_page" => {
+                if let Some(per_page) = parse_number(key, value, mode)? {
+                    params.per_page = mode.per_page(per_page)?;
+                    if params.per_page > max_per_page {
+                        if mode.is_strict() {
+                            return Err(ValidationError::new(
+                                ErrorCode::InvalidPerPage,
+                                format!(
+                                    "Invalid per_page value: {}. Must be between 1 and {}",
+                                    per_page, max_per_page
+                                ),
+                            )
+                            .parameter("per_page")
+                            .into());
+                        }
+                        params.per_page = max_per_page;
+                    }
+                }
+            }
+            "sort_by" => params.sort_by = Some(value.to_string()),
+            "sort_direction" => params.sort_direction = mode.sort_direction(value)?,
+            "filter" => filters.push(value),
+            "search" => search = Some(value),
+            "search_fields" => search_fields = Some(value),
+            "fields" => params.fields = Some(parse_fields(value)),
+            "tz" => tz = Some(value),
+            "near" => near = Some(value),
+            "cursor" => params.cursor = parse_cursor(value, mode)?,
+            "count" => parse_count(value, mode, &mut params)?,
+            _ => {}
+        }
+    }
+
+    if let Some(ref allowed) = config.allowed_fields {
+        let names: Vec<&str> = allowed.iter().map(String::as_str).collect();
+        params.validate_fields(&names).map_err(|e| {
+            PaginatorError::from(
+                ValidationError::new(ErrorCode::InvalidField, e)
+                    .parameter("fields")
+                    .allowed(allowed.iter().cloned()),
+            )
+        })?;
+    }
+
+    let time_zone = match tz {
+        Some(name) => parse_time_zone(name).map_err(|e| {
+            PaginatorError::from(
+                ValidationError::new(ErrorCode::InvalidParameter, e).parameter("tz"),
+            )
+        })?,
+        None => Tz::UTC,
+    };
+    let dates = DateContext::new(time_zone);
+
+    if let Some(near) = near {
+        let (field, origin) = parse_near(near).ok_or_else(|| {
+            ValidationError::new(
+                ErrorCode::InvalidParameter,
+                format!("Invalid near '{}': expected field:lon,lat", near),
+            )
+            .parameter("near")
+        })?;
+        params.distance_sort = Some(DistanceSort::new(field, origin));
+        params
+            .sort_by
+            .get_or_insert_with(|| DISTANCE_COLUMN.to_string());
+    }
+
+    for (index, filter) in filters.into_iter().enumerate() {
+        match try_parse_filter_with(filter, &dates) {
+            Ok(filter) => params.filters.push(filter),
+            Err(PaginatorError::Validation(error)) if mode.is_strict() => {
+                return Err(error.parameter(format!("filter[{}]", index)).into())
+            }
+            Err(_) => {}
+        }
+    }
+
+    if let Some(query) = search {
+        let fields: Vec<String> = search_fields
+            .map(|f| f.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default();
+
+        if !fields.is_empty() {
+            params.search = Some(SearchParams {
+                query: query.to_string(),
+                fields,
+                case_sensitive: false,
+                exact_match: false,
+            });
+        }
+    }
+
+    Ok(params)
+}
+
+/// The query pairs `parse_query` turns back into `params`, for links and
+/// clients.
+///
+/// Relative dates in filters are written as the RFC 3339 timestamps they
+/// resolved to, so `tz` is never needed. Search options other than the query
+/// and fields have no query key and are left out.
+pub fn query_pairs(params: &PaginationParams) -> Vec<(&'static str, String)> {
+    let mut pairs = vec![
+        ("page", params.page.to_string()),
+        ("per_page", params.per_page.to_string()),
+    ];
+    if let Some(sort_by) = &params.sort_by {
+        pairs.push(("sort_by", sort_by.clone()));
+    }
+    if let Some(direction) = &params.sort_direction {
+        let direction = match direction {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        };
+        pairs.push(("sort_direction", direction.to_string()));
+    }
+    for filter in &params.filters {
+        pairs.push(("filter", filter_query_value(filter)));
+    }
+    if let Some(search) = &params.search {
+        pairs.push(("search", search.query.clone()));
+        pairs.push(("search_fields", search.fields.join(",")));
+    }
+    if let Some(fields) = &params.fields {
+        pairs.push(("fields", fields.join(",")));
+    }
+    if let Some(near) = &params.distance_sort {
+        pairs.push((
+            "near",
+            format!("{}:{},{}", near.field, near.origin.lon, near.origin.lat),
+        ));
+    }
+    if let Some(cursor) = params.cursor.as_ref().and_then(|c| c.encode().ok()) {
+        pairs.push(("cursor", cursor));
+    }
+    let count = match params.count_strategy {
+        _ if params.disable_total_count => Some("none".to_string()),
+        CountStrategy::Exact => None,
+        CountStrategy::Estimated => Some("estimated".to_string()),
+        CountStrategy::Capped(cap) => Some(format!("capped:{}", cap)),
+    };
+    if let Some(count) = count {
+        pairs.push(("count", count));
+    }
+    pairs
+}
+
+/// Writes `filter` as `field:operator:value`.
+fn filter_query_value(filter: &Filter) -> String {
+    let operator = OPERATORS
+        .iter()
+        .find(|(_, operator)| *operator == filter.operator)
+        .map(|(name, _)| *name)
+        .unwrap_or_default();
+    if filter.value == FilterValue::Null {
+        return format!("{}:{}", filter.field, operator);
+    }
+    format!(
+        "{}:{}:{}",
+        filter.field,
+        operator,
+        filter_value_text(&filter.value)
+    )
+}
+
+fn filter_value_text(value: &FilterValue) -> String {
+    let points = |ring: &[GeoPoint]| {
+        ring.iter()
+            .map(|point| format!("{},{}", point.lon, point.lat))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    match value {
+        FilterValue::String(s) => s.clone(),
+        FilterValue::Int(i) => i.to_string(),
+        FilterValue::Float(f) => f.to_string(),
+        FilterValue::Bool(b) => b.to_string(),
+        FilterValue::DateTime(dt) => dt.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        FilterValue::Point(point) => points(std::slice::from_ref(point)),
+        FilterValue::BoundingBox(bbox) => points(&[bbox.min, bbox.max]),
+        FilterValue::Polygon(ring) => points(ring),
+        FilterValue::Array(values) => values
+            .iter()
+            .map(filter_value_text)
+            .collect::<Vec<_>>()
+            .join(","),
+        FilterValue::Null => String::new(),
+    }
+}
+
+fn parse_number(key: &str, value: &str, mode: ParseMode) -> PaginatorResult<Option<u32>> {
+    match value.parse::<u32>() {
+        Ok(number) => Ok(Some(number)),
+        Err(_) if mode.is_strict() => {
+            let code = if key == "page" {
+                ErrorCode::InvalidPage
+            } else {
+                ErrorCode::InvalidPerPage
+            };
+            Err(ValidationError::new(
+                code,
+                format!("Invalid {} '{}': expected a positive integer", key, value),
+            )
+            .parameter(key)
+            .into())
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+fn parse_cursor(value: &str, mode: ParseMode) -> PaginatorResult<Option<Cursor>> {
+    match Cursor::decode(value) {
+        Ok(cursor) => Ok(Some(cursor)),
+        Err(e) if mode.is_strict() => Err(ValidationError::new(
+            ErrorCode::InvalidCursor,
+            format!("Invalid cursor: {}", e),
+        )
+        .parameter("cursor")
+        .into()),
+        Err(_) => Ok(None),
+    }
+}
+
+fn parse_count(value: &str, mode: ParseMode, params: &mut PaginationParams) -> PaginatorResult<()> {
+    let strategy = match value {
+        "exact" => Some(CountStrategy::Exact),
+        "estimated" => Some(CountStrategy::Estimated),
+        "none" => {
+            params.disable_total_count = true;
+            return Ok(());
+        }
+        _ => value
+            .strip_prefix("capped:")
+            .and_then(|cap| cap.parse::<u32>().ok())
+            .map(CountStrategy::Capped),
+    };
+
+    match strategy {
+        Some(strategy) => params.count_strategy = strategy,
+        None if mode.is_strict() => {
+            return Err(ValidationError::new(
+                ErrorCode::InvalidParameter,
+                format!("Invalid count '{}'", value),
+            )
+            .parameter("count")
+            .allowed(["exact", "estimated", "capped:<n>", "none"])
+            .into())
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Query string names of the filter operators.
+const OPERATORS: &[(&str, FilterOperator)] = &[
+    ("eq", FilterOperator::Eq),
+    ("ne", FilterOperator::Ne),
+    ("gt", FilterOperator::Gt),
+    ("lt", FilterOperator::Lt),
+    ("gte", FilterOperator::Gte),
+    ("lte", FilterOperator::Lte),
+    ("like", FilterOperator::Like),
+    ("ilike", FilterOperator::ILike),
+    ("in", FilterOperator::In),
+    ("not_in", FilterOperator::NotIn),
+    ("is_null", FilterOperator::IsNull),
+    ("is_not_null", FilterOperator::IsNotNull),
+    ("between", FilterOperator::Between),
+    ("contains", FilterOperator::Contains),
+    ("contains_all", FilterOperator::ContainsAll),
+    ("contains_any", FilterOperator::ContainsAny),
+    ("length_eq", FilterOperator::LengthEq),
+    ("length_gt", FilterOperator::LengthGt),
+    ("length_gte", FilterOperator::LengthGte),
+    ("length_lt", FilterOperator::LengthLt),
+    ("length_lte", FilterOperator::LengthLte),
+    ("starts_with", FilterOperator::StartsWith),
+    ("ends_with", FilterOperator::EndsWith),
+    ("ieq", FilterOperator::IEq),
+    ("regex", FilterOperator::Regex),
+    ("within_radius", FilterOperator::WithinRadius),
+    ("within_box", FilterOperator::WithinBox),
+    ("within_polygon", FilterOperator::WithinPolygon),
+];
+
+pub fn parse_filter(filter_str: &str) -> Option<Filter> {
+    parse_filter_with(filter_str, &DateContext::default())
+}
+
+/// Parses `field:operator:value` (`field:is_null` and `field:is_not_null`
+/// need no value), resolving relative date values of range comparisons such
+/// as `created_at:gte:now-7d` or `startOf:month` against `dates`.
+pub fn parse_filter_with(filter_str: &str, dates: &DateContext) -> Option<Filter> {
+    try_parse_filter_with(filter_str, dates).ok()
+}
+
+/// Like `parse_filter_with`, but reports why a filter cannot be parsed.
+///
+/// Unknown operators list the supported ones in the error's `allowed`
+/// values.
+pub fn try_parse_filter_with(filter_str: &str, dates: &DateContext) -> PaginatorResult<Filter> {
+    let parts: Vec<&str> = filter_str.splitn(3, ':').collect();
+    let malformed = || {
+        PaginatorError::from(ValidationError::new(
+            ErrorCode::InvalidFilter,
+            format!(
+                "Invalid filter '{}': expected field:operator:value",
+                filter_str
+            ),
+        ))
+    };
+    if parts.len() < 2 {
+        return Err(malformed());
+    }
+
+    let field = parts[0].to_string();
+    let operator = match OPERATORS.iter().find(|(name, _)| *name == parts[1]) {
+        Some((_, operator)) => operator.clone(),
+        None => {
+            return Err(ValidationError::new(
+                ErrorCode::InvalidFilter,
+                format!("Unknown filter operator '{}'", parts[1]),
+            )
+            .allowed(OPERATORS.iter().map(|(name, _)| *name))
+            .into())
+        }
+    };
+
+    // `is_null` and `is_not_null` need no value.
+    let value_str = match parts.get(2) {
+        Some(value) => *value,
+        None if matches!(operator, FilterOperator::IsNull | FilterOperator::IsNotNull) => "",
+        None => return Err(malformed()),
+    };
+
+    let value = match operator {
+        FilterOperator::IsNull | FilterOperator::IsNotNull => FilterValue::Null,
+        // Text operators take the value verbatim, even when it looks numeric.
+        FilterOperator::StartsWith
+        | FilterOperator::EndsWith
+        | FilterOperator::IEq
+        | FilterOperator::Regex => FilterValue::String(value_str.to_string()),
+        FilterOperator::WithinRadius
+        | FilterOperator::WithinBox
+        | FilterOperator::WithinPolygon => {
+            parse_geo_value(&operator, value_str).ok_or_else(|| {
+                ValidationError::new(
+                    ErrorCode::InvalidFilter,
+                    format!(
+                        "Invalid coordinates '{}' for {}: expected {}",
+                        value_str,
+                        parts[1],
+                        geo_value_format(&operator)
+                    ),
+                )
+            })?
+        }
+        FilterOperator::In
+        | FilterOperator::NotIn
+        | FilterOperator::ContainsAll
+        | FilterOperator::ContainsAny => {
+            let values: Vec<FilterValue> = value_str
+                .split(',')
+                .map(|v| {
+                    let trimmed = v.trim();
+
+                    if let Ok(i) = trimmed.parse::<i64>() {
+                        FilterValue::Int(i)
+                    } else if let Ok(f) = trimmed.parse::<f64>() {
+                        FilterValue::Float(f)
+                    } else if trimmed == "true" || trimmed == "false" {
+                        FilterValue::Bool(trimmed == "true")
+                    } else {
+                        string_or_date(trimmed, &operator, dates)
+                    }
+                })
+                .collect();
+            FilterValue::Array(values)
+        }
+        FilterOperator::Between => {
+            let values: Vec<FilterValue> = value_str
+                .split(',')
+                .map(|v| {
+                    let trimmed = v.trim();
+                    if let Ok(i) = trimmed.parse::<i64>() {
+                        FilterValue::Int(i)
+                    } else if let Ok(f) = trimmed.parse::<f64>() {
+                        FilterValue::Float(f)
+                    } else {
+                        string_or_date(trimmed, &operator, dates)
+                    }
+                })
+                .collect();
+            FilterValue::Array(values)
+        }
+        _ => {
+            if let Ok(i) = value_str.parse::<i64>() {
+                FilterValue::Int(i)
+            } else if let Ok(f) = value_str.parse::<f64>() {
+                FilterValue::Float(f)
+            } else if value_str == "true" || value_str == "false" {
+                FilterValue::Bool(value_str == "true")
+            } else {
+                string_or_date(value_str, &operator, dates)
+            }
+        }
+    };
+
+    let date_source = date_source(value_str, &value, dates);
+    Ok(Filter {
+        field,
+        operator,
+        value,
+        date_source,
+    })
+}
+
+/// The source of a value that resolved at least one relative date.
+fn date_source(value_str: &str, value: &FilterValue, dates: &DateContext) -> Option<DateSource> {
+    let resolved = value
+        .elements()
+        .iter()
+        .any(|v| matches!(v, FilterValue::DateTime(_)));
+    let relative = value_str
+        .split(',')
+        .any(|v| RelativeDate::parse(v.trim()).is_some());
+    (resolved && relative).then(|| DateSource {
+        expression: value_str.to_string(),
+        time_zone: dates.time_zone().name().to_string(),
+    })
+}
+
+/// Parses the comma-separated coordinates of a geo operator, longitude
+/// first: `lon,lat,meters` for a radius, `min_lon,min_lat,max_lon,max_lat`
+/// for a box and `lon,lat,lon,lat,...` (three points or more) for a polygon.
+fn parse_geo_value(operator: &FilterOperator, value: &str) -> Option<FilterValue> {
+    let numbers = value
+        .split(',')
+        .map(|v| v.trim().parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<f64>>>()?;
+    let points = |coords: &[f64]| {
+        coords
+            .chunks(2)
+            .map(|pair| {
+                let point = GeoPoint::new(pair[0], pair[1]);
+                point.validate().ok().map(|_| point)
+            })
+            .collect::<Option<Vec<GeoPoint>>>()
+    };
+
+    match (operator, numbers.as_slice()) {
+        (FilterOperator::WithinRadius, [lon, lat, meters]) if *meters >= 0.0 => {
+            let center = points(&[*lon, *lat])?;
+            Some(FilterValue::Array(vec![
+                FilterValue::Point(center[0]),
+                FilterValue::Float(*meters),
+            ]))
+        }
+        (FilterOperator::WithinBox, coords @ [_, _, _, _]) => {
+            let corners = points(coords)?;
+            Some(FilterValue::BoundingBox(GeoBox::new(
+                corners[0], corners[1],
+            )))
+        }
+        (FilterOperator::WithinPolygon, coords) if coords.len() >= 6 && coords.len() % 2 == 0 => {
+            Some(FilterValue::Polygon(points(coords)?))
+        }
+        _ => None,
+    }
+}
+
+fn geo_value_format(operator: &FilterOperator) -> &'static str {
+    match operator {
+        FilterOperator::WithinRadius => "lon,lat,meters",
+        FilterOperator::WithinBox => "min_lon,min_lat,max_lon,max_lat",
+        _ => "lon,lat,lon,lat,lon,lat,...",
+    }
+}
+
+/// Parses `field:lon,lat`, the origin of a distance sort.
+pub fn parse_near(near: &str) -> Option<(String, GeoPoint)> {
+    let (field, coords) = near.split_once(':')?;
+    let (lon, lat) = coords.split_once(',')?;
+    let point = GeoPoint::new(lon.trim().parse().ok()?, lat.trim().parse().ok()?);
+    point.validate().ok()?;
+    Some((field.to_string(), point))
+}
+
+/// Reads a value that is not a number. Range comparisons read RFC 3339
+/// timestamps and relative dates as `FilterValue::DateTime`, and `eq`/`ne`
+/// read RFC 3339 timestamps. Every other operator keeps the text, so `like`,
+/// `contains` or `in` on a text column never compare against a timestamp,
+/// and `name:eq:now` still matches the text `now`.
+fn string_or_date(value: &str, operator: &FilterOperator, dates: &DateContext) -> FilterValue {
+    let resolved = match operator {
+        _ if operator.is_range_operator() => dates.resolve(value),
+        FilterOperator::Eq | FilterOperator::Ne => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|at| at.with_timezone(&Utc)),
+        _ => None,
+    };
+    match resolved {
+        Some(dt) => FilterValue::DateTime(dt),
+        None => FilterValue::String(value.to_string()),
+    }
+}
@@ -0,0 +1,102 @@
+use crate::error::{ErrorCode, PaginatorError, PaginatorResult, ValidationError};
+use async_trait::async_trait;
+use paginator_utils::{PaginationParams, PaginatorResponse, TotalKind};
+use std::sync::Arc;
+
+/// Pages of `T` from any storage.
+///
+/// Each backend crate has a source wrapping its entry point: `SqlxSource`
+/// in `paginator-sqlx`, `SeaOrmSource` in `paginator-sea-orm` and
+/// `SurrealSource` in `paginator-surrealdb`. Services written against the
+/// trait, or against `Arc<dyn PaginatedSource<T>>`, can take any of them, or
+/// a `Vec` in tests.
+#[async_trait]
+pub trait PaginatedSource<T>: Send + Sync {
+    async fn fetch_page(&self, params: &PaginationParams) -> PaginatorResult<PaginatorResponse<T>>;
+}
+
+/// Serves the rows in order, by `page` and `per_page`, so fill it with the
+/// rows a test expects. Filters, search, sorting and cursors are rejected
+/// with a validation error rather than silently ignored.
+#[async_trait]
+impl<T> PaginatedSource<T> for Vec<T>
+where
+    T: Clone + Send + Sync,
+{
+    async fn fetch_page(&self, params: &PaginationParams) -> PaginatorResult<PaginatorResponse<T>> {
+        if params.page < 1 {
+            return Err(PaginatorError::InvalidPage(params.page));
+        }
+        if params.per_page < 1 || params.per_page > 100 {
+            return Err(PaginatorError::InvalidPerPage(params.per_page));
+        }
+        if let Some((code, parameter)) = unsupported_param(params) {
+            return Err(ValidationError::new(
+                code,
+                format!(
+                    "A Vec source does not support the '{}' parameter",
+                    parameter
+                ),
+            )
+            .parameter(parameter)
+            .into());
+        }
+
+        let rows = self
+            .iter()
+            .skip(params.offset() as usize)
+            .take(params.fetch_limit() as usize)
+            .cloned()
+            .collect();
+        let total = (!params.disable_total_count)
+            .then(|| (self.len().min(u32::MAX as usize) as u32, TotalKind::Exact));
+        Ok(PaginatorResponse::from_rows(rows, params, total))
+    }
+}
+
+/// The first parameter a `Vec` source cannot apply, if any.
+fn unsupported_param(params: &PaginationParams) -> Option<(ErrorCode, &'static str)> {
+    if !params.filters.is_empty() {
+        Some((ErrorCode::InvalidFilter, "filter"))
+    } else if params.search.is_some() {
+        Some((ErrorCode::InvalidSearch, "search"))
+    } else if params.sort_by.is_some() {
+        Some((ErrorCode::InvalidSort, "sort_by"))
+    } else if params.distance_sort.is_some() {
+        Some((ErrorCode::InvalidSort, "near"))
+    } else if params.cursor.is_some() {
+        Some((ErrorCode::InvalidCursor, "cursor"))
+    } else {
+        None
+    }
+}
+
+#[async_trait]
+impl<T, S> PaginatedSource<T> for &S
+where
+    S: PaginatedSource<T> + ?Sized,
+{
+    async fn fetch_page(&self, params: &PaginationParams) -> PaginatorResult<PaginatorResponse<T>> {
+        (**self).fetch_page(params).await
+    }
+}
+
+#[async_trait]
+impl<T, S> PaginatedSource<T> for Box<S>
+where
+    S: PaginatedSource<T> + ?Sized,
+{
+    async fn fetch_page(&self, params: &PaginationParams) -> PaginatorResult<PaginatorResponse<T>> {
+        (**self).fetch_page(params).await
+    }
+}
+
+#[async_trait]
+impl<T, S> PaginatedSource<T> for Arc<S>
+where
+    S: PaginatedSource<T> + ?Sized,
+{
+    async fn fetch_page(&self, params: &PaginationParams) -> PaginatorResult<PaginatorResponse<T>> {
+        (**self).fetch_page(params).await
+    }
+}
@@ -0,0 +1,74 @@
+use crate::error::{ErrorCode, PaginatorError, PaginatorResult, ValidationError};
+use paginator_utils::SortDirection;
+
+/// How framework extractors treat query parameters they cannot use.
+///
+/// `Lenient` clamps `page` and `per_page` into range and drops anything it
+/// cannot parse, so a typo silently widens the result. `Strict` rejects the
+/// request instead, naming the offending parameter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    #[default]
+    Lenient,
+    Strict,
+}
+
+impl ParseMode {
+    pub fn is_strict(self) -> bool {
+        self == ParseMode::Strict
+    }
+
+    /// Checks a page number, which starts at 1.
+    pub fn page(self, page: u32) -> PaginatorResult<u32> {
+        match self {
+            ParseMode::Strict if page == 0 => Err(PaginatorError::InvalidPage(page)),
+            _ => Ok(page.max(1)),
+        }
+    }
+
+    /// Checks a page size, which must be between 1 and 100.
+    pub fn per_page(self, per_page: u32) -> PaginatorResult<u32> {
+        match self {
+            ParseMode::Strict if !(1..=100).contains(&per_page) => {
+                Err(PaginatorError::InvalidPerPage(per_page))
+            }
+            _ => Ok(per_page.clamp(1, 100)),
+        }
+    }
+
+    /// Parses `asc` or `desc`, case-insensitively.
+    pub fn sort_direction(self, value: &str) -> PaginatorResult<Option<SortDirection>> {
+        match value.to_lowercase().as_str() {
+            "asc" => Ok(Some(SortDirection::Asc)),
+            "desc" => Ok(Some(SortDirection::Desc)),
+            _ if self.is_strict() => Err(ValidationError::new(
+                ErrorCode::InvalidSort,
+                format!("Invalid sort direction '{}'", value),
+            )
+            .parameter("sort_direction")
+            .allowed(["asc", "desc"])
+            .into()),
+            _ => Ok(None),
+        }
+    }
+
+    /// Rejects query keys outside `known` when strict.
+    pub fn check_keys<'a, I>(self, keys: I, known: &[&str]) -> PaginatorResult<()>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        if !self.is_strict() {
+            return Ok(());
+        }
+        match keys.into_iter().find(|key| !known.contains(key)) {
+            Some(key) => Err(ValidationError::new(
+                ErrorCode::InvalidParameter,
+                format!("Unknown query parameter '{}'", key),
+            )
+            .parameter(key)
+            .allowed(known.iter().copied())
+            .into()),
+            None => Ok(()),
+        }
+    }
+}
@@ -18,6 +18,7 @@ where
         Ok(PaginatorResponse {
             data: vec![],
             meta: PaginatorResponseMeta::new(0, params.per_page, 0),
+            links: None,
         })
     }
 
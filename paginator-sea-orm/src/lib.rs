@@ -1,11 +1,16 @@
+use futures_util::future::try_join;
 use paginator_rs::{
-    CursorDirection, CursorValue, FilterOperator, FilterValue, PaginationParams, PaginatorError,
-    PaginatorResponse, PaginatorResponseMeta,
+    count_fingerprint_with, parse_explain_rows, polygon_wkt, BindValue, CountCache, CountStrategy,
+    Cursor, CursorValue, DistanceSort, ErrorCode, FieldPath, Filter, FilterOperator, FilterValue,
+    PaginatedSource, PaginationParams, PaginationSpan, PaginatorError, PaginatorResponse,
+    PlannedStatement, QueryPlan, SortDirection, TotalKind, ValidationError, DISTANCE_COLUMN,
+    LIKE_ESCAPE,
 };
 use sea_orm::{
-    sea_query::{Alias, Condition, Expr, SimpleExpr},
-    ConnectionTrait, EntityTrait, PaginatorTrait as SeaPaginatorTrait, QueryFilter, QuerySelect,
-    Select,
+    sea_query::{Alias, Condition, Expr, Func, LikeExpr, SelectStatement, SimpleExpr},
+    AccessMode, ConnectionTrait, DatabaseTransaction, DbBackend, EntityTrait, FromQueryResult,
+    IsolationLevel, JsonValue, Order, QueryFilter, QueryOrder, QuerySelect, QueryTrait, Select,
+    Statement, TransactionTrait,
 };
 use serde::Serialize;
 
@@ -15,7 +20,11 @@ fn filter_value_to_sea_value(value: &FilterValue) -> sea_orm::sea_query::Value {
         FilterValue::Int(i) => (*i).into(),
         FilterValue::Float(f) => (*f).into(),
         FilterValue::Bool(b) => (*b).into(),
+        FilterValue::DateTime(dt) => (*dt).into(),
         FilterValue::Null => sea_orm::sea_query::Value::String(None),
+        FilterValue::Point(_) | FilterValue::BoundingBox(_) | FilterValue::Polygon(_) => {
+            value.to_text().into()
+        }
         FilterValue::Array(_) => sea_orm::sea_query::Value::String(None),
     }
 }
@@ -25,6 +34,7 @@ fn cursor_value_to_sea_value(value: &CursorValue) -> sea_orm::sea_query::Value {
         CursorValue::String(s) => s.clone().into(),
         CursorValue::Int(i) => (*i).into(),
         CursorValue::Float(f) => (*f).into(),
+        CursorValue::DateTime(dt) => (*dt).into(),
         // Parse UUID string and convert to sea-orm UUID value
         CursorValue::Uuid(u) => {
             if let Ok(parsed) = uuid::Uuid::parse_str(u) {
@@ -37,63 +47,415 @@ fn cursor_value_to_sea_value(value: &CursorValue) -> sea_orm::sea_query::Value {
     }
 }
 
-fn build_filter_condition(params: &PaginationParams) -> Condition {
-    let mut condition = Condition::all();
+/// Decides how field names are written: as plain columns, as JSON
+/// extraction for paths under a column of `PaginateOptions::json_columns`,
+/// or as the distance expression for `DISTANCE_COLUMN` when sorting by
+/// distance.
+#[derive(Clone, Copy)]
+struct Fields<'a> {
+    backend: DbBackend,
+    json_columns: &'a [String],
+    distance: Option<&'a DistanceSort>,
+}
 
-    if let Some(ref cursor) = params.cursor {
-        let col = Expr::col(Alias::new(&cursor.field));
-        let cursor_val = cursor_value_to_sea_value(&cursor.value);
-
-        let cursor_expr = match cursor.direction {
-            CursorDirection::After => match params.sort_direction.as_ref() {
-                Some(paginator_rs::SortDirection::Desc) => col.lt(cursor_val),
-                _ => col.gt(cursor_val),
-            },
-            CursorDirection::Before => match params.sort_direction.as_ref() {
-                Some(paginator_rs::SortDirection::Desc) => col.gt(cursor_val),
-                _ => col.lt(cursor_val),
-            },
+impl<'a> Fields<'a> {
+    fn new(backend: DbBackend, options: &'a PaginateOptions, params: &'a PaginationParams) -> Self {
+        Self {
+            backend,
+            json_columns: &options.json_columns,
+            distance: params.distance_sort.as_ref(),
+        }
+    }
+
+    fn json_path(&self, field: &str) -> Option<FieldPath> {
+        let column = field.split(['.', '[']).next()?;
+        if !self.json_columns.iter().any(|c| c == column) {
+            return None;
+        }
+        FieldPath::parse(field)
+            .ok()
+            .filter(|path| !path.segments().is_empty())
+    }
+
+    /// The expression for `field` and whether its operands must be converted
+    /// to JSON, which PostgreSQL needs to compare `jsonb` values. `text`
+    /// extracts the value as text, for `LIKE` patterns.
+    fn column(&self, field: &str, text: bool) -> (Expr, bool) {
+        if let (Some(sort), DISTANCE_COLUMN) = (self.distance, field) {
+            return (Expr::expr(distance_expr(sort, self.backend)), false);
+        }
+        let Some(path) = self.json_path(field) else {
+            return (Expr::col(Alias::new(field)), false);
+        };
+
+        let column = Expr::col(Alias::new(path.column()));
+        let expr: SimpleExpr = match self.backend {
+            DbBackend::Postgres => {
+                let name = if text {
+                    "jsonb_extract_path_text"
+                } else {
+                    "jsonb_extract_path"
+                };
+                let segments = path.segment_texts().into_iter().map(SimpleExpr::from);
+                Func::cust(Alias::new(name))
+                    .args(std::iter::once(column.into()).chain(segments))
+                    .into()
+            }
+            DbBackend::MySql => {
+                let extract = Func::cust(Alias::new("JSON_EXTRACT"))
+                    .arg(column)
+                    .arg(Expr::val(path.json_path()));
+                if text {
+                    Func::cust(Alias::new("JSON_UNQUOTE")).arg(extract).into()
+                } else {
+                    extract.into()
+                }
+            }
+            DbBackend::Sqlite => Func::cust(Alias::new("json_extract"))
+                .arg(column)
+                .arg(Expr::val(path.json_path()))
+                .into(),
+        };
+        (
+            Expr::expr(expr),
+            self.backend == DbBackend::Postgres && !text,
+        )
+    }
+
+    fn is_json(&self, field: &str) -> bool {
+        self.json_path(field).is_some()
+    }
+}
+
+/// `value`, converted with `to_jsonb` when `wrap` is set.
+fn operand(value: sea_orm::sea_query::Value, wrap: bool) -> SimpleExpr {
+    if wrap {
+        Func::cust(Alias::new("to_jsonb")).arg(value).into()
+    } else {
+        value.into()
+    }
+}
+
+/// Builds an array operator. PostgreSQL compares native arrays, or `jsonb`
+/// arrays for JSON paths; MySQL and SQLite store arrays as JSON.
+fn array_condition(filter: &Filter, fields: Fields) -> SimpleExpr {
+    let (col, _) = fields.column(&filter.field, false);
+    let col: SimpleExpr = col.into();
+    let json = fields.is_json(&filter.field);
+    let values: Vec<SimpleExpr> = filter
+        .value
+        .elements()
+        .iter()
+        .map(|value| filter_value_to_sea_value(value).into())
+        .collect();
+    let placeholders = vec!["?"; values.len()].join(", ");
+    let with_col = |template: String, values: Vec<SimpleExpr>| {
+        let exprs = std::iter::once(col.clone()).chain(values);
+        Expr::cust_with_exprs(numbered(&template, fields.backend), exprs)
+    };
+
+    if let Some(comparison) = filter.operator.length_comparison() {
+        let function = match fields.backend {
+            DbBackend::Postgres if json => "jsonb_array_length",
+            DbBackend::Postgres => "cardinality",
+            DbBackend::MySql => "JSON_LENGTH",
+            DbBackend::Sqlite => "json_array_length",
+        };
+        let len = filter_value_to_sea_value(&filter.value).into();
+        return with_col(format!("{}(?) {} ?", function, comparison), vec![len]);
+    }
+
+    let all = filter.operator == FilterOperator::ContainsAll;
+    if values.is_empty() {
+        // Every array holds all of nothing, and none holds any of it.
+        return Expr::cust(if all { "1=1" } else { "1=0" });
+    }
+
+    match fields.backend {
+        DbBackend::Postgres if json && all => {
+            with_col(format!("? @> jsonb_build_array({})", placeholders), values)
+        }
+        // `@>` with a one-element array tests membership of that element.
+        DbBackend::Postgres if json => values
+            .into_iter()
+            .fold(Condition::any(), |any, value| {
+                any.add(with_col(
+                    "? @> jsonb_build_array(?)".to_string(),
+                    vec![value],
+                ))
+            })
+            .into(),
+        // `= ANY(col)` tests membership of one element, so `@>` becomes
+        // every element being a member and `&&` any of them.
+        DbBackend::Postgres => values
+            .into_iter()
+            .fold(
+                if all {
+                    Condition::all()
+                } else {
+                    Condition::any()
+                },
+                |condition, value| {
+                    condition
+                        .add(Expr::expr(value).eq(Func::cust(Alias::new("ANY")).arg(col.clone())))
+                },
+            )
+            .into(),
+        DbBackend::MySql => with_col(
+            format!(
+                "{}(?, JSON_ARRAY({}))",
+                if all {
+                    "JSON_CONTAINS"
+                } else {
+                    "JSON_OVERLAPS"
+                },
+                placeholders
+            ),
+            values,
+        ),
+        DbBackend::Sqlite if all => values
+            .into_iter()
+            .fold(Condition::all(), |every, value| {
+                every.add(with_col(
+                    "EXISTS (SELECT 1 FROM json_each(?) WHERE value = ?)".to_string(),
+                    vec![value],
+                ))
+            })
+            .into(),
+        DbBackend::Sqlite => with_col(
+            format!(
+                "EXISTS (SELECT 1 FROM json_each(?) WHERE value IN ({}))",
+                placeholders
+            ),
+            values,
+        ),
+    }
+}
+
+/// SQL of a WGS 84 geometry built from a bound well-known text.
+fn geometry_sql(backend: DbBackend) -> &'static str {
+    match backend {
+        DbBackend::Postgres => "ST_GeomFromText(?, 4326)",
+        // MySQL reads SRID 4326 coordinates latitude first by default.
+        DbBackend::MySql => "ST_GeomFromText(?, 4326, 'axis-order=long-lat')",
+        DbBackend::Sqlite => "GeomFromText(?, 4326)",
+    }
+}
+
+/// The distance in metres between the geometry column of `sort` and its
+/// origin.
+fn distance_expr(sort: &DistanceSort, backend: DbBackend) -> SimpleExpr {
+    let template = match backend {
+        DbBackend::Postgres => "ST_Distance(?::geography, ST_GeogFromText(?))".to_string(),
+        DbBackend::MySql => format!("ST_Distance_Sphere(?, {})", geometry_sql(backend)),
+        // The third argument measures on the ellipsoid, in metres.
+        DbBackend::Sqlite => format!("ST_Distance(?, {}, 1)", geometry_sql(backend)),
+    };
+    Expr::cust_with_exprs(
+        numbered(&template, backend),
+        [
+            Expr::col(Alias::new(sort.field.as_str())).into(),
+            Expr::val(sort.origin.to_wkt()).into(),
+        ],
+    )
+}
+
+/// Builds a geo operator: PostGIS on PostgreSQL, the spatial functions of
+/// MySQL 8 and SpatiaLite on SQLite. Malformed values match nothing.
+fn geo_condition(filter: &Filter, fields: Fields) -> SimpleExpr {
+    let col: SimpleExpr = Expr::col(Alias::new(filter.field.as_str())).into();
+    let backend = fields.backend;
+
+    if let Some((center, meters)) = filter.radius() {
+        let template = match backend {
+            DbBackend::Postgres => "ST_DWithin(?::geography, ST_GeogFromText(?), ?)".to_string(),
+            DbBackend::MySql => format!("ST_Distance_Sphere(?, {}) <= ?", geometry_sql(backend)),
+            DbBackend::Sqlite => format!("PtDistWithin(?, {}, ?) = 1", geometry_sql(backend)),
         };
+        return Expr::cust_with_exprs(
+            numbered(&template, backend),
+            [
+                col,
+                Expr::val(center.to_wkt()).into(),
+                Expr::val(meters).into(),
+            ],
+        );
+    }
+
+    let Some(ring) = filter.area() else {
+        return Expr::cust("1=0");
+    };
+    let template = match backend {
+        DbBackend::Postgres => format!("ST_Within(?::geometry, {})", geometry_sql(backend)),
+        DbBackend::MySql => format!("ST_Within(?, {})", geometry_sql(backend)),
+        // SpatiaLite answers -1 for invalid input, which would count as true.
+        DbBackend::Sqlite => format!("ST_Within(?, {}) = 1", geometry_sql(backend)),
+    };
+    Expr::cust_with_exprs(
+        numbered(&template, backend),
+        [col, Expr::val(polygon_wkt(&ring)).into()],
+    )
+}
+
+/// Rewrites the `?` placeholders of a custom expression to the `$n` form
+/// sea-query expects for PostgreSQL.
+fn numbered(template: &str, backend: DbBackend) -> String {
+    if backend != DbBackend::Postgres {
+        return template.to_string();
+    }
+    let mut count = 0;
+    template
+        .split('?')
+        .enumerate()
+        .map(|(idx, part)| {
+            if idx == 0 {
+                part.to_string()
+            } else {
+                count += 1;
+                format!("${}{}", count, part)
+            }
+        })
+        .collect()
+}
+
+/// Restricts the rows to those past `cursor` in the page's order.
+///
+/// Kept out of `build_filter_condition`, so counts cover the whole filtered
+/// set as on the other backends.
+fn cursor_condition(cursor: &Cursor, params: &PaginationParams, fields: Fields) -> Condition {
+    let (col, wrap) = fields.column(&cursor.field, false);
+    let cursor_val = operand(cursor_value_to_sea_value(&cursor.value), wrap);
+
+    let beyond = |col: Expr, value: SimpleExpr| match params.order_direction() {
+        SortDirection::Desc => col.lt(value),
+        SortDirection::Asc => col.gt(value),
+    };
 
-        condition = condition.add(cursor_expr);
+    match (params.tie_breaker_field(), &cursor.tie_value) {
+        // Rows tying on the cursor field continue after the cursor row's key.
+        (Some(key), Some(tie_value)) => Condition::any()
+            .add(beyond(col.clone(), cursor_val.clone()))
+            .add(col.eq(cursor_val).and(beyond(
+                Expr::col(Alias::new(key)),
+                cursor_value_to_sea_value(tie_value).into(),
+            ))),
+        _ => Condition::all().add(beyond(col, cursor_val)),
     }
+}
 
-    for filter in &params.filters {
-        let col = Expr::col(Alias::new(&filter.field));
+/// Builds the filter and search condition. Operator and value combinations
+/// that have no SQL form, such as `In` with a single value or `Like` with a
+/// number, are rejected rather than dropped.
+fn build_filter_condition(
+    params: &PaginationParams,
+    fields: Fields,
+) -> Result<Condition, PaginatorError> {
+    let mut condition = Condition::all();
+
+    for (idx, filter) in params.filters.iter().enumerate() {
+        let text = matches!(
+            filter.operator,
+            FilterOperator::Like
+                | FilterOperator::ILike
+                | FilterOperator::Contains
+                | FilterOperator::StartsWith
+                | FilterOperator::EndsWith
+                | FilterOperator::IEq
+                | FilterOperator::Regex
+        );
+        let (col, wrap) = fields.column(&filter.field, text);
+        let value = |value: &FilterValue| operand(filter_value_to_sea_value(value), wrap);
+        let unsupported = || {
+            PaginatorError::from(
+                ValidationError::new(
+                    ErrorCode::InvalidFilter,
+                    format!(
+                        "Filter on '{}' cannot apply {:?} to {:?}",
+                        filter.field, filter.operator, filter.value
+                    ),
+                )
+                .parameter(format!("filter[{}]", idx)),
+            )
+        };
 
         let filter_expr: SimpleExpr = match (&filter.operator, &filter.value) {
-            (FilterOperator::Eq, value) => col.eq(filter_value_to_sea_value(value)),
-            (FilterOperator::Ne, value) => col.ne(filter_value_to_sea_value(value)),
-            (FilterOperator::Gt, value) => col.gt(filter_value_to_sea_value(value)),
-            (FilterOperator::Lt, value) => col.lt(filter_value_to_sea_value(value)),
-            (FilterOperator::Gte, value) => col.gte(filter_value_to_sea_value(value)),
-            (FilterOperator::Lte, value) => col.lte(filter_value_to_sea_value(value)),
+            (
+                FilterOperator::Eq
+                | FilterOperator::Ne
+                | FilterOperator::Gt
+                | FilterOperator::Lt
+                | FilterOperator::Gte
+                | FilterOperator::Lte,
+                FilterValue::Array(_),
+            ) => return Err(unsupported()),
+            (FilterOperator::Eq, v) => col.eq(value(v)),
+            (FilterOperator::Ne, v) => col.ne(value(v)),
+            (FilterOperator::Gt, v) => col.gt(value(v)),
+            (FilterOperator::Lt, v) => col.lt(value(v)),
+            (FilterOperator::Gte, v) => col.gte(value(v)),
+            (FilterOperator::Lte, v) => col.lte(value(v)),
             (FilterOperator::Like, FilterValue::String(pattern)) => col.like(pattern.clone()),
+            (FilterOperator::ILike, FilterValue::String(pattern))
+                if fields.is_json(&filter.field) =>
+            {
+                Expr::expr(Func::lower(col)).like(pattern.to_lowercase())
+            }
             (FilterOperator::ILike, FilterValue::String(pattern)) => {
                 Expr::expr(Expr::cust(format!("LOWER({})", filter.field)))
                     .like(pattern.to_lowercase())
             }
+            (FilterOperator::Contains, FilterValue::String(value)) => {
+                col.like(format!("%{}%", value))
+            }
+            (FilterOperator::Like | FilterOperator::ILike | FilterOperator::Contains, _) => {
+                return Err(unsupported())
+            }
             (FilterOperator::In, FilterValue::Array(values)) => {
-                let sea_values: Vec<sea_orm::sea_query::Value> =
-                    values.iter().map(filter_value_to_sea_value).collect();
-                col.is_in(sea_values)
+                col.is_in(values.iter().map(value).collect::<Vec<_>>())
             }
             (FilterOperator::NotIn, FilterValue::Array(values)) => {
-                let sea_values: Vec<sea_orm::sea_query::Value> =
-                    values.iter().map(filter_value_to_sea_value).collect();
-                col.is_not_in(sea_values)
+                col.is_not_in(values.iter().map(value).collect::<Vec<_>>())
             }
+            (FilterOperator::In | FilterOperator::NotIn, _) => return Err(unsupported()),
             (FilterOperator::IsNull, _) => col.is_null(),
             (FilterOperator::IsNotNull, _) => col.is_not_null(),
-            (FilterOperator::Between, FilterValue::Array(values)) if values.len() == 2 => col
-                .between(
-                    filter_value_to_sea_value(&values[0]),
-                    filter_value_to_sea_value(&values[1]),
-                ),
-            (FilterOperator::Contains, FilterValue::String(value)) => {
-                col.like(format!("%{}%", value))
+            (FilterOperator::Between, FilterValue::Array(values)) if values.len() == 2 => {
+                col.between(value(&values[0]), value(&values[1]))
             }
-            _ => continue,
+            (FilterOperator::Between, _) => return Err(unsupported()),
+            (FilterOperator::StartsWith | FilterOperator::EndsWith, _) => col
+                .like(LikeExpr::new(filter.like_pattern().unwrap_or_default()).escape(LIKE_ESCAPE)),
+            (FilterOperator::IEq, v) => {
+                Expr::expr(Func::lower(col)).eq(Func::lower(Expr::val(v.to_text())))
+            }
+            (FilterOperator::Regex, v) => {
+                let template = match fields.backend {
+                    DbBackend::Postgres => "? ~ ?",
+                    // SQLite only knows REGEXP once a regexp() function is
+                    // registered.
+                    DbBackend::MySql | DbBackend::Sqlite => "? REGEXP ?",
+                };
+                Expr::cust_with_exprs(
+                    numbered(template, fields.backend),
+                    [col.into(), Expr::val(v.to_text()).into()],
+                )
+            }
+            (
+                FilterOperator::ContainsAll
+                | FilterOperator::ContainsAny
+                | FilterOperator::LengthEq
+                | FilterOperator::LengthGt
+                | FilterOperator::LengthGte
+                | FilterOperator::LengthLt
+                | FilterOperator::LengthLte,
+                _,
+            ) => array_condition(filter, fields),
+            (
+                FilterOperator::WithinRadius
+                | FilterOperator::WithinBox
+                | FilterOperator::WithinPolygon,
+                _,
+            ) => geo_condition(filter, fields),
         };
 
         condition = condition.add(filter_expr);
@@ -103,7 +465,7 @@ fn build_filter_condition(params: &PaginationParams) -> Condition {
         let mut search_condition = Condition::any();
 
         for field in &search.fields {
-            let col = Expr::col(Alias::new(field));
+            let (col, _) = fields.column(field, true);
             let pattern = if search.exact_match {
                 search.query.clone()
             } else {
@@ -112,6 +474,8 @@ fn build_filter_condition(params: &PaginationParams) -> Condition {
 
             let search_expr = if search.case_sensitive {
                 col.like(pattern)
+            } else if fields.is_json(field) {
+                Expr::expr(Func::lower(col)).like(pattern.to_lowercase())
             } else {
                 Expr::expr(Expr::cust(format!("LOWER({})", field))).like(pattern.to_lowercase())
             };
@@ -122,7 +486,295 @@ fn build_filter_condition(params: &PaginationParams) -> Condition {
         condition = condition.add(search_condition);
     }
 
-    condition
+    Ok(condition)
+}
+
+/// Builds the `COUNT(*)` select for `query`, honouring the row cap of
+/// `CountStrategy::Capped`.
+fn count_statement<E>(query: &Select<E>, params: &PaginationParams) -> SelectStatement
+where
+    E: EntityTrait,
+{
+    let (source, alias) = match params.count_strategy.count_limit() {
+        Some(limit) => (query.clone().limit(limit).into_query(), "_capped"),
+        None => (query.clone().into_query(), "_count"),
+    };
+    SelectStatement::new()
+        .expr(Expr::cust("COUNT(*) AS num_items"))
+        .from_subquery(source, Alias::new(alias))
+        .to_owned()
+}
+
+/// Builds the `EXPLAIN` statement whose first line carries the PostgreSQL
+/// planner's row estimate for `query`.
+fn estimate_statement<E>(query: &Select<E>, backend: DbBackend) -> Statement
+where
+    E: EntityTrait,
+{
+    let mut explain = query.build(backend);
+    explain.sql = format!("EXPLAIN {}", explain.sql);
+    explain
+}
+
+/// Computes the total for `query` according to `params.count_strategy`.
+///
+/// Planner estimates are only available on PostgreSQL; other backends answer
+/// `CountStrategy::Estimated` with an exact count.
+async fn count_total<C, E>(
+    query: &Select<E>,
+    db: &C,
+    params: &PaginationParams,
+) -> Result<(u32, TotalKind), PaginatorError>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+    <E as EntityTrait>::Model: Sync,
+{
+    let backend = db.get_database_backend();
+
+    if params.count_strategy == CountStrategy::Estimated && backend == DbBackend::Postgres {
+        let plan = db
+            .query_one(estimate_statement(query, backend))
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Estimate query failed: {}", e)))?;
+        let estimate = plan
+            .and_then(|row| row.try_get_by_index::<String>(0).ok())
+            .and_then(|line| parse_explain_rows(&line));
+        if let Some(rows) = estimate {
+            return Ok((rows.min(u32::MAX as u64) as u32, TotalKind::Estimate));
+        }
+    }
+
+    let row = db
+        .query_one(backend.build(&count_statement(query, params)))
+        .await
+        .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?;
+    let count = row
+        .map(|row| row.try_get::<i64>("", "num_items"))
+        .transpose()
+        .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?
+        .unwrap_or(0);
+
+    Ok(params.count_strategy.resolve(count.max(0) as u64))
+}
+
+/// Applies the cursor condition and ordering, distance and window count
+/// columns and limits to the filtered `query`, producing the data select.
+fn page_select<E>(
+    query: Select<E>,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+    backend: DbBackend,
+) -> Select<E>
+where
+    E: EntityTrait,
+{
+    let fields = Fields::new(backend, options, params);
+    let query = if options.projects(params) {
+        project(query, params, fields)
+    } else {
+        query
+    };
+    let query = match params.distance_sort {
+        Some(ref sort) => query.expr_as(distance_expr(sort, backend), DISTANCE_COLUMN),
+        None => query,
+    };
+    if let Some(ref cursor) = params.cursor {
+        let query = query.filter(cursor_condition(cursor, params, fields));
+        return cursor_order(query, params, fields).limit(params.fetch_limit() as u64);
+    }
+
+    // Other sorts are applied by the caller; the distance only exists here.
+    let query =
+        if params.distance_sort.is_some() && params.sort_by.as_deref() == Some(DISTANCE_COLUMN) {
+            cursor_order(query, params, fields)
+        } else {
+            tie_breaker_order(query, params)
+        };
+
+    let query = if options.uses_window_count(params) {
+        query.expr_as(Expr::cust("COUNT(*) OVER ()"), WINDOW_TOTAL_COLUMN)
+    } else {
+        query
+    };
+    query
+        .offset(params.offset() as u64)
+        .limit(params.fetch_limit() as u64)
+}
+
+/// Selects only `params.fields`, each under its own name.
+fn project<E>(query: Select<E>, params: &PaginationParams, fields: Fields) -> Select<E>
+where
+    E: EntityTrait,
+{
+    let mut query = query.select_only();
+    for field in params.fields.iter().flatten() {
+        let (col, _) = fields.column(field, false);
+        QueryTrait::query(&mut query).expr_as(col, Alias::new(field));
+    }
+    query
+}
+
+/// Replaces the select's ordering with `params.order_field()`, which runs
+/// against the requested direction for backward cursor pages.
+fn cursor_order<E>(mut query: Select<E>, params: &PaginationParams, fields: Fields) -> Select<E>
+where
+    E: EntityTrait,
+{
+    let Some(field) = params.order_field() else {
+        return query;
+    };
+    QueryTrait::query(&mut query).clear_order_by();
+    let order = match params.order_direction() {
+        SortDirection::Desc => Order::Desc,
+        SortDirection::Asc => Order::Asc,
+    };
+    let (col, _) = fields.column(field, false);
+    tie_breaker_order(query.order_by(col, order), params)
+}
+
+/// Appends `params.tie_breaker_field()` to the select's ordering.
+fn tie_breaker_order<E>(query: Select<E>, params: &PaginationParams) -> Select<E>
+where
+    E: EntityTrait,
+{
+    let Some(key) = params.tie_breaker_field() else {
+        return query;
+    };
+    let order = match params.order_direction() {
+        SortDirection::Desc => Order::Desc,
+        SortDirection::Asc => Order::Asc,
+    };
+    query.order_by(Expr::col(Alias::new(key)), order)
+}
+
+/// Execution options for `PaginateSeaOrm::paginate_with_options`.
+#[derive(Clone, Debug, Default)]
+pub struct PaginateOptions {
+    count_cache: Option<CountCache>,
+    cache_tags: Vec<String>,
+    concurrent: bool,
+    window_count: bool,
+    project_fields: bool,
+    trace_filter_values: bool,
+    json_columns: Vec<String>,
+}
+
+impl PaginateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skips the count select when `cache` holds a total for the same
+    /// statement. See `CountCache` for `tags`.
+    pub fn count_cache<I, S>(mut self, cache: CountCache, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.count_cache = Some(cache);
+        self.cache_tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Runs the count and data selects concurrently instead of one after the
+    /// other. On a pooled connection this takes two connections per request.
+    pub fn concurrent(mut self, concurrent: bool) -> Self {
+        self.concurrent = concurrent;
+        self
+    }
+
+    /// Computes the total with `COUNT(*) OVER ()` in the data select instead of
+    /// a separate count. Only used for offset pages with an exact count.
+    pub fn window_count(mut self, window_count: bool) -> Self {
+        self.window_count = window_count;
+        self
+    }
+
+    /// Selects only `params.fields` in the data select. Entity models cannot
+    /// decode the partial rows, so page with `paginate_json`.
+    pub fn project_fields(mut self, project_fields: bool) -> Self {
+        self.project_fields = project_fields;
+        self
+    }
+
+    /// Records filter values in `tracing` spans instead of redacting them to
+    /// `?`. Only has an effect with the `tracing` feature.
+    pub fn trace_filter_values(mut self, trace_filter_values: bool) -> Self {
+        self.trace_filter_values = trace_filter_values;
+        self
+    }
+
+    /// Declares JSON columns whose contents can be filtered and paged over
+    /// with paths such as `attrs.color` or `attrs[0].sku`. Paths compile to
+    /// the backend's JSON extraction with every segment bound; PostgreSQL
+    /// columns must be `jsonb`.
+    pub fn json_columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.json_columns = columns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    fn projects(&self, params: &PaginationParams) -> bool {
+        self.project_fields && params.fields.is_some()
+    }
+
+    fn uses_window_count(&self, params: &PaginationParams) -> bool {
+        self.window_count
+            && params.cursor.is_none()
+            && !params.disable_total_count
+            && params.count_strategy.is_exact()
+    }
+}
+
+/// Column carrying the window-function total in window-count rows.
+const WINDOW_TOTAL_COLUMN: &str = "_paginator_total";
+
+/// Fetches an offset page whose rows also carry the filtered total.
+///
+/// A page past the end returns no rows and therefore no total, in which case
+/// a separate count is run.
+async fn paginate_with_window_count<C, E, M>(
+    query: Select<E>,
+    db: &C,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+    span: &PaginationSpan,
+) -> Result<(Vec<M>, (u32, TotalKind)), PaginatorError>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+    <E as EntityTrait>::Model: Sync,
+    M: FromQueryResult + Sync,
+{
+    let backend = db.get_database_backend();
+    let page = page_select(query.clone(), params, options, backend);
+    let rows = span
+        .phase("data", db.query_all(page.build(backend)))
+        .await
+        .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?;
+
+    let total = match rows.first() {
+        Some(row) => {
+            let total = row
+                .try_get::<i64>("", WINDOW_TOTAL_COLUMN)
+                .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?;
+            params.count_strategy.resolve(total.max(0) as u64)
+        }
+        None if params.offset() == 0 => (0, TotalKind::Exact),
+        None => span.phase("count", count_total(&query, db, params)).await?,
+    };
+
+    let data = rows
+        .iter()
+        .map(|row| M::from_query_result(row, ""))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?;
+
+    Ok((data, total))
 }
 
 #[async_trait::async_trait]
@@ -137,6 +789,13 @@ where
         db: &'db C,
         params: &PaginationParams,
     ) -> Result<PaginatorResponse<Self::Item>, PaginatorError>;
+
+    async fn paginate_with_options(
+        self,
+        db: &'db C,
+        params: &PaginationParams,
+        options: &PaginateOptions,
+    ) -> Result<PaginatorResponse<Self::Item>, PaginatorError>;
 }
 
 #[async_trait::async_trait]
@@ -153,55 +812,215 @@ where
         db: &'db C,
         params: &PaginationParams,
     ) -> Result<PaginatorResponse<Self::Item>, PaginatorError> {
-        let filter_condition = build_filter_condition(params);
-        let mut query = self.filter(filter_condition.clone());
+        self.paginate_with_options(db, params, &PaginateOptions::default())
+            .await
+    }
 
-        let total = if params.disable_total_count {
-            None
-        } else {
-            let count = query
-                .clone()
-                .count(db)
+    async fn paginate_with_options(
+        self,
+        db: &'db C,
+        params: &PaginationParams,
+        options: &PaginateOptions,
+    ) -> Result<PaginatorResponse<Self::Item>, PaginatorError> {
+        let span = PaginationSpan::new("sea-orm", params, options.trace_filter_values);
+        span.run(async { fetch_page(self, db, params, options, &span).await })
+            .await
+    }
+}
+
+/// Snapshot reads for connections that can open transactions.
+#[async_trait::async_trait]
+pub trait PaginateSeaOrmSnapshot<'db, C>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    type Item;
+
+    /// Like `PaginateSeaOrm::paginate_with_options`, but runs the count and
+    /// data selects inside one snapshot transaction so `total` and `data`
+    /// agree under concurrent writes. The selects run one after the other;
+    /// `concurrent` is ignored.
+    async fn paginate_snapshot(
+        self,
+        db: &'db C,
+        params: &PaginationParams,
+        options: &PaginateOptions,
+    ) -> Result<PaginatorResponse<Self::Item>, PaginatorError>;
+}
+
+#[async_trait::async_trait]
+impl<'db, C, E> PaginateSeaOrmSnapshot<'db, C> for Select<E>
+where
+    C: ConnectionTrait + TransactionTrait,
+    E: EntityTrait,
+    <E as EntityTrait>::Model: Serialize + Send + Sync,
+{
+    type Item = <E as EntityTrait>::Model;
+
+    async fn paginate_snapshot(
+        self,
+        db: &'db C,
+        params: &PaginationParams,
+        options: &PaginateOptions,
+    ) -> Result<PaginatorResponse<Self::Item>, PaginatorError> {
+        let options = options.clone().concurrent(false);
+        let span = PaginationSpan::new("sea-orm", params, options.trace_filter_values);
+        span.run(async {
+            let txn = begin_snapshot(db).await?;
+            let response = fetch_page(self, &txn, params, &options, &span).await?;
+            txn.commit()
                 .await
-                .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?;
-            Some(count)
-        };
+                .map_err(|e| PaginatorError::Custom(format!("Failed to end snapshot: {}", e)))?;
+            Ok(response)
+        })
+        .await
+    }
+}
 
-        if params.cursor.is_some() {
-            query = query.limit((params.limit() + 1) as u64);
-        } else {
-            query = query
-                .offset(params.offset() as u64)
-                .limit(params.limit() as u64);
+/// A select on a connection as a `PaginatedSource`, paged with
+/// `PaginateSeaOrm::paginate_with_options`.
+#[derive(Clone, Debug)]
+pub struct SeaOrmSource<E: EntityTrait, C> {
+    db: C,
+    select: Select<E>,
+    options: PaginateOptions,
+}
+
+impl<E: EntityTrait, C> SeaOrmSource<E, C> {
+    pub fn new(db: C, select: Select<E>) -> Self {
+        Self {
+            db,
+            select,
+            options: PaginateOptions::default(),
         }
+    }
+
+    pub fn options(mut self, options: PaginateOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
 
-        let mut data = query
-            .all(db)
+#[async_trait::async_trait]
+impl<E, C> PaginatedSource<E::Model> for SeaOrmSource<E, C>
+where
+    C: ConnectionTrait + Send + Sync,
+    E: EntityTrait,
+    <E as EntityTrait>::Model: Serialize + Send + Sync,
+{
+    async fn fetch_page(
+        &self,
+        params: &PaginationParams,
+    ) -> Result<PaginatorResponse<E::Model>, PaginatorError> {
+        self.select
+            .clone()
+            .paginate_with_options(&self.db, params, &self.options)
             .await
-            .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?;
+    }
+}
 
-        let meta = if params.cursor.is_some() {
-            let has_next = data.len() > params.per_page as usize;
-            if has_next {
-                data.truncate(params.per_page as usize);
-            }
-            PaginatorResponseMeta::new_with_cursors(
-                params.page,
-                params.per_page,
-                total.map(|t| t as u32),
-                has_next,
-                None,
-                None,
+/// Opens the transaction for `PaginateSeaOrmSnapshot::paginate_snapshot`:
+/// `REPEATABLE READ` and read-only on PostgreSQL and MySQL, a plain read
+/// transaction on SQLite, which keeps the snapshot of its first read.
+async fn begin_snapshot<C>(db: &C) -> Result<DatabaseTransaction, PaginatorError>
+where
+    C: TransactionTrait + ConnectionTrait,
+{
+    let txn = match db.get_database_backend() {
+        DbBackend::Sqlite => db.begin().await,
+        _ => {
+            db.begin_with_config(
+                Some(IsolationLevel::RepeatableRead),
+                Some(AccessMode::ReadOnly),
             )
-        } else if let Some(count) = total {
-            PaginatorResponseMeta::new(params.page, params.per_page, count as u32)
+            .await
+        }
+    };
+    txn.map_err(|e| PaginatorError::Custom(format!("Failed to begin snapshot: {}", e)))
+}
+
+/// Filters `select` by `params`, then counts and fetches one page of it on
+/// `db`, decoding rows as `M`.
+async fn fetch_page<C, E, M>(
+    select: Select<E>,
+    db: &C,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+    span: &PaginationSpan,
+) -> Result<PaginatorResponse<M>, PaginatorError>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+    <E as EntityTrait>::Model: Sync,
+    M: FromQueryResult + Sync,
+{
+    // Keyed by the unfiltered statement: the filtered one carries resolved
+    // relative dates, which change with every request.
+    let backend = db.get_database_backend();
+    let fingerprint = options.count_cache.as_ref().map(|_| {
+        let statement = select.build(backend);
+        count_fingerprint_with(&statement.to_string(), params, &options.json_columns)
+    });
+    let query = select.filter(build_filter_condition(
+        params,
+        Fields::new(backend, options, params),
+    )?);
+    let cached = options
+        .count_cache
+        .as_ref()
+        .zip(fingerprint)
+        .and_then(|(cache, fingerprint)| cache.get(fingerprint));
+
+    let store = |total: (u32, TotalKind)| {
+        if let (Some(cache), Some(fingerprint)) = (&options.count_cache, fingerprint) {
+            cache.insert(fingerprint, total, &options.cache_tags);
+        }
+    };
+
+    if cached.is_none() && options.uses_window_count(params) {
+        let (data, total) = paginate_with_window_count(query, db, params, options, span).await?;
+        store(total);
+        let response = PaginatorResponse::from_rows(data, params, Some(total));
+        span.record_result(response.data.len(), response.meta.total);
+        return Ok(response);
+    }
+
+    let needs_count = !params.disable_total_count && cached.is_none();
+    let page_query = page_select(query.clone(), params, options, backend);
+
+    let count_future = async {
+        if needs_count {
+            span.phase("count", count_total(&query, db, params))
+                .await
+                .map(Some)
+        } else if params.disable_total_count {
+            Ok(None)
         } else {
-            let has_next = data.len() as u32 > params.per_page;
-            PaginatorResponseMeta::new_without_total(params.page, params.per_page, has_next)
-        };
+            Ok(cached)
+        }
+    };
+    let data_future = async {
+        span.phase("data", page_query.into_model::<M>().all(db))
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))
+    };
+
+    let (total, data) = if options.concurrent {
+        try_join(count_future, data_future).await?
+    } else {
+        let total = count_future.await?;
+        (total, data_future.await?)
+    };
 
-        Ok(PaginatorResponse { data, meta })
+    if needs_count {
+        if let Some(total) = total {
+            store(total);
+        }
     }
+
+    let response = PaginatorResponse::from_rows(data, params, total);
+    span.record_result(response.data.len(), response.meta.total);
+    Ok(response)
 }
 
 pub async fn paginate<C, E>(
@@ -217,6 +1036,116 @@ where
     select.paginate_with(db, params).await
 }
 
+/// Like `PaginateSeaOrm::paginate_with_options`, but decodes each row as a
+/// JSON object, which also works for the partial rows of
+/// `PaginateOptions::project_fields`.
+pub async fn paginate_json<C, E>(
+    select: Select<E>,
+    db: &C,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<PaginatorResponse<JsonValue>, PaginatorError>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+    <E as EntityTrait>::Model: Sync,
+{
+    let span = PaginationSpan::new("sea-orm", params, options.trace_filter_values);
+    span.run(async {
+        let mut response: PaginatorResponse<JsonValue> =
+            fetch_page(select, db, params, options, &span).await?;
+        // JSON rows keep every selected column, including the window total.
+        if options.uses_window_count(params) {
+            for row in &mut response.data {
+                if let JsonValue::Object(row) = row {
+                    row.remove(WINDOW_TOTAL_COLUMN);
+                }
+            }
+        }
+        Ok(response)
+    })
+    .await
+}
+
+/// Returns the count and data statements `paginate` would run for `backend`,
+/// with their bind values in order, without executing anything. Fails like
+/// `paginate` on filters that cannot be built.
+pub fn plan<E>(
+    select: Select<E>,
+    backend: DbBackend,
+    params: &PaginationParams,
+) -> Result<QueryPlan, PaginatorError>
+where
+    E: EntityTrait,
+{
+    plan_with_options(select, backend, params, &PaginateOptions::default())
+}
+
+/// Like `plan`, for `paginate_with_options`. Count cache lookups are not
+/// simulated; the plan shows the statements of a cache miss.
+pub fn plan_with_options<E>(
+    select: Select<E>,
+    backend: DbBackend,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<QueryPlan, PaginatorError>
+where
+    E: EntityTrait,
+{
+    let query = select.filter(build_filter_condition(
+        params,
+        Fields::new(backend, options, params),
+    )?);
+
+    let count = if params.disable_total_count || options.uses_window_count(params) {
+        None
+    } else if params.count_strategy == CountStrategy::Estimated && backend == DbBackend::Postgres {
+        Some(estimate_statement(&query, backend))
+    } else {
+        Some(backend.build(&count_statement(&query, params)))
+    };
+
+    Ok(QueryPlan {
+        count: count.map(planned_statement),
+        data: planned_statement(page_select(query, params, options, backend).build(backend)),
+    })
+}
+
+fn planned_statement(statement: Statement) -> PlannedStatement {
+    let binds = statement
+        .values
+        .map(|values| values.0.into_iter().map(bind_value).collect())
+        .unwrap_or_default();
+    PlannedStatement {
+        sql: statement.sql,
+        binds,
+    }
+}
+
+fn bind_value(value: sea_orm::sea_query::Value) -> BindValue {
+    use sea_orm::sea_query::Value;
+
+    match value {
+        Value::Bool(Some(b)) => BindValue::Bool(b),
+        Value::TinyInt(Some(i)) => BindValue::Int(i.into()),
+        Value::SmallInt(Some(i)) => BindValue::Int(i.into()),
+        Value::Int(Some(i)) => BindValue::Int(i.into()),
+        Value::BigInt(Some(i)) => BindValue::Int(i),
+        Value::TinyUnsigned(Some(i)) => BindValue::Int(i.into()),
+        Value::SmallUnsigned(Some(i)) => BindValue::Int(i.into()),
+        Value::Unsigned(Some(i)) => BindValue::Int(i.into()),
+        Value::BigUnsigned(Some(i)) => BindValue::Int(i as i64),
+        Value::Float(Some(f)) => BindValue::Float(f.into()),
+        Value::Double(Some(f)) => BindValue::Float(f),
+        Value::String(Some(s)) => BindValue::String(*s),
+        Value::Char(Some(c)) => BindValue::String(c.to_string()),
+        Value::Uuid(Some(u)) => BindValue::String(u.to_string()),
+        Value::ChronoDateTimeUtc(Some(dt)) => BindValue::DateTime(*dt),
+        other if other == other.as_null() => BindValue::Null,
+        other => BindValue::String(format!("{:?}", other)),
+    }
+}
+
 pub async fn paginate_with_sort<C, E, F>(
     select: Select<E>,
     db: &C,
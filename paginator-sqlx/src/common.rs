@@ -1,6 +1,10 @@
-use paginator_rs::{PaginationParams, PaginatorError, PaginatorResponse};
+#[cfg(any(feature = "postgres", feature = "mysql", feature = "sqlite"))]
+use paginator_rs::{count_fingerprint_with, TotalKind};
+use paginator_rs::{
+    CountCache, ErrorCode, PaginationParams, PaginatorError, PaginatorResponse, ValidationError,
+};
 use serde::Serialize;
-use sqlx::{Database, Executor, FromRow};
+use sqlx::{Database, Executor, FromRow, Pool};
 use std::marker::PhantomData;
 
 /// Validates that a field name is safe for use in SQL queries.
@@ -8,23 +12,205 @@ use std::marker::PhantomData;
 /// Returns an error if the field name contains potentially dangerous characters.
 pub fn validate_field_name(field: &str) -> Result<(), PaginatorError> {
     if field.is_empty() {
-        return Err(PaginatorError::Custom(
-            "Field name cannot be empty".to_string(),
-        ));
+        return Err(
+            ValidationError::new(ErrorCode::InvalidField, "Field name cannot be empty").into(),
+        );
     }
 
     for c in field.chars() {
         if !c.is_alphanumeric() && c != '_' && c != '.' {
-            return Err(PaginatorError::Custom(format!(
-                "Invalid field name '{}': contains unsafe character '{}'",
-                field, c
-            )));
+            return Err(ValidationError::new(
+                ErrorCode::InvalidField,
+                format!(
+                    "Invalid field name '{}': contains unsafe character '{}'",
+                    field, c
+                ),
+            )
+            .into());
         }
     }
 
     Ok(())
 }
 
+/// Execution options for `paginate_query_with_options`.
+#[derive(Clone, Debug, Default)]
+pub struct PaginateOptions {
+    pub(crate) count_cache: Option<CountCache>,
+    pub(crate) cache_tags: Vec<String>,
+    pub(crate) concurrent: bool,
+    pub(crate) window_count: bool,
+    pub(crate) deferred_join_key: Option<String>,
+    pub(crate) trace_filter_values: bool,
+    pub(crate) project_fields: bool,
+    pub(crate) json_columns: Vec<String>,
+}
+
+impl PaginateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skips the COUNT statement when `cache` holds a total for the same
+    /// base query, filters and search. See `CountCache` for `tags`.
+    pub fn count_cache<I, S>(mut self, cache: CountCache, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.count_cache = Some(cache);
+        self.cache_tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Issues the COUNT and data statements concurrently instead of one after
+    /// the other. Each statement takes its own connection, so only use this
+    /// with a pool executor.
+    pub fn concurrent(mut self, yes: bool) -> Self {
+        self.concurrent = yes;
+        self
+    }
+
+    /// Computes exact totals with `COUNT(*) OVER ()` inside the data query,
+    /// saving the separate COUNT statement. Requires window function support
+    /// (PostgreSQL, MySQL 8+, SQLite 3.25+).
+    ///
+    /// Ignored for cursor pagination and non-exact count strategies. When a
+    /// page past the end comes back empty, a regular COUNT is run instead.
+    pub fn window_count(mut self, yes: bool) -> Self {
+        self.window_count = yes;
+        self
+    }
+
+    /// Fetches offset pages with a deferred join: the page is first selected
+    /// as `key_column` values only, then joined back to the full rows. This
+    /// keeps deep pages from materializing every skipped row.
+    ///
+    /// `key_column` must be a unique column of the base query's result, and
+    /// `sort_by` must name a result column rather than a table-qualified one.
+    /// Cursor pages and distance sorts are unaffected, and `window_count`
+    /// takes precedence.
+    pub fn deferred_join(mut self, key_column: impl Into<String>) -> Self {
+        self.deferred_join_key = Some(key_column.into());
+        self
+    }
+
+    /// Records filter values in `tracing` spans instead of redacting them to
+    /// `?`. Only has an effect with the `tracing` feature.
+    pub fn trace_filter_values(mut self, yes: bool) -> Self {
+        self.trace_filter_values = yes;
+        self
+    }
+
+    /// Selects only `PaginationParams::fields` in the data query instead of
+    /// every column of the base query. The row type must decode from that
+    /// subset, e.g. with `Option` fields or `#[sqlx(default)]`; otherwise
+    /// leave this off and prune with `paginator_rs::select_fields`.
+    pub fn project_fields(mut self, yes: bool) -> Self {
+        self.project_fields = yes;
+        self
+    }
+
+    /// Declares JSON columns whose contents can be filtered, sorted and
+    /// paged over with paths such as `attrs.color` or `attrs[0].sku`.
+    ///
+    /// Paths compile to the dialect's JSON extraction (`jsonb_extract_path`
+    /// on PostgreSQL, `JSON_EXTRACT` on MySQL, `json_extract` on SQLite) with
+    /// every path segment bound. PostgreSQL columns must be `jsonb`. Dotted
+    /// names under other columns keep their `table.column` meaning.
+    pub fn json_columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.json_columns = columns.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "mysql", feature = "sqlite"))]
+impl PaginateOptions {
+    /// Whether the total should come from `window_count_query`.
+    pub(crate) fn uses_window_count(&self, params: &PaginationParams) -> bool {
+        self.window_count
+            && params.cursor.is_none()
+            && !params.disable_total_count
+            && params.count_strategy.is_exact()
+    }
+
+    /// Whether the data query selects `params.fields` instead of `*`.
+    pub(crate) fn projects(&self, params: &PaginationParams) -> bool {
+        self.project_fields && params.fields.is_some()
+    }
+
+    /// Cache key of the count; `json_columns` decides how filter paths
+    /// compile, so it is part of the key.
+    fn count_fingerprint(&self, base_query: &str, params: &PaginationParams) -> u64 {
+        count_fingerprint_with(base_query, params, &self.json_columns)
+    }
+
+    pub(crate) fn cached_total(
+        &self,
+        base_query: &str,
+        params: &PaginationParams,
+    ) -> Option<(u32, TotalKind)> {
+        self.count_cache
+            .as_ref()
+            .and_then(|cache| cache.get(self.count_fingerprint(base_query, params)))
+    }
+
+    pub(crate) fn store_total(
+        &self,
+        base_query: &str,
+        params: &PaginationParams,
+        total: (u32, TotalKind),
+    ) {
+        if let Some(cache) = &self.count_cache {
+            cache.insert(
+                self.count_fingerprint(base_query, params),
+                total,
+                &self.cache_tags,
+            );
+        }
+    }
+}
+
+/// A base query on a pool as a `PaginatedSource`, paged with
+/// `paginate_query_with_options` of the pool's database.
+pub struct SqlxSource<DB: Database, T> {
+    pub(crate) pool: Pool<DB>,
+    pub(crate) base_query: String,
+    pub(crate) options: PaginateOptions,
+    _row: PhantomData<fn() -> T>,
+}
+
+impl<DB: Database, T> SqlxSource<DB, T> {
+    pub fn new(pool: Pool<DB>, base_query: impl Into<String>) -> Self {
+        Self {
+            pool,
+            base_query: base_query.into(),
+            options: PaginateOptions::default(),
+            _row: PhantomData,
+        }
+    }
+
+    pub fn options(mut self, options: PaginateOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<DB: Database, T> Clone for SqlxSource<DB, T> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            base_query: self.base_query.clone(),
+            options: self.options.clone(),
+            _row: PhantomData,
+        }
+    }
+}
+
 pub trait PaginateQuery<'q, DB: Database, T>
 where
     T: Send + Unpin,
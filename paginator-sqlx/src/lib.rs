@@ -1,5 +1,7 @@
 mod common;
 mod query_builder;
+#[cfg(any(feature = "postgres", feature = "mysql", feature = "sqlite"))]
+mod statement;
 
 #[cfg(feature = "postgres")]
 pub mod postgres;
@@ -10,5 +12,5 @@ pub mod mysql;
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
 
-pub use common::{validate_field_name, PaginateQuery, PaginatedQuery};
+pub use common::{validate_field_name, PaginateOptions, PaginateQuery, PaginatedQuery, SqlxSource};
 pub use query_builder::QueryBuilderExt;
@@ -1,14 +1,18 @@
-use crate::common::{validate_field_name, PaginateQuery, PaginatedQuery};
-use crate::query_builder::QueryBuilderExt;
+use crate::common::{PaginateOptions, PaginateQuery, PaginatedQuery, SqlxSource};
+use crate::statement::{
+    self, count_query, data_query, fetch_window_page, finish, total_query, Dialect,
+};
+use async_trait::async_trait;
+use futures_util::future::try_join;
 use paginator_rs::{
-    CursorDirection, CursorValue, PaginationParams, PaginatorError, PaginatorResponse,
-    PaginatorResponseMeta,
+    parse_explain_rows, CountStrategy, PaginatedSource, PaginationParams, PaginationSpan,
+    PaginatorError, PaginatorResponse, QueryPlan, TotalKind,
 };
 use paginator_utils::IntoPaginationParams;
 use serde::Serialize;
-use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::postgres::{PgArguments, PgConnection, PgRow};
 use sqlx::query_builder::QueryBuilder;
-use sqlx::{query::Query, Executor, FromRow, Postgres};
+use sqlx::{query::Query, Acquire, Executor, FromRow, Pool, Postgres};
 
 impl<'q, T> PaginateQuery<'q, Postgres, T> for Query<'q, Postgres, PgArguments>
 where
@@ -19,8 +23,93 @@ where
     }
 }
 
-fn is_cte_query(query: &str) -> bool {
-    query.trim().to_uppercase().starts_with("WITH")
+#[async_trait]
+impl<T> PaginatedSource<T> for SqlxSource<Postgres, T>
+where
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin + Serialize,
+{
+    async fn fetch_page(
+        &self,
+        params: &PaginationParams,
+    ) -> Result<PaginatorResponse<T>, PaginatorError> {
+        paginate_query_with_options(&self.pool, &self.base_query, params, &self.options).await
+    }
+}
+
+/// Reads the planner's row estimate for the filtered query from `EXPLAIN`,
+/// running the statement `total_query` plans for estimated counts.
+async fn estimate_total<'e, E>(
+    executor: E,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<Option<u64>, PaginatorError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let Some(explain) = total_query(Dialect::Postgres, base_query, params, options) else {
+        return Ok(None);
+    };
+    let mut explain_builder: QueryBuilder<Postgres> = explain.build();
+    let plan: Vec<(String,)> = explain_builder
+        .build_query_as()
+        .fetch_all(executor)
+        .await
+        .map_err(|e| PaginatorError::Custom(format!("Estimate query failed: {}", e)))?;
+
+    Ok(plan.first().and_then(|(line,)| parse_explain_rows(line)))
+}
+
+/// Computes the total for `base_query` according to `params.count_strategy`.
+async fn count_total<'e, E>(
+    executor: E,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<(u32, TotalKind), PaginatorError>
+where
+    E: Executor<'e, Database = Postgres> + Clone,
+{
+    if params.count_strategy == CountStrategy::Estimated {
+        if let Some(rows) = estimate_total(executor.clone(), base_query, params, options).await? {
+            return Ok((rows.min(u32::MAX as u64) as u32, TotalKind::Estimate));
+        }
+    }
+    exact_total(executor, base_query, params, options).await
+}
+
+/// Like `count_total`, running both the estimate and the count on `conn`.
+async fn count_total_on(
+    conn: &mut PgConnection,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<(u32, TotalKind), PaginatorError> {
+    if params.count_strategy == CountStrategy::Estimated {
+        if let Some(rows) = estimate_total(&mut *conn, base_query, params, options).await? {
+            return Ok((rows.min(u32::MAX as u64) as u32, TotalKind::Estimate));
+        }
+    }
+    exact_total(conn, base_query, params, options).await
+}
+
+async fn exact_total<'e, E>(
+    executor: E,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<(u32, TotalKind), PaginatorError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let mut count_builder: QueryBuilder<Postgres> =
+        count_query(Dialect::Postgres, base_query, params, options).build();
+    let (count,) = count_builder
+        .build_query_as::<(i64,)>()
+        .fetch_one(executor)
+        .await
+        .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?;
+    Ok(params.count_strategy.resolve(count as u64))
 }
 
 pub async fn paginate_query<'e, E, T, P>(
@@ -28,189 +117,255 @@ pub async fn paginate_query<'e, E, T, P>(
     base_query: &str,
     params: P,
 ) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    E: Executor<'e, Database = Postgres> + Clone,
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin + Serialize,
+    P: IntoPaginationParams,
+{
+    paginate_query_with_options(executor, base_query, params, &PaginateOptions::default()).await
+}
+
+/// Like `paginate_query`, with caching and execution behaviour taken from `options`.
+pub async fn paginate_query_with_options<'e, E, T, P>(
+    executor: E,
+    base_query: &str,
+    params: P,
+    options: &PaginateOptions,
+) -> Result<PaginatorResponse<T>, PaginatorError>
 where
     E: Executor<'e, Database = Postgres> + Clone,
     T: for<'r> FromRow<'r, PgRow> + Send + Unpin + Serialize,
     P: IntoPaginationParams,
 {
     let params = params.into_pagination_params();
-    let has_filters_or_search = !params.filters.is_empty() || params.search.is_some();
-
-    let count_query_str = if is_cte_query(base_query) {
-        if has_filters_or_search {
-            format!(
-                "{}, _paginator_filtered AS (SELECT * FROM ({}) AS _base WHERE 1=1",
-                base_query.trim_end_matches(';'),
-                base_query
-            )
-        } else {
-            format!("SELECT COUNT(*) FROM ({}) as count_subquery", base_query)
-        }
-    } else {
-        if has_filters_or_search {
-            format!("SELECT COUNT(*) FROM ({}) AS _base WHERE 1=1", base_query)
+
+    let span = PaginationSpan::new("postgres", &params, options.trace_filter_values);
+    span.run(async {
+        let cached = if params.disable_total_count {
+            None
         } else {
-            format!("SELECT COUNT(*) FROM ({}) as count_subquery", base_query)
+            options.cached_total(base_query, &params)
+        };
+
+        if cached.is_none() && options.uses_window_count(&params) {
+            let (data, total) = fetch_window_page(
+                Dialect::Postgres,
+                executor.clone(),
+                base_query,
+                &params,
+                options,
+                &span,
+            )
+            .await?;
+            let total = match total {
+                Some(total) => total,
+                None => {
+                    span.phase("count", count_total(executor, base_query, &params, options))
+                        .await?
+                }
+            };
+            options.store_total(base_query, &params, total);
+            return Ok(finish(data, &params, Some(total), &span));
         }
-    };
 
-    let total = if params.disable_total_count {
-        None
-    } else {
-        let count = if has_filters_or_search {
-            let mut count_builder: QueryBuilder<Postgres> = QueryBuilder::new(&count_query_str);
-            count_builder.push_filters(&params);
-            count_builder.push_search(&params);
+        let needs_count = !params.disable_total_count && cached.is_none();
+        let mut data_builder: QueryBuilder<Postgres> =
+            data_query(Dialect::Postgres, base_query, &params, options)?.build();
 
-            if is_cte_query(base_query) {
-                count_builder.push(") SELECT COUNT(*) FROM _paginator_filtered");
+        let count_future = async {
+            if needs_count {
+                span.phase(
+                    "count",
+                    count_total(executor.clone(), base_query, &params, options),
+                )
+                .await
+                .map(Some)
+            } else {
+                Ok(cached)
             }
+        };
+        let data_future = async {
+            span.phase(
+                "data",
+                data_builder
+                    .build_query_as::<T>()
+                    .fetch_all(executor.clone()),
+            )
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))
+        };
 
-            let count_query = count_builder.build_query_as::<(i64,)>();
-            let (total,) = count_query
-                .fetch_one(executor.clone())
-                .await
-                .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?;
-            total
+        let (total, data) = if options.concurrent {
+            try_join(count_future, data_future).await?
         } else {
-            let (total,): (i64,) = sqlx::query_as(&count_query_str)
-                .fetch_one(executor.clone())
-                .await
-                .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?;
-            total
+            let total = count_future.await?;
+            (total, data_future.await?)
         };
-        Some(count)
-    };
 
-    let data_query_str = if is_cte_query(base_query) {
-        if has_filters_or_search {
-            format!(
-                "{}, _paginator_filtered AS (SELECT * FROM ({}) AS _base WHERE 1=1",
-                base_query.trim_end_matches(';'),
-                base_query
-            )
-        } else {
-            base_query.to_string()
+        if let (true, Some(total)) = (needs_count, total) {
+            options.store_total(base_query, &params, total);
         }
-    } else {
-        if has_filters_or_search {
-            format!("SELECT * FROM ({}) AS _base WHERE 1=1", base_query)
-        } else {
-            base_query.to_string()
-        }
-    };
 
-    let mut data_builder: QueryBuilder<Postgres> = QueryBuilder::new(&data_query_str);
+        Ok(finish(data, &params, total, &span))
+    })
+    .await
+}
 
-    if has_filters_or_search {
-        data_builder.push_filters(&params);
-        data_builder.push_search(&params);
+/// Like `paginate_query`, for a single connection or an open transaction
+/// (`&mut PgConnection`, `&mut Transaction<'_, Postgres>` or `&Pool<Postgres>`).
+pub async fn paginate_query_on<'a, A, T, P>(
+    conn: A,
+    base_query: &str,
+    params: P,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    A: Acquire<'a, Database = Postgres>,
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin + Serialize,
+    P: IntoPaginationParams,
+{
+    paginate_query_on_with_options(conn, base_query, params, &PaginateOptions::default()).await
+}
 
-        if is_cte_query(base_query) {
-            data_builder.push(") SELECT * FROM _paginator_filtered");
-        }
-    }
+/// Like `paginate_query_with_options`, for a single connection or an open
+/// transaction. The count and data statements run one after the other on that
+/// connection, so session state such as `SET LOCAL` applies to both;
+/// `concurrent` is ignored.
+pub async fn paginate_query_on_with_options<'a, A, T, P>(
+    conn: A,
+    base_query: &str,
+    params: P,
+    options: &PaginateOptions,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    A: Acquire<'a, Database = Postgres>,
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin + Serialize,
+    P: IntoPaginationParams,
+{
+    let params = params.into_pagination_params();
 
-    if let Some(ref cursor) = params.cursor {
-        // Validate cursor field name to prevent SQL injection
-        validate_field_name(&cursor.field)?;
-
-        let operator = match cursor.direction {
-            CursorDirection::After => match params.sort_direction.as_ref() {
-                Some(paginator_rs::SortDirection::Desc) => "<",
-                _ => ">",
-            },
-            CursorDirection::Before => match params.sort_direction.as_ref() {
-                Some(paginator_rs::SortDirection::Desc) => ">",
-                _ => "<",
-            },
-        };
+    let span = PaginationSpan::new("postgres", &params, options.trace_filter_values);
+    span.run(async {
+        let mut conn = conn
+            .acquire()
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Failed to acquire connection: {}", e)))?;
+        paginate_on_connection(&mut conn, base_query, &params, options, &span).await
+    })
+    .await
+}
 
-        if !has_filters_or_search {
-            data_builder.push(" WHERE ");
-        } else {
-            data_builder.push(" AND ");
-        }
+/// Like `paginate_query_with_options`, but runs the count and data statements
+/// inside one `REPEATABLE READ` read-only transaction taken from `pool`, so
+/// `total` and `data` come from the same snapshot even under concurrent
+/// writes. The statements run one after the other; `concurrent` is ignored.
+pub async fn paginate_query_snapshot<T, P>(
+    pool: &Pool<Postgres>,
+    base_query: &str,
+    params: P,
+    options: &PaginateOptions,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin + Serialize,
+    P: IntoPaginationParams,
+{
+    let params = params.into_pagination_params();
 
-        data_builder.push(&cursor.field);
-        data_builder.push(" ");
-        data_builder.push(operator);
-        data_builder.push(" ");
+    let span = PaginationSpan::new("postgres", &params, options.trace_filter_values);
+    span.run(async {
+        let mut tx = pool
+            .begin_with("BEGIN ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Failed to begin snapshot: {}", e)))?;
+        let response = paginate_on_connection(&mut tx, base_query, &params, options, &span).await?;
+        tx.commit()
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Failed to end snapshot: {}", e)))?;
+        Ok(response)
+    })
+    .await
+}
 
-        match &cursor.value {
-            CursorValue::String(s) => {
-                data_builder.push_bind(s.clone());
-            }
-            CursorValue::Int(i) => {
-                data_builder.push_bind(*i);
-            }
-            CursorValue::Float(f) => {
-                data_builder.push_bind(*f);
-            }
-            CursorValue::Uuid(u) => {
-                data_builder.push_bind(u.clone());
-                data_builder.push("::uuid");
-            }
-        }
-    }
+/// Returns the count and data statements `paginate_query` would run, with
+/// their bind values in order, without executing anything.
+pub fn plan<P>(base_query: &str, params: P) -> Result<QueryPlan, PaginatorError>
+where
+    P: IntoPaginationParams,
+{
+    plan_with_options(base_query, params, &PaginateOptions::default())
+}
 
-    if let Some(ref sort_field) = params.sort_by {
-        // Validate sort field name to prevent SQL injection
-        validate_field_name(sort_field)?;
+/// Like `plan`, for `paginate_query_with_options`. Count cache lookups are
+/// not simulated; the plan shows the statements of a cache miss.
+pub fn plan_with_options<P>(
+    base_query: &str,
+    params: P,
+    options: &PaginateOptions,
+) -> Result<QueryPlan, PaginatorError>
+where
+    P: IntoPaginationParams,
+{
+    let params = params.into_pagination_params();
+    statement::plan::<Postgres>(Dialect::Postgres, base_query, &params, options)
+}
 
-        data_builder.push(" ORDER BY ");
-        data_builder.push(sort_field);
-        match params.sort_direction.as_ref() {
-            Some(paginator_rs::SortDirection::Desc) => data_builder.push(" DESC"),
-            _ => data_builder.push(" ASC"),
+/// Runs the count and data statements one after the other on `conn`.
+async fn paginate_on_connection<T>(
+    conn: &mut PgConnection,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+    span: &PaginationSpan,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin + Serialize,
+{
+    let cached = if params.disable_total_count {
+        None
+    } else {
+        options.cached_total(base_query, params)
+    };
+
+    if cached.is_none() && options.uses_window_count(params) {
+        let (data, total) = fetch_window_page(
+            Dialect::Postgres,
+            &mut *conn,
+            base_query,
+            params,
+            options,
+            span,
+        )
+        .await?;
+        let total = match total {
+            Some(total) => total,
+            None => {
+                span.phase("count", count_total_on(conn, base_query, params, options))
+                    .await?
+            }
         };
+        options.store_total(base_query, params, total);
+        return Ok(finish(data, params, Some(total), span));
     }
 
-    if params.cursor.is_some() {
-        data_builder.push(" LIMIT ");
-        data_builder.push_bind((params.limit() + 1) as i64);
-    } else if params.disable_total_count {
-        // Fetch one extra row to detect if there's a next page
-        data_builder.push(" LIMIT ");
-        data_builder.push_bind((params.limit() + 1) as i64);
-        data_builder.push(" OFFSET ");
-        data_builder.push_bind(params.offset() as i64);
+    let needs_count = !params.disable_total_count && cached.is_none();
+    let total = if needs_count {
+        let total = span
+            .phase(
+                "count",
+                count_total_on(&mut *conn, base_query, params, options),
+            )
+            .await?;
+        options.store_total(base_query, params, total);
+        Some(total)
     } else {
-        data_builder.push(" LIMIT ");
-        data_builder.push_bind(params.limit() as i64);
-        data_builder.push(" OFFSET ");
-        data_builder.push_bind(params.offset() as i64);
-    }
+        cached
+    };
 
-    let data_query = data_builder.build_query_as::<T>();
-    let mut data = data_query
-        .fetch_all(executor)
+    let mut data_builder: QueryBuilder<Postgres> =
+        data_query(Dialect::Postgres, base_query, params, options)?.build();
+    let data = span
+        .phase("data", data_builder.build_query_as::<T>().fetch_all(conn))
         .await
         .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?;
 
-    let meta = if params.cursor.is_some() {
-        let has_next = data.len() > params.per_page as usize;
-        if has_next {
-            data.truncate(params.per_page as usize);
-        }
-        PaginatorResponseMeta::new_with_cursors(
-            params.page,
-            params.per_page,
-            total.map(|t| t as u32),
-            has_next,
-            None,
-            None,
-        )
-    } else if let Some(count) = total {
-        PaginatorResponseMeta::new(params.page, params.per_page, count as u32)
-    } else {
-        // disable_total_count is true - we fetched one extra row to detect next page
-        let has_next = data.len() > params.per_page as usize;
-        if has_next {
-            data.truncate(params.per_page as usize);
-        }
-        PaginatorResponseMeta::new_without_total(params.page, params.per_page, has_next)
-    };
-
-    Ok(PaginatorResponse { data, meta })
+    Ok(finish(data, params, total, span))
 }
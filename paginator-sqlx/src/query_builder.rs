@@ -1,4 +1,8 @@
-use paginator_rs::{Filter, FilterOperator, FilterValue, PaginationParams};
+use paginator_rs::chrono::{DateTime, Utc};
+use paginator_rs::{
+    polygon_wkt, BindValue, FieldPath, Filter, FilterOperator, FilterValue, GeoPoint,
+    PaginationParams, LIKE_ESCAPE,
+};
 use sqlx::query_builder::QueryBuilder;
 use sqlx::Database;
 
@@ -14,128 +18,540 @@ where
     f64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
     bool: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
     String: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+    DateTime<Utc>: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
     &'args str: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
 {
     fn push_filter(&mut self, filter: &Filter) -> &mut Self {
-        self.push(&filter.field);
+        let mut statement = Statement::default();
+        statement.push_filter(filter, Fields::plain(Dialect::of::<DB>()));
+        statement.append_to(self);
+        self
+    }
 
-        match &filter.operator {
-            FilterOperator::Eq => {
-                self.push(" = ");
-                bind_value(self, &filter.value);
-            }
-            FilterOperator::Ne => {
-                self.push(" != ");
-                bind_value(self, &filter.value);
-            }
-            FilterOperator::Gt => {
-                self.push(" > ");
-                bind_value(self, &filter.value);
-            }
-            FilterOperator::Lt => {
-                self.push(" < ");
-                bind_value(self, &filter.value);
+    fn push_filters(&mut self, params: &PaginationParams) -> &mut Self {
+        let mut statement = Statement::default();
+        statement.push_filters(params, Fields::plain(Dialect::of::<DB>()));
+        statement.append_to(self);
+        self
+    }
+
+    fn push_search(&mut self, params: &PaginationParams) -> &mut Self {
+        let mut statement = Statement::default();
+        statement.push_search(params, Fields::plain(Dialect::of::<DB>()));
+        statement.append_to(self);
+        self
+    }
+}
+
+/// SQL dialect differences the shared statement builders need to know about.
+/// Variants of dialects whose feature is disabled are never constructed.
+#[cfg_attr(
+    not(all(feature = "postgres", feature = "mysql", feature = "sqlite")),
+    allow(dead_code)
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+impl Dialect {
+    /// The dialect of `DB`. Databases other than the three supported ones
+    /// are treated as PostgreSQL, whose syntax the generic builders follow.
+    fn of<DB: Database>() -> Self {
+        match DB::NAME {
+            "MySQL" => Dialect::MySql,
+            "SQLite" => Dialect::Sqlite,
+            _ => Dialect::Postgres,
+        }
+    }
+}
+
+/// Decides how field names are written: as plain columns, or as JSON
+/// extraction for paths under a column of `PaginateOptions::json_columns`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Fields<'a> {
+    dialect: Dialect,
+    json_columns: &'a [String],
+}
+
+impl<'a> Fields<'a> {
+    #[cfg(any(feature = "postgres", feature = "mysql", feature = "sqlite"))]
+    pub(crate) fn new(dialect: Dialect, options: &'a crate::PaginateOptions) -> Self {
+        Self {
+            dialect,
+            json_columns: &options.json_columns,
+        }
+    }
+
+    /// Plain columns only, for the `QueryBuilderExt` methods.
+    fn plain(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            json_columns: &[],
+        }
+    }
+
+    /// Parses `field` as a JSON path if it descends into a JSON column.
+    pub(crate) fn json_path(&self, field: &str) -> Option<FieldPath> {
+        let column = field.split(['.', '[']).next()?;
+        if !self.json_columns.iter().any(|c| c == column) {
+            return None;
+        }
+        FieldPath::parse(field)
+            .ok()
+            .filter(|path| !path.segments().is_empty())
+    }
+
+    /// Whether comparisons against JSON paths need their operands converted
+    /// to JSON: PostgreSQL compares `jsonb` only with `jsonb`.
+    pub(crate) fn wraps_operands(&self, path: Option<&FieldPath>) -> bool {
+        path.is_some() && self.dialect == Dialect::Postgres
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Part {
+    Sql(String),
+    Bind(BindValue),
+}
+
+/// SQL text interleaved with bind values, independent of the database.
+///
+/// Statements are assembled once and then either turned into a sqlx
+/// `QueryBuilder` for execution or rendered for a `QueryPlan`, so both always
+/// agree.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Statement {
+    pub(crate) parts: Vec<Part>,
+}
+
+impl Statement {
+    pub(crate) fn push(&mut self, sql: impl AsRef<str>) -> &mut Self {
+        match self.parts.last_mut() {
+            Some(Part::Sql(last)) => last.push_str(sql.as_ref()),
+            _ => self.parts.push(Part::Sql(sql.as_ref().to_string())),
+        }
+        self
+    }
+
+    /// Binds `value`. `BindValue::Null` is written as a `NULL` literal, since
+    /// an untyped null cannot be bound.
+    pub(crate) fn push_bind(&mut self, value: impl Into<BindValue>) -> &mut Self {
+        match value.into() {
+            BindValue::Null => self.push("NULL"),
+            value => {
+                self.parts.push(Part::Bind(value));
+                self
             }
-            FilterOperator::Gte => {
-                self.push(" >= ");
-                bind_value(self, &filter.value);
+        }
+    }
+
+    /// Appends this statement to `builder`, binding values in order.
+    pub(crate) fn append_to<'args, DB>(self, builder: &mut QueryBuilder<'args, DB>)
+    where
+        DB: Database,
+        i64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        f64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        bool: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        String: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        DateTime<Utc>: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+    {
+        for part in self.parts {
+            match part {
+                Part::Sql(sql) => {
+                    builder.push(sql);
+                }
+                Part::Bind(BindValue::Null) => {
+                    builder.push("NULL");
+                }
+                Part::Bind(BindValue::Bool(b)) => {
+                    builder.push_bind(b);
+                }
+                Part::Bind(BindValue::Int(i)) => {
+                    builder.push_bind(i);
+                }
+                Part::Bind(BindValue::Float(f)) => {
+                    builder.push_bind(f);
+                }
+                Part::Bind(BindValue::String(s)) => {
+                    builder.push_bind(s);
+                }
+                Part::Bind(BindValue::DateTime(dt)) => {
+                    builder.push_bind(dt);
+                }
             }
-            FilterOperator::Lte => {
-                self.push(" <= ");
-                bind_value(self, &filter.value);
+        }
+    }
+
+    pub(crate) fn push_filter(&mut self, filter: &Filter, fields: Fields) -> &mut Self {
+        let path = fields.json_path(&filter.field);
+        let path = path.as_ref();
+        let wrap = fields.wraps_operands(path);
+
+        match &filter.operator {
+            FilterOperator::Eq => self.push_comparison(filter, path, fields, " = "),
+            FilterOperator::Ne => self.push_comparison(filter, path, fields, " != "),
+            FilterOperator::Gt => self.push_comparison(filter, path, fields, " > "),
+            FilterOperator::Lt => self.push_comparison(filter, path, fields, " < "),
+            FilterOperator::Gte => self.push_comparison(filter, path, fields, " >= "),
+            FilterOperator::Lte => self.push_comparison(filter, path, fields, " <= "),
+            FilterOperator::Contains => self.push_comparison(filter, path, fields, " @> "),
+            FilterOperator::Like | FilterOperator::ILike => {
+                self.push_field_ref(&filter.field, path, fields, true);
+                self.push(if filter.operator == FilterOperator::Like {
+                    " LIKE "
+                } else {
+                    " ILIKE "
+                });
+                self.push_value(&filter.value);
             }
-            FilterOperator::Like => {
+            FilterOperator::StartsWith | FilterOperator::EndsWith => {
+                self.push_field_ref(&filter.field, path, fields, true);
                 self.push(" LIKE ");
-                bind_value(self, &filter.value);
+                self.push_bind(filter.like_pattern().unwrap_or_default());
+                self.push(format!(" ESCAPE '{}'", LIKE_ESCAPE));
             }
-            FilterOperator::ILike => {
-                self.push(" ILIKE ");
-                bind_value(self, &filter.value);
+            FilterOperator::IEq => {
+                self.push("LOWER(");
+                self.push_field_ref(&filter.field, path, fields, true);
+                self.push(") = LOWER(");
+                self.push_bind(filter.value.to_text());
+                self.push(")");
             }
-            FilterOperator::In => {
-                if let FilterValue::Array(values) = &filter.value {
-                    self.push(" IN (");
-                    let mut separated = self.separated(", ");
-                    for value in values {
-                        match value {
-                            FilterValue::String(s) => {
-                                separated.push_bind(s.clone());
-                            }
-                            FilterValue::Int(i) => {
-                                separated.push_bind(*i);
-                            }
-                            FilterValue::Float(f) => {
-                                separated.push_bind(*f);
-                            }
-                            FilterValue::Bool(b) => {
-                                separated.push_bind(*b);
-                            }
-                            _ => {}
-                        };
-                    }
-                    self.push(")");
-                }
+            FilterOperator::Regex => {
+                self.push_field_ref(&filter.field, path, fields, true);
+                self.push(match fields.dialect {
+                    Dialect::Postgres => " ~ ",
+                    // SQLite only knows REGEXP once a regexp() function is
+                    // registered, e.g. with sqlx's `regexp` feature.
+                    Dialect::MySql | Dialect::Sqlite => " REGEXP ",
+                });
+                self.push_bind(filter.value.to_text());
             }
-            FilterOperator::NotIn => {
+            FilterOperator::In | FilterOperator::NotIn => {
+                self.push_field_ref(&filter.field, path, fields, false);
                 if let FilterValue::Array(values) = &filter.value {
-                    self.push(" NOT IN (");
-                    let mut separated = self.separated(", ");
-                    for value in values {
-                        match value {
-                            FilterValue::String(s) => {
-                                separated.push_bind(s.clone());
-                            }
-                            FilterValue::Int(i) => {
-                                separated.push_bind(*i);
-                            }
-                            FilterValue::Float(f) => {
-                                separated.push_bind(*f);
-                            }
-                            FilterValue::Bool(b) => {
-                                separated.push_bind(*b);
-                            }
-                            _ => {}
-                        };
-                    }
+                    self.push(if filter.operator == FilterOperator::In {
+                        " IN ("
+                    } else {
+                        " NOT IN ("
+                    });
+                    self.push_list(values, wrap);
                     self.push(")");
                 }
             }
             FilterOperator::IsNull => {
+                self.push_field_ref(&filter.field, path, fields, false);
                 self.push(" IS NULL");
             }
             FilterOperator::IsNotNull => {
+                self.push_field_ref(&filter.field, path, fields, false);
                 self.push(" IS NOT NULL");
             }
             FilterOperator::Between => {
+                self.push_field_ref(&filter.field, path, fields, false);
                 if let FilterValue::Array(arr) = &filter.value {
                     if arr.len() == 2 {
                         self.push(" BETWEEN ");
-                        bind_value(self, &arr[0]);
+                        self.push_operand(&arr[0], wrap);
                         self.push(" AND ");
-                        bind_value(self, &arr[1]);
+                        self.push_operand(&arr[1], wrap);
                     }
                 }
             }
-            FilterOperator::Contains => {
-                self.push(" @> ");
-                bind_value(self, &filter.value);
+            FilterOperator::ContainsAll
+            | FilterOperator::ContainsAny
+            | FilterOperator::LengthEq
+            | FilterOperator::LengthGt
+            | FilterOperator::LengthGte
+            | FilterOperator::LengthLt
+            | FilterOperator::LengthLte => {
+                self.push_array_filter(filter, path, fields);
+            }
+            FilterOperator::WithinRadius
+            | FilterOperator::WithinBox
+            | FilterOperator::WithinPolygon => {
+                self.push_geo_filter(filter, fields);
             }
         }
 
         self
     }
 
-    fn push_filters(&mut self, params: &PaginationParams) -> &mut Self {
-        if !params.filters.is_empty() {
-            for filter in &params.filters {
-                self.push(" AND ");
-                self.push_filter(filter);
+    /// Pushes `field <op> value`, the value converted to JSON for JSON paths
+    /// where the dialect needs it.
+    fn push_comparison(
+        &mut self,
+        filter: &Filter,
+        path: Option<&FieldPath>,
+        fields: Fields,
+        op: &str,
+    ) {
+        let wrap = fields.wraps_operands(path);
+        self.push_field_ref(&filter.field, path, fields, false);
+        self.push(op);
+        self.push_operand(&filter.value, wrap);
+    }
+
+    /// Pushes an array operator. PostgreSQL compares native arrays, or
+    /// `jsonb` arrays for JSON paths; MySQL and SQLite store arrays as JSON.
+    fn push_array_filter(
+        &mut self,
+        filter: &Filter,
+        path: Option<&FieldPath>,
+        fields: Fields,
+    ) -> &mut Self {
+        let values = filter.value.elements();
+        let json = path.is_some();
+
+        if let Some(comparison) = filter.operator.length_comparison() {
+            self.push(match fields.dialect {
+                Dialect::Postgres if json => "jsonb_array_length(",
+                Dialect::Postgres => "cardinality(",
+                Dialect::MySql => "JSON_LENGTH(",
+                Dialect::Sqlite => "json_array_length(",
+            });
+            self.push_field_ref(&filter.field, path, fields, false);
+            self.push(format!(") {} ", comparison));
+            self.push_value(&filter.value);
+            return self;
+        }
+
+        let all = filter.operator == FilterOperator::ContainsAll;
+        if values.is_empty() {
+            // Every array holds all of nothing, and none holds any of it.
+            return self.push(if all { "1=1" } else { "1=0" });
+        }
+
+        match fields.dialect {
+            Dialect::Postgres if json => {
+                // `@>` with a one-element array tests membership of that element.
+                let groups: Vec<&[FilterValue]> = if all {
+                    vec![values]
+                } else {
+                    values.chunks(1).collect()
+                };
+                self.push("(");
+                for (idx, group) in groups.into_iter().enumerate() {
+                    if idx > 0 {
+                        self.push(" OR ");
+                    }
+                    self.push_field_ref(&filter.field, path, fields, false);
+                    self.push(" @> jsonb_build_array(");
+                    self.push_list(group, false);
+                    self.push(")");
+                }
+                self.push(")")
+            }
+            // `= ANY(col)` tests membership of one element and, unlike `@>`
+            // against a bound `bigint[]`, works for `int4[]` or `varchar[]`
+            // columns, so `@>` becomes every element being a member and `&&`
+            // any of them.
+            Dialect::Postgres => {
+                self.push("(");
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        self.push(if all { " AND " } else { " OR " });
+                    }
+                    self.push_value(value);
+                    self.push(" = ANY(");
+                    self.push_field_ref(&filter.field, path, fields, false);
+                    self.push(")");
+                }
+                self.push(")")
+            }
+            Dialect::MySql => {
+                self.push(if all {
+                    "JSON_CONTAINS("
+                } else {
+                    "JSON_OVERLAPS("
+                });
+                self.push_field_ref(&filter.field, path, fields, false);
+                self.push(", JSON_ARRAY(");
+                self.push_list(values, false);
+                self.push("))")
+            }
+            Dialect::Sqlite if all => {
+                self.push("(");
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        self.push(" AND ");
+                    }
+                    self.push("EXISTS (SELECT 1 FROM json_each(");
+                    self.push_field_ref(&filter.field, path, fields, false);
+                    self.push(") WHERE value = ");
+                    self.push_value(value);
+                    self.push(")");
+                }
+                self.push(")")
+            }
+            Dialect::Sqlite => {
+                self.push("EXISTS (SELECT 1 FROM json_each(");
+                self.push_field_ref(&filter.field, path, fields, false);
+                self.push(") WHERE value IN (");
+                self.push_list(values, false);
+                self.push("))")
+            }
+        }
+    }
+
+    /// Pushes a geo operator: PostGIS on PostgreSQL, the spatial functions of
+    /// MySQL 8 and SpatiaLite on SQLite. Radii are in metres on the sphere
+    /// (MySQL) or spheroid; malformed values match nothing.
+    fn push_geo_filter(&mut self, filter: &Filter, fields: Fields) -> &mut Self {
+        let field = filter.field.as_str();
+
+        if let Some((center, meters)) = filter.radius() {
+            match fields.dialect {
+                Dialect::Postgres => {
+                    self.push(format!("ST_DWithin({}::geography, ", field));
+                    self.push_geography(&center);
+                    self.push(", ");
+                }
+                Dialect::MySql => {
+                    self.push_distance(field, &center, fields);
+                    self.push(" <= ");
+                }
+                Dialect::Sqlite => {
+                    self.push(format!("PtDistWithin({}, ", field));
+                    self.push_geometry(center.to_wkt(), fields);
+                    self.push(", ");
+                }
+            }
+            self.push_bind(meters);
+            return self.push(match fields.dialect {
+                Dialect::Postgres => ")",
+                Dialect::MySql => "",
+                Dialect::Sqlite => ") = 1",
+            });
+        }
+
+        let Some(ring) = filter.area() else {
+            return self.push("1=0");
+        };
+        match fields.dialect {
+            Dialect::Postgres => self.push(format!("ST_Within({}::geometry, ", field)),
+            Dialect::MySql | Dialect::Sqlite => self.push(format!("ST_Within({}, ", field)),
+        };
+        self.push_geometry(polygon_wkt(&ring), fields);
+        // SpatiaLite answers -1 for invalid input, which would count as true.
+        self.push(match fields.dialect {
+            Dialect::Sqlite => ") = 1",
+            Dialect::Postgres | Dialect::MySql => ")",
+        })
+    }
+
+    /// Pushes the distance in metres between the geometry column `field` and
+    /// `origin`.
+    pub(crate) fn push_distance(
+        &mut self,
+        field: &str,
+        origin: &GeoPoint,
+        fields: Fields,
+    ) -> &mut Self {
+        match fields.dialect {
+            Dialect::Postgres => {
+                self.push(format!("ST_Distance({}::geography, ", field));
+                self.push_geography(origin);
+            }
+            Dialect::MySql => {
+                self.push(format!("ST_Distance_Sphere({}, ", field));
+                self.push_geometry(origin.to_wkt(), fields);
+            }
+            Dialect::Sqlite => {
+                // The third argument measures on the ellipsoid, in metres.
+                self.push(format!("ST_Distance({}, ", field));
+                self.push_geometry(origin.to_wkt(), fields);
+                self.push(", 1");
+            }
+        }
+        self.push(")")
+    }
+
+    /// Pushes a WGS 84 geometry built from bound well-known text.
+    fn push_geometry(&mut self, wkt: String, fields: Fields) -> &mut Self {
+        self.push(match fields.dialect {
+            Dialect::Postgres | Dialect::MySql => "ST_GeomFromText(",
+            Dialect::Sqlite => "GeomFromText(",
+        });
+        self.push_bind(wkt);
+        self.push(match fields.dialect {
+            // MySQL reads SRID 4326 coordinates latitude first by default.
+            Dialect::MySql => ", 4326, 'axis-order=long-lat')",
+            Dialect::Postgres | Dialect::Sqlite => ", 4326)",
+        })
+    }
+
+    /// Pushes a PostGIS geography for `point`.
+    fn push_geography(&mut self, point: &GeoPoint) -> &mut Self {
+        self.push("ST_GeogFromText(");
+        self.push_bind(point.to_wkt());
+        self.push(")")
+    }
+
+    /// Pushes `field`, or the extraction of `path` from its JSON column.
+    /// `text` extracts the value as text, for `LIKE` patterns.
+    pub(crate) fn push_field_ref(
+        &mut self,
+        field: &str,
+        path: Option<&FieldPath>,
+        fields: Fields,
+        text: bool,
+    ) -> &mut Self {
+        let Some(path) = path else {
+            return self.push(field);
+        };
+
+        match fields.dialect {
+            Dialect::Postgres => {
+                self.push(if text {
+                    "jsonb_extract_path_text("
+                } else {
+                    "jsonb_extract_path("
+                });
+                self.push(path.column());
+                for segment in path.segment_texts() {
+                    self.push(", ");
+                    self.push_bind(segment);
+                }
+                self.push(")")
             }
+            Dialect::MySql => {
+                if text {
+                    self.push("JSON_UNQUOTE(");
+                }
+                self.push("JSON_EXTRACT(");
+                self.push(path.column());
+                self.push(", ");
+                self.push_bind(path.json_path());
+                self.push(")");
+                if text {
+                    self.push(")");
+                }
+                self
+            }
+            // json_extract already yields SQL values, text included.
+            Dialect::Sqlite => {
+                self.push("json_extract(");
+                self.push(path.column());
+                self.push(", ");
+                self.push_bind(path.json_path());
+                self.push(")")
+            }
+        }
+    }
+
+    pub(crate) fn push_filters(&mut self, params: &PaginationParams, fields: Fields) -> &mut Self {
+        for filter in &params.filters {
+            self.push(" AND ");
+            self.push_filter(filter, fields);
         }
         self
     }
 
-    fn push_search(&mut self, params: &PaginationParams) -> &mut Self {
+    pub(crate) fn push_search(&mut self, params: &PaginationParams, fields: Fields) -> &mut Self {
         if let Some(ref search) = params.search {
             if !search.fields.is_empty() {
                 self.push(" AND (");
@@ -151,13 +567,14 @@ where
                         format!("%{}%", search.query)
                     };
 
+                    let path = fields.json_path(field);
                     if search.case_sensitive {
-                        self.push(field);
+                        self.push_field_ref(field, path.as_ref(), fields, true);
                         self.push(" LIKE ");
                         self.push_bind(pattern);
                     } else {
                         self.push("LOWER(");
-                        self.push(field);
+                        self.push_field_ref(field, path.as_ref(), fields, true);
                         self.push(") LIKE LOWER(");
                         self.push_bind(pattern);
                         self.push(")");
@@ -169,31 +586,62 @@ where
         }
         self
     }
-}
 
-fn bind_value<'args, DB: Database>(builder: &mut QueryBuilder<'args, DB>, value: &FilterValue)
-where
-    i64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
-    f64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
-    bool: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
-    String: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
-{
-    match value {
-        FilterValue::String(s) => {
-            builder.push_bind(s.clone());
-        }
-        FilterValue::Int(i) => {
-            builder.push_bind(*i);
-        }
-        FilterValue::Float(f) => {
-            builder.push_bind(*f);
+    fn push_value(&mut self, value: &FilterValue) {
+        match value {
+            FilterValue::String(s) => {
+                self.push_bind(s.clone());
+            }
+            FilterValue::Int(i) => {
+                self.push_bind(*i);
+            }
+            FilterValue::Float(f) => {
+                self.push_bind(*f);
+            }
+            FilterValue::Bool(b) => {
+                self.push_bind(*b);
+            }
+            FilterValue::DateTime(dt) => {
+                self.push_bind(*dt);
+            }
+            FilterValue::Null => {
+                self.push_bind(BindValue::Null);
+            }
+            FilterValue::Point(_) | FilterValue::BoundingBox(_) | FilterValue::Polygon(_) => {
+                self.push_bind(value.to_text());
+            }
+            FilterValue::Array(_) => {}
         }
-        FilterValue::Bool(b) => {
-            builder.push_bind(*b);
+    }
+
+    /// Pushes `value`, converted with `to_jsonb` when `wrap` is set.
+    pub(crate) fn push_operand(&mut self, value: &FilterValue, wrap: bool) {
+        if wrap {
+            self.push("to_jsonb(");
+            self.push_value(value);
+            self.push(")");
+        } else {
+            self.push_value(value);
         }
-        FilterValue::Null => {
-            builder.push("NULL");
+    }
+
+    /// Binds the scalar members of `values`, comma separated.
+    fn push_list(&mut self, values: &[FilterValue], wrap: bool) {
+        let scalars = values.iter().filter(|value| {
+            matches!(
+                value,
+                FilterValue::String(_)
+                    | FilterValue::Int(_)
+                    | FilterValue::Float(_)
+                    | FilterValue::Bool(_)
+                    | FilterValue::DateTime(_)
+            )
+        });
+        for (idx, value) in scalars.enumerate() {
+            if idx > 0 {
+                self.push(", ");
+            }
+            self.push_operand(value, wrap);
         }
-        FilterValue::Array(_) => {}
     }
 }
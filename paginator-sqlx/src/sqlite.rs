@@ -1,13 +1,15 @@
-use crate::common::{validate_field_name, PaginateQuery, PaginatedQuery};
-use crate::query_builder::QueryBuilderExt;
+use crate::common::{PaginateOptions, PaginateQuery, PaginatedQuery, SqlxSource};
+use crate::statement::{self, count_query, data_query, fetch_window_page, finish, Dialect};
+use async_trait::async_trait;
+use futures_util::future::try_join;
 use paginator_rs::{
-    CursorDirection, CursorValue, PaginationParams, PaginatorError, PaginatorResponse,
-    PaginatorResponseMeta,
+    PaginatedSource, PaginationParams, PaginationSpan, PaginatorError, PaginatorResponse,
+    QueryPlan, TotalKind,
 };
 use serde::Serialize;
 use sqlx::query_builder::QueryBuilder;
-use sqlx::sqlite::{SqliteArguments, SqliteRow};
-use sqlx::{query::Query, Executor, FromRow, Sqlite};
+use sqlx::sqlite::{SqliteArguments, SqliteConnection, SqliteRow};
+use sqlx::{query::Query, Acquire, Executor, FromRow, Pool, Sqlite};
 
 impl<'q, T> PaginateQuery<'q, Sqlite, T> for Query<'q, Sqlite, SqliteArguments<'q>>
 where
@@ -18,8 +20,40 @@ where
     }
 }
 
-fn is_cte_query(query: &str) -> bool {
-    query.trim().to_uppercase().starts_with("WITH")
+#[async_trait]
+impl<T> PaginatedSource<T> for SqlxSource<Sqlite, T>
+where
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin + Serialize,
+{
+    async fn fetch_page(
+        &self,
+        params: &PaginationParams,
+    ) -> Result<PaginatorResponse<T>, PaginatorError> {
+        paginate_query_with_options(&self.pool, &self.base_query, params, &self.options).await
+    }
+}
+
+/// Computes the total for `base_query` according to `params.count_strategy`.
+///
+/// Planner estimates are only read from PostgreSQL; other dialects answer
+/// `CountStrategy::Estimated` with an exact count.
+async fn count_total<'e, E>(
+    executor: E,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<(u32, TotalKind), PaginatorError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let mut count_builder: QueryBuilder<Sqlite> =
+        count_query(Dialect::Sqlite, base_query, params, options).build();
+    let (count,) = count_builder
+        .build_query_as::<(i64,)>()
+        .fetch_one(executor)
+        .await
+        .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?;
+    Ok(params.count_strategy.resolve(count as u64))
 }
 
 pub async fn paginate_query<'e, E, T>(
@@ -31,183 +65,234 @@ where
     E: Executor<'e, Database = Sqlite> + Clone,
     T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin + Serialize,
 {
-    let has_filters_or_search = !params.filters.is_empty() || params.search.is_some();
-
-    let count_query_str = if is_cte_query(base_query) {
-        if has_filters_or_search {
-            format!(
-                "{}, _paginator_filtered AS (SELECT * FROM ({}) AS _base WHERE 1=1",
-                base_query.trim_end_matches(';'),
-                base_query
-            )
-        } else {
-            format!("SELECT COUNT(*) FROM ({}) as count_subquery", base_query)
-        }
-    } else {
-        if has_filters_or_search {
-            format!("SELECT COUNT(*) FROM ({}) AS _base WHERE 1=1", base_query)
+    paginate_query_with_options(executor, base_query, params, &PaginateOptions::default()).await
+}
+
+/// Like `paginate_query`, with caching and execution behaviour taken from `options`.
+pub async fn paginate_query_with_options<'e, E, T>(
+    executor: E,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    E: Executor<'e, Database = Sqlite> + Clone,
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin + Serialize,
+{
+    let span = PaginationSpan::new("sqlite", params, options.trace_filter_values);
+    span.run(async {
+        let cached = if params.disable_total_count {
+            None
         } else {
-            format!("SELECT COUNT(*) FROM ({}) as count_subquery", base_query)
+            options.cached_total(base_query, params)
+        };
+
+        if cached.is_none() && options.uses_window_count(params) {
+            let (data, total) = fetch_window_page(
+                Dialect::Sqlite,
+                executor.clone(),
+                base_query,
+                params,
+                options,
+                &span,
+            )
+            .await?;
+            let total = match total {
+                Some(total) => total,
+                None => {
+                    span.phase("count", count_total(executor, base_query, params, options))
+                        .await?
+                }
+            };
+            options.store_total(base_query, params, total);
+            return Ok(finish(data, params, Some(total), &span));
         }
-    };
 
-    let total = if params.disable_total_count {
-        None
-    } else {
-        let count = if has_filters_or_search {
-            let mut count_builder: QueryBuilder<Sqlite> = QueryBuilder::new(&count_query_str);
-            count_builder.push_filters(&params);
-            count_builder.push_search(&params);
+        let needs_count = !params.disable_total_count && cached.is_none();
+        let mut data_builder: QueryBuilder<Sqlite> =
+            data_query(Dialect::Sqlite, base_query, params, options)?.build();
 
-            if is_cte_query(base_query) {
-                count_builder.push(") SELECT COUNT(*) FROM _paginator_filtered");
+        let count_future = async {
+            if needs_count {
+                span.phase(
+                    "count",
+                    count_total(executor.clone(), base_query, params, options),
+                )
+                .await
+                .map(Some)
+            } else {
+                Ok(cached)
             }
+        };
+        let data_future = async {
+            span.phase(
+                "data",
+                data_builder
+                    .build_query_as::<T>()
+                    .fetch_all(executor.clone()),
+            )
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))
+        };
 
-            let count_query = count_builder.build_query_as::<(i64,)>();
-            let (total,) = count_query
-                .fetch_one(executor.clone())
-                .await
-                .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?;
-            total
+        let (total, data) = if options.concurrent {
+            try_join(count_future, data_future).await?
         } else {
-            let (total,): (i64,) = sqlx::query_as(&count_query_str)
-                .fetch_one(executor.clone())
-                .await
-                .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?;
-            total
+            let total = count_future.await?;
+            (total, data_future.await?)
         };
-        Some(count)
-    };
 
-    let data_query_str = if is_cte_query(base_query) {
-        if has_filters_or_search {
-            format!(
-                "{}, _paginator_filtered AS (SELECT * FROM ({}) AS _base WHERE 1=1",
-                base_query.trim_end_matches(';'),
-                base_query
-            )
-        } else {
-            base_query.to_string()
+        if let (true, Some(total)) = (needs_count, total) {
+            options.store_total(base_query, params, total);
         }
-    } else {
-        if has_filters_or_search {
-            format!("SELECT * FROM ({}) AS _base WHERE 1=1", base_query)
-        } else {
-            base_query.to_string()
-        }
-    };
-
-    let mut data_builder: QueryBuilder<Sqlite> = QueryBuilder::new(&data_query_str);
 
-    if has_filters_or_search {
-        data_builder.push_filters(&params);
-        data_builder.push_search(&params);
+        Ok(finish(data, params, total, &span))
+    })
+    .await
+}
 
-        if is_cte_query(base_query) {
-            data_builder.push(") SELECT * FROM _paginator_filtered");
-        }
-    }
+/// Like `paginate_query`, for a single connection or an open transaction
+/// (`&mut SqliteConnection`, `&mut Transaction<'_, Sqlite>` or `&Pool<Sqlite>`).
+pub async fn paginate_query_on<'a, A, T>(
+    conn: A,
+    base_query: &str,
+    params: &PaginationParams,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    A: Acquire<'a, Database = Sqlite>,
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin + Serialize,
+{
+    paginate_query_on_with_options(conn, base_query, params, &PaginateOptions::default()).await
+}
 
-    if let Some(ref cursor) = params.cursor {
-        // Validate cursor field name to prevent SQL injection
-        validate_field_name(&cursor.field)?;
-
-        let operator = match cursor.direction {
-            CursorDirection::After => match params.sort_direction.as_ref() {
-                Some(paginator_rs::SortDirection::Desc) => "<",
-                _ => ">",
-            },
-            CursorDirection::Before => match params.sort_direction.as_ref() {
-                Some(paginator_rs::SortDirection::Desc) => ">",
-                _ => "<",
-            },
-        };
+/// Like `paginate_query_with_options`, for a single connection or an open
+/// transaction. The count and data statements run one after the other on that
+/// connection and see the transaction's uncommitted writes; `concurrent` is
+/// ignored.
+pub async fn paginate_query_on_with_options<'a, A, T>(
+    conn: A,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    A: Acquire<'a, Database = Sqlite>,
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin + Serialize,
+{
+    let span = PaginationSpan::new("sqlite", params, options.trace_filter_values);
+    span.run(async {
+        let mut conn = conn
+            .acquire()
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Failed to acquire connection: {}", e)))?;
+        paginate_on_connection(&mut conn, base_query, params, options, &span).await
+    })
+    .await
+}
 
-        if !has_filters_or_search {
-            data_builder.push(" WHERE ");
-        } else {
-            data_builder.push(" AND ");
-        }
+/// Like `paginate_query_with_options`, but runs the count and data statements
+/// inside one read transaction taken from `pool`. SQLite keeps the snapshot
+/// of the first read for the rest of the transaction, so `total` and `data`
+/// agree even under concurrent writes. The statements run one after the
+/// other; `concurrent` is ignored.
+pub async fn paginate_query_snapshot<T>(
+    pool: &Pool<Sqlite>,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin + Serialize,
+{
+    let span = PaginationSpan::new("sqlite", params, options.trace_filter_values);
+    span.run(async {
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Failed to begin snapshot: {}", e)))?;
+        let response = paginate_on_connection(&mut tx, base_query, params, options, &span).await?;
+        tx.commit()
+            .await
+            .map_err(|e| PaginatorError::Custom(format!("Failed to end snapshot: {}", e)))?;
+        Ok(response)
+    })
+    .await
+}
 
-        data_builder.push(&cursor.field);
-        data_builder.push(" ");
-        data_builder.push(operator);
-        data_builder.push(" ");
+/// Returns the count and data statements `paginate_query` would run, with
+/// their bind values in order, without executing anything.
+pub fn plan(base_query: &str, params: &PaginationParams) -> Result<QueryPlan, PaginatorError> {
+    plan_with_options(base_query, params, &PaginateOptions::default())
+}
 
-        match &cursor.value {
-            CursorValue::String(s) => {
-                data_builder.push_bind(s.clone());
-            }
-            CursorValue::Int(i) => {
-                data_builder.push_bind(*i);
-            }
-            CursorValue::Float(f) => {
-                data_builder.push_bind(*f);
-            }
-            CursorValue::Uuid(u) => {
-                // SQLite stores UUIDs as strings
-                data_builder.push_bind(u.clone());
-            }
-        }
-    }
+/// Like `plan`, for `paginate_query_with_options`. Count cache lookups are
+/// not simulated; the plan shows the statements of a cache miss.
+pub fn plan_with_options(
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<QueryPlan, PaginatorError> {
+    statement::plan::<Sqlite>(Dialect::Sqlite, base_query, params, options)
+}
 
-    if let Some(ref sort_field) = params.sort_by {
-        // Validate sort field name to prevent SQL injection
-        validate_field_name(sort_field)?;
+/// Runs the count and data statements one after the other on `conn`.
+async fn paginate_on_connection<T>(
+    conn: &mut SqliteConnection,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+    span: &PaginationSpan,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin + Serialize,
+{
+    let cached = if params.disable_total_count {
+        None
+    } else {
+        options.cached_total(base_query, params)
+    };
 
-        data_builder.push(" ORDER BY ");
-        data_builder.push(sort_field);
-        match params.sort_direction.as_ref() {
-            Some(paginator_rs::SortDirection::Desc) => data_builder.push(" DESC"),
-            _ => data_builder.push(" ASC"),
+    if cached.is_none() && options.uses_window_count(params) {
+        let (data, total) = fetch_window_page(
+            Dialect::Sqlite,
+            &mut *conn,
+            base_query,
+            params,
+            options,
+            span,
+        )
+        .await?;
+        let total = match total {
+            Some(total) => total,
+            None => {
+                span.phase("count", count_total(conn, base_query, params, options))
+                    .await?
+            }
         };
+        options.store_total(base_query, params, total);
+        return Ok(finish(data, params, Some(total), span));
     }
 
-    if params.cursor.is_some() {
-        data_builder.push(" LIMIT ");
-        data_builder.push_bind((params.limit() + 1) as i64);
-    } else if params.disable_total_count {
-        // Fetch one extra row to detect if there's a next page
-        data_builder.push(" LIMIT ");
-        data_builder.push_bind((params.limit() + 1) as i64);
-        data_builder.push(" OFFSET ");
-        data_builder.push_bind(params.offset() as i64);
+    let needs_count = !params.disable_total_count && cached.is_none();
+    let total = if needs_count {
+        let total = span
+            .phase(
+                "count",
+                count_total(&mut *conn, base_query, params, options),
+            )
+            .await?;
+        options.store_total(base_query, params, total);
+        Some(total)
     } else {
-        data_builder.push(" LIMIT ");
-        data_builder.push_bind(params.limit() as i64);
-        data_builder.push(" OFFSET ");
-        data_builder.push_bind(params.offset() as i64);
-    }
+        cached
+    };
 
-    let data_query = data_builder.build_query_as::<T>();
-    let mut data = data_query
-        .fetch_all(executor)
+    let mut data_builder: QueryBuilder<Sqlite> =
+        data_query(Dialect::Sqlite, base_query, params, options)?.build();
+    let data = span
+        .phase("data", data_builder.build_query_as::<T>().fetch_all(conn))
         .await
         .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?;
 
-    let meta = if params.cursor.is_some() {
-        let has_next = data.len() > params.per_page as usize;
-        if has_next {
-            data.truncate(params.per_page as usize);
-        }
-        PaginatorResponseMeta::new_with_cursors(
-            params.page,
-            params.per_page,
-            total.map(|t| t as u32),
-            has_next,
-            None,
-            None,
-        )
-    } else if let Some(count) = total {
-        PaginatorResponseMeta::new(params.page, params.per_page, count as u32)
-    } else {
-        // disable_total_count is true - we fetched one extra row to detect next page
-        let has_next = data.len() > params.per_page as usize;
-        if has_next {
-            data.truncate(params.per_page as usize);
-        }
-        PaginatorResponseMeta::new_without_total(params.page, params.per_page, has_next)
-    };
-
-    Ok(PaginatorResponse { data, meta })
+    Ok(finish(data, params, total, span))
 }
@@ -0,0 +1,628 @@
+use crate::common::{validate_field_name, PaginateOptions};
+pub(crate) use crate::query_builder::Dialect;
+use crate::query_builder::{Fields, Part, Statement};
+use paginator_rs::chrono::{DateTime, Utc};
+use paginator_rs::{
+    BindValue, CountStrategy, CursorValue, PaginationParams, PaginationSpan, PaginatorError,
+    PaginatorResponse, PlannedStatement, QueryPlan, SortDirection, TotalKind, DISTANCE_COLUMN,
+};
+use sqlx::error::BoxDynError;
+use sqlx::query_builder::QueryBuilder;
+use sqlx::{Arguments, ColumnIndex, Database, Executor, FromRow, IntoArguments, Row};
+
+impl Statement {
+    pub(crate) fn new(sql: impl Into<String>) -> Self {
+        Self {
+            parts: vec![Part::Sql(sql.into())],
+        }
+    }
+
+    fn binds(&self) -> Vec<BindValue> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Bind(value) => Some(value.clone()),
+                Part::Sql(_) => None,
+            })
+            .collect()
+    }
+
+    /// Turns the statement into a sqlx `QueryBuilder` ready to execute.
+    pub(crate) fn build<'args, DB>(self) -> QueryBuilder<'args, DB>
+    where
+        DB: Database,
+        i64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        f64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        bool: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        String: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        DateTime<Utc>: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+    {
+        let mut builder = QueryBuilder::new("");
+        self.append_to(&mut builder);
+        builder
+    }
+
+    /// Renders the statement into `sql` and returns its arguments, for use
+    /// with `sqlx::query_with_result` where `DB` is generic.
+    fn render<'q, DB>(self, sql: &mut String) -> Result<DB::Arguments<'q>, BoxDynError>
+    where
+        DB: Database,
+        i64: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+        f64: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+        bool: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+        String: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+        DateTime<Utc>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    {
+        let mut arguments = DB::Arguments::default();
+        for part in self.parts {
+            match part {
+                Part::Sql(text) => {
+                    sql.push_str(&text);
+                    continue;
+                }
+                Part::Bind(BindValue::Null) => {
+                    sql.push_str("NULL");
+                    continue;
+                }
+                Part::Bind(BindValue::Bool(b)) => arguments.add(b)?,
+                Part::Bind(BindValue::Int(i)) => arguments.add(i)?,
+                Part::Bind(BindValue::Float(f)) => arguments.add(f)?,
+                Part::Bind(BindValue::String(s)) => arguments.add(s)?,
+                Part::Bind(BindValue::DateTime(dt)) => arguments.add(dt)?,
+            }
+            arguments.format_placeholder(sql)?;
+        }
+        Ok(arguments)
+    }
+
+    /// Renders the statement with `DB`'s placeholders, exactly as `build`
+    /// would send it.
+    pub(crate) fn plan<DB>(self) -> PlannedStatement
+    where
+        DB: Database,
+        for<'args> i64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        for<'args> f64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        for<'args> bool: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        for<'args> String: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+        for<'args> DateTime<Utc>: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+    {
+        let binds = self.binds();
+        let builder: QueryBuilder<DB> = self.build();
+        PlannedStatement {
+            sql: builder.sql().to_string(),
+            binds,
+        }
+    }
+}
+
+/// Builds the statement that produces the total, or `None` when no separate
+/// count runs. Estimated counts on PostgreSQL read the planner's row estimate
+/// from `EXPLAIN` and fall back to `count_query` when it yields nothing.
+pub(crate) fn total_query(
+    dialect: Dialect,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Option<Statement> {
+    if params.disable_total_count || options.uses_window_count(params) {
+        None
+    } else if dialect == Dialect::Postgres && params.count_strategy == CountStrategy::Estimated {
+        // The estimate only needs the filtered rows, not their distances.
+        let params = PaginationParams {
+            distance_sort: None,
+            ..params.clone()
+        };
+        Some(filtered_query(
+            dialect, "EXPLAIN ", base_query, &params, options,
+        ))
+    } else {
+        Some(count_query(dialect, base_query, params, options))
+    }
+}
+
+/// Builds the statements `paginate_query_with_options` runs on a count cache
+/// miss, without executing them.
+pub(crate) fn plan<DB>(
+    dialect: Dialect,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<QueryPlan, PaginatorError>
+where
+    DB: Database,
+    for<'args> i64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+    for<'args> f64: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+    for<'args> bool: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+    for<'args> String: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+    for<'args> DateTime<Utc>: sqlx::Encode<'args, DB> + sqlx::Type<DB>,
+{
+    let data = if options.uses_window_count(params) {
+        window_count_query(dialect, base_query, params, options)?
+    } else {
+        data_query(dialect, base_query, params, options)?
+    };
+
+    Ok(QueryPlan {
+        count: total_query(dialect, base_query, params, options).map(Statement::plan::<DB>),
+        data: data.plan::<DB>(),
+    })
+}
+
+pub(crate) fn is_cte_query(query: &str) -> bool {
+    query.trim().to_uppercase().starts_with("WITH")
+}
+
+fn has_filters_or_search(params: &PaginationParams) -> bool {
+    !params.filters.is_empty() || params.search.is_some()
+}
+
+/// Whether the base query's rows must be narrowed or extended before paging.
+fn needs_filtering(params: &PaginationParams) -> bool {
+    has_filters_or_search(params) || params.distance_sort.is_some()
+}
+
+/// `base_query` without a trailing `;`, so it can be nested.
+fn nested(base_query: &str) -> &str {
+    base_query.trim_end().trim_end_matches(';').trim_end()
+}
+
+/// Starts a statement with `prefix`. A `WITH` base query that needs filtering
+/// is filtered once in a leading `_paginator_filtered` CTE, which
+/// `push_filtered_query` then selects from.
+fn begin(prefix: &str, base_query: &str, params: &PaginationParams, fields: Fields) -> Statement {
+    let mut builder = Statement::new(prefix);
+    if is_cte_query(base_query) && needs_filtering(params) {
+        builder.push("WITH _paginator_filtered AS (");
+        push_filtered_rows(&mut builder, base_query, params, fields, true);
+        builder.push(") ");
+    }
+    builder
+}
+
+/// Builds `prefix` followed by a statement selecting the filtered rows of
+/// `base_query`, without cursor conditions, ordering or limits.
+pub(crate) fn filtered_query(
+    dialect: Dialect,
+    prefix: &str,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Statement {
+    let fields = Fields::new(dialect, options);
+    let mut builder = begin(prefix, base_query, params, fields);
+    push_filtered_query(&mut builder, base_query, params, fields);
+    builder
+}
+
+/// Appends a statement selecting the filtered rows of `base_query` to
+/// `builder`, with their distance as `DISTANCE_COLUMN` when sorting by
+/// distance. The builder must have been started with `begin`.
+fn push_filtered_query(
+    builder: &mut Statement,
+    base_query: &str,
+    params: &PaginationParams,
+    fields: Fields,
+) {
+    if !needs_filtering(params) {
+        builder.push(nested(base_query));
+    } else if is_cte_query(base_query) {
+        builder.push("SELECT * FROM _paginator_filtered");
+    } else {
+        push_filtered_rows(builder, base_query, params, fields, true);
+    }
+}
+
+fn push_filtered_rows(
+    builder: &mut Statement,
+    base_query: &str,
+    params: &PaginationParams,
+    fields: Fields,
+    with_distance: bool,
+) {
+    builder.push("SELECT *");
+    if let (Some(sort), true) = (&params.distance_sort, with_distance) {
+        builder.push(", ");
+        builder.push_distance(&sort.field, &sort.origin, fields);
+        builder.push(format!(" AS {}", DISTANCE_COLUMN));
+    }
+    builder.push(format!(" FROM ({}) AS _base WHERE 1=1", nested(base_query)));
+    builder.push_filters(params, fields);
+    builder.push_search(params, fields);
+}
+
+/// Builds the `COUNT(*)` statement for `base_query`, honouring the row cap of
+/// `CountStrategy::Capped`.
+pub(crate) fn count_query(
+    dialect: Dialect,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Statement {
+    let fields = Fields::new(dialect, options);
+    let count_limit = params.count_strategy.count_limit();
+
+    if !has_filters_or_search(params) {
+        let mut builder = match count_limit {
+            Some(_) => Statement::new(format!(
+                "SELECT COUNT(*) FROM (SELECT 1 FROM ({}) AS count_subquery",
+                nested(base_query)
+            )),
+            None => Statement::new(format!(
+                "SELECT COUNT(*) FROM ({}) as count_subquery",
+                nested(base_query)
+            )),
+        };
+        push_count_cap(&mut builder, count_limit);
+        return builder;
+    }
+
+    let mut builder = if is_cte_query(base_query) {
+        let mut builder = Statement::new("WITH _paginator_filtered AS (");
+        push_filtered_rows(&mut builder, base_query, params, fields, false);
+        match count_limit {
+            Some(_) => builder.push(") SELECT COUNT(*) FROM (SELECT 1 FROM _paginator_filtered"),
+            None => builder.push(") SELECT COUNT(*) FROM _paginator_filtered"),
+        };
+        builder
+    } else {
+        let mut builder = match count_limit {
+            Some(_) => Statement::new(format!(
+                "SELECT COUNT(*) FROM (SELECT 1 FROM ({}) AS _base WHERE 1=1",
+                nested(base_query)
+            )),
+            None => Statement::new(format!(
+                "SELECT COUNT(*) FROM ({}) AS _base WHERE 1=1",
+                nested(base_query)
+            )),
+        };
+        builder.push_filters(params, fields);
+        builder.push_search(params, fields);
+        builder
+    };
+
+    push_count_cap(&mut builder, count_limit);
+    builder
+}
+
+fn push_count_cap(builder: &mut Statement, count_limit: Option<u64>) {
+    if let Some(limit) = count_limit {
+        builder.push(" LIMIT ");
+        builder.push_bind(limit as i64);
+        builder.push(") AS _capped");
+    }
+}
+
+/// Builds the page query: filtered rows plus cursor condition, ordering and
+/// `LIMIT`/`OFFSET`.
+pub(crate) fn data_query(
+    dialect: Dialect,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<Statement, PaginatorError> {
+    let fields = Fields::new(dialect, options);
+    validate_distance_sort(params)?;
+
+    // Rows joined back on the key could not be ordered by an extracted value
+    // consistently with the key page, so JSON sorts page directly. Distance
+    // sorts compute the distance of every filtered row either way, which
+    // leaves a deferred join nothing to save.
+    let sorts_json = params
+        .sort_by
+        .as_deref()
+        .is_some_and(|field| fields.json_path(field).is_some());
+    if let (Some(key), None, false, None) = (
+        options.deferred_join_key.as_deref(),
+        &params.cursor,
+        sorts_json,
+        &params.distance_sort,
+    ) {
+        return deferred_join_query(base_query, key, params, options, fields);
+    }
+
+    let mut builder = begin("", base_query, params, fields);
+
+    if let Some(ref cursor) = params.cursor {
+        let path = fields.json_path(&cursor.field);
+        let wrap = fields.wraps_operands(path.as_ref());
+        if path.is_none() {
+            // Validate cursor field name to prevent SQL injection
+            validate_field_name(&cursor.field)?;
+        }
+
+        // Wrapping keeps the condition independent of the base query's own
+        // WHERE clause or CTEs.
+        builder.push(format!(
+            "SELECT {} FROM (",
+            projection(params, options, "")?
+        ));
+        push_filtered_query(&mut builder, base_query, params, fields);
+        builder.push(") AS _cursor_base WHERE ");
+        let push_comparison = |builder: &mut Statement, operator: &str| {
+            builder.push_field_ref(&cursor.field, path.as_ref(), fields, false);
+            builder.push(format!(" {} ", operator));
+            push_cursor_value(builder, &cursor.value, dialect, wrap);
+        };
+        match (params.tie_breaker_field(), &cursor.tie_value) {
+            (Some(key), Some(tie_value)) => {
+                // Rows tying on the cursor field continue after the cursor
+                // row's key.
+                validate_field_name(key)?;
+                builder.push("(");
+                push_comparison(&mut builder, params.cursor_operator());
+                builder.push(" OR (");
+                push_comparison(&mut builder, "=");
+                builder.push(format!(" AND {} {} ", key, params.cursor_operator()));
+                push_cursor_value(&mut builder, tie_value, dialect, false);
+                builder.push("))");
+            }
+            _ => push_comparison(&mut builder, params.cursor_operator()),
+        }
+    } else if options.projects(params) {
+        builder.push(format!(
+            "SELECT {} FROM (",
+            projection(params, options, "")?
+        ));
+        push_filtered_query(&mut builder, base_query, params, fields);
+        builder.push(") AS _projected");
+    } else {
+        push_filtered_query(&mut builder, base_query, params, fields);
+    }
+
+    push_order_and_limit(&mut builder, params, fields)?;
+    Ok(builder)
+}
+
+fn validate_distance_sort(params: &PaginationParams) -> Result<(), PaginatorError> {
+    match params.distance_sort {
+        Some(ref sort) => validate_field_name(&sort.field),
+        None => Ok(()),
+    }
+}
+
+/// Column list of the outermost `SELECT`: the requested `fields` when
+/// `PaginateOptions::project_fields` is on, `*` otherwise. Each column is
+/// prefixed with `qualifier`.
+fn projection(
+    params: &PaginationParams,
+    options: &PaginateOptions,
+    qualifier: &str,
+) -> Result<String, PaginatorError> {
+    match params.fields.as_ref().filter(|_| options.project_fields) {
+        Some(fields) => {
+            for field in fields {
+                validate_field_name(field)?;
+            }
+            Ok(fields
+                .iter()
+                .map(|field| format!("{}{}", qualifier, field))
+                .collect::<Vec<_>>()
+                .join(", "))
+        }
+        None => Ok(format!("{}*", qualifier)),
+    }
+}
+
+/// Builds an offset page query that first pages over `key` alone and then
+/// joins back to fetch the full rows of that page:
+///
+/// ```sql
+/// SELECT _paginator_rows.* FROM (<filtered>) AS _paginator_rows
+/// INNER JOIN (SELECT key FROM (<filtered>) AS _paginator_keys_src
+///             ORDER BY sort, key LIMIT ? OFFSET ?) AS _paginator_keys
+///   ON _paginator_rows.key = _paginator_keys.key
+/// ORDER BY _paginator_rows.sort, _paginator_rows.key
+/// ```
+///
+/// `key` breaks ties in the sort so the inner and outer ordering agree.
+fn deferred_join_query(
+    base_query: &str,
+    key: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+    fields: Fields,
+) -> Result<Statement, PaginatorError> {
+    validate_field_name(key)?;
+    if let Some(ref sort_field) = params.sort_by {
+        validate_field_name(sort_field)?;
+    }
+
+    let direction = match params.sort_direction.as_ref() {
+        Some(SortDirection::Desc) => "DESC",
+        _ => "ASC",
+    };
+    let order_by = |qualifier: &str| {
+        let mut columns = Vec::new();
+        if let Some(ref sort_field) = params.sort_by {
+            columns.push(format!("{}{} {}", qualifier, sort_field, direction));
+        }
+        if params.sort_by.as_deref() != Some(key) {
+            columns.push(format!("{}{} {}", qualifier, key, direction));
+        }
+        format!(" ORDER BY {}", columns.join(", "))
+    };
+
+    let columns = projection(params, options, "_paginator_rows.")?;
+    let mut builder = begin("", base_query, params, fields);
+    builder.push(format!("SELECT {} FROM (", columns));
+    push_filtered_query(&mut builder, base_query, params, fields);
+    builder.push(") AS _paginator_rows INNER JOIN (SELECT ");
+    builder.push(key);
+    builder.push(" FROM (");
+    push_filtered_query(&mut builder, base_query, params, fields);
+    builder.push(") AS _paginator_keys_src");
+    builder.push(order_by(""));
+    builder.push(" LIMIT ");
+    builder.push_bind(params.fetch_limit() as i64);
+    builder.push(" OFFSET ");
+    builder.push_bind(params.offset() as i64);
+    builder.push(format!(
+        ") AS _paginator_keys ON _paginator_rows.{key} = _paginator_keys.{key}",
+        key = key
+    ));
+    builder.push(order_by("_paginator_rows."));
+
+    Ok(builder)
+}
+
+/// Builds a page query that also returns the filtered total in a
+/// `_paginator_total` column computed with `COUNT(*) OVER ()`.
+///
+/// Only valid without a cursor, where the window covers the whole filtered set.
+pub(crate) fn window_count_query(
+    dialect: Dialect,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<Statement, PaginatorError> {
+    validate_distance_sort(params)?;
+    let fields = Fields::new(dialect, options);
+    let columns = projection(params, options, "")?;
+    let mut builder = begin("", base_query, params, fields);
+    builder.push(format!(
+        "SELECT {}, COUNT(*) OVER () AS {} FROM (",
+        columns, WINDOW_TOTAL_COLUMN
+    ));
+    push_filtered_query(&mut builder, base_query, params, fields);
+    builder.push(") AS _windowed");
+
+    push_order_and_limit(&mut builder, params, fields)?;
+    Ok(builder)
+}
+
+/// Column carrying the window-function total in `window_count_query` rows.
+pub(crate) const WINDOW_TOTAL_COLUMN: &str = "_paginator_total";
+
+/// Binds a cursor value, converted to `jsonb` when compared with an
+/// extracted JSON value.
+fn push_cursor_value(builder: &mut Statement, value: &CursorValue, dialect: Dialect, wrap: bool) {
+    if wrap {
+        builder.push("to_jsonb(");
+    }
+    match value {
+        CursorValue::String(s) => {
+            builder.push_bind(s.clone());
+        }
+        CursorValue::Int(i) => {
+            builder.push_bind(*i);
+        }
+        CursorValue::Float(f) => {
+            builder.push_bind(*f);
+        }
+        CursorValue::DateTime(dt) => {
+            builder.push_bind(*dt);
+        }
+        CursorValue::Uuid(u) => {
+            builder.push_bind(u.clone());
+            // MySQL and SQLite store UUIDs as strings, and so does JSON
+            if dialect == Dialect::Postgres && !wrap {
+                builder.push("::uuid");
+            }
+        }
+    }
+    if wrap {
+        builder.push(")");
+    }
+}
+
+fn push_order_and_limit(
+    builder: &mut Statement,
+    params: &PaginationParams,
+    fields: Fields,
+) -> Result<(), PaginatorError> {
+    if let Some(sort_field) = params.order_field() {
+        let path = fields.json_path(sort_field);
+        if path.is_none() {
+            // Validate sort field name to prevent SQL injection
+            validate_field_name(sort_field)?;
+        }
+
+        let direction = match params.order_direction() {
+            SortDirection::Desc => " DESC",
+            SortDirection::Asc => " ASC",
+        };
+        builder.push(" ORDER BY ");
+        builder.push_field_ref(sort_field, path.as_ref(), fields, false);
+        builder.push(direction);
+        if let Some(key) = params.tie_breaker_field() {
+            validate_field_name(key)?;
+            builder.push(format!(", {}{}", key, direction));
+        }
+    }
+
+    builder.push(" LIMIT ");
+    builder.push_bind(params.fetch_limit() as i64);
+    if params.cursor.is_none() {
+        builder.push(" OFFSET ");
+        builder.push_bind(params.offset() as i64);
+    }
+
+    Ok(())
+}
+
+/// Fetches the page with `COUNT(*) OVER ()` attached to every row. The total
+/// is `None` when a page past the end came back empty and a separate count is
+/// needed.
+pub(crate) async fn fetch_window_page<'e, DB, E, T>(
+    dialect: Dialect,
+    executor: E,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+    span: &PaginationSpan,
+) -> Result<(Vec<T>, Option<(u32, TotalKind)>), PaginatorError>
+where
+    DB: Database,
+    E: Executor<'e, Database = DB>,
+    T: for<'r> FromRow<'r, DB::Row> + Send + Unpin,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    for<'q> i64: sqlx::Encode<'q, DB> + sqlx::Decode<'q, DB> + sqlx::Type<DB>,
+    for<'q> f64: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    for<'q> bool: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    for<'q> String: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    for<'q> DateTime<Utc>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    for<'c> &'c str: ColumnIndex<DB::Row>,
+{
+    let mut sql = String::new();
+    let arguments = window_count_query(dialect, base_query, params, options)?
+        .render::<DB>(&mut sql)
+        .map_err(|e| PaginatorError::Custom(format!("Failed to bind value: {}", e)))?;
+    let rows = span
+        .phase(
+            "data",
+            sqlx::query_with(&sql, arguments).fetch_all(executor),
+        )
+        .await
+        .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?;
+
+    let window_total = rows
+        .first()
+        .map(|row| row.try_get::<i64, _>(WINDOW_TOTAL_COLUMN))
+        .transpose()
+        .map_err(|e| PaginatorError::Custom(format!("Failed to read window count: {}", e)))?;
+    let data = rows
+        .iter()
+        .map(T::from_row)
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|e| PaginatorError::Custom(format!("Failed to decode row: {}", e)))?;
+
+    // An empty page carries no window total; only page one proves the set is empty.
+    let total = match window_total {
+        Some(count) => Some(params.count_strategy.resolve(count as u64)),
+        None if params.offset() == 0 => Some(params.count_strategy.resolve(0)),
+        None => None,
+    };
+    Ok((data, total))
+}
+
+/// Assembles the response and records its outcome on `span`.
+pub(crate) fn finish<T>(
+    data: Vec<T>,
+    params: &PaginationParams,
+    total: Option<(u32, TotalKind)>,
+    span: &PaginationSpan,
+) -> PaginatorResponse<T> {
+    let response = PaginatorResponse::from_rows(data, params, total);
+    span.record_result(response.data.len(), response.meta.total);
+    response
+}
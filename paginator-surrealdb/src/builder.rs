@@ -1,8 +1,13 @@
-use crate::query::paginate_query;
-use paginator_rs::{PaginationParams, PaginatorError, PaginatorResponse};
+use crate::query::{paginate_query, paginate_query_with_options, plan, PaginateOptions};
+use crate::validate_field_name;
+use paginator_rs::{
+    PaginatedSource, PaginationParams, PaginatorError, PaginatorResponse, QueryPlan,
+    DISTANCE_COLUMN,
+};
 use serde::{de::DeserializeOwned, Serialize};
 use surrealdb::{Connection, Surreal};
 
+#[derive(Clone, Debug)]
 pub struct QueryBuilder {
     select: String,
     from: Option<String>,
@@ -45,12 +50,42 @@ impl QueryBuilder {
     }
 
     pub fn build_query(&self) -> Result<String, PaginatorError> {
+        self.build_query_selecting(&self.select)
+    }
+
+    /// Builds the query selecting `params.fields` and the ordering field in
+    /// place of `*`. Explicit `select` lists are left alone.
+    fn build_projected_query(&self, params: &PaginationParams) -> Result<String, PaginatorError> {
+        let fields = match params.fields {
+            Some(ref fields) if self.select == "*" => fields,
+            _ => return self.build_query(),
+        };
+
+        // The distance is computed from the geometry field after projection.
+        let distance = params.distance_sort.as_ref();
+        let mut selection: Vec<&str> = Vec::with_capacity(fields.len() + 2);
+        for field in fields
+            .iter()
+            .map(String::as_str)
+            .chain(params.order_field())
+            .chain(distance.map(|sort| sort.field.as_str()))
+            .filter(|field| distance.is_none() || *field != DISTANCE_COLUMN)
+        {
+            validate_field_name(field)?;
+            if !selection.contains(&field) {
+                selection.push(field);
+            }
+        }
+        self.build_query_selecting(&selection.join(", "))
+    }
+
+    fn build_query_selecting(&self, select: &str) -> Result<String, PaginatorError> {
         let from = self
             .from
             .as_ref()
             .ok_or_else(|| PaginatorError::Custom("FROM clause is required".to_string()))?;
 
-        let mut query = format!("SELECT {} FROM {}", self.select, from);
+        let mut query = format!("SELECT {} FROM {}", select, from);
 
         if !self.conditions.is_empty() {
             query.push_str(&format!(" WHERE {}", self.conditions.join(" AND ")));
@@ -71,4 +106,68 @@ impl QueryBuilder {
         let query = self.build_query()?;
         paginate_query(db, &query, params).await
     }
+
+    pub async fn paginate_with_options<T, C>(
+        self,
+        db: &Surreal<C>,
+        params: &PaginationParams,
+        options: &PaginateOptions,
+    ) -> Result<PaginatorResponse<T>, PaginatorError>
+    where
+        T: DeserializeOwned + Serialize,
+        C: Connection,
+    {
+        let query = if options.project_fields {
+            self.build_projected_query(params)?
+        } else {
+            self.build_query()?
+        };
+        paginate_query_with_options(db, &query, params, options).await
+    }
+
+    /// Returns the statements `paginate` would run, without executing them.
+    pub fn plan(&self, params: &PaginationParams) -> Result<QueryPlan, PaginatorError> {
+        plan(&self.build_query()?, params)
+    }
+}
+
+/// A `QueryBuilder` on a connection as a `PaginatedSource`, paged with
+/// `QueryBuilder::paginate_with_options`.
+#[derive(Clone, Debug)]
+pub struct SurrealSource<C: Connection> {
+    db: Surreal<C>,
+    query: QueryBuilder,
+    options: PaginateOptions,
+}
+
+impl<C: Connection> SurrealSource<C> {
+    pub fn new(db: Surreal<C>, query: QueryBuilder) -> Self {
+        Self {
+            db,
+            query,
+            options: PaginateOptions::default(),
+        }
+    }
+
+    pub fn options(mut self, options: PaginateOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, C> PaginatedSource<T> for SurrealSource<C>
+where
+    T: DeserializeOwned + Serialize + Send,
+    C: Connection,
+{
+    async fn fetch_page(
+        &self,
+        params: &PaginationParams,
+    ) -> Result<PaginatorResponse<T>, PaginatorError> {
+        self.query
+            .clone()
+            .paginate_with_options(&self.db, params, &self.options)
+            .await
+    }
 }
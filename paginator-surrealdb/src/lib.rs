@@ -2,30 +2,46 @@ mod builder;
 mod query;
 mod table;
 
-pub use builder::QueryBuilder;
-pub use query::{paginate_query, CountResult};
+pub use builder::{QueryBuilder, SurrealSource};
+pub use query::{paginate_query, paginate_query_with_options, plan, CountResult, PaginateOptions};
 pub use table::{paginate_by_id_range, paginate_table};
 
-use paginator_rs::PaginatorError;
+use paginator_rs::{ErrorCode, FieldPath, PaginatorError, ValidationError};
 
 /// Validates that a field name is safe for use in SurrealQL queries.
-/// Only allows alphanumeric characters, underscores, and dots (for qualified names).
+/// Only allows alphanumeric characters, underscores, dots (for nested fields)
+/// and array indexes such as `items[0].sku`.
 /// Returns an error if the field name contains potentially dangerous characters.
 pub fn validate_field_name(field: &str) -> Result<(), PaginatorError> {
     if field.is_empty() {
-        return Err(PaginatorError::Custom(
-            "Field name cannot be empty".to_string(),
-        ));
+        return Err(
+            ValidationError::new(ErrorCode::InvalidField, "Field name cannot be empty").into(),
+        );
     }
 
     for c in field.chars() {
-        if !c.is_alphanumeric() && c != '_' && c != '.' {
-            return Err(PaginatorError::Custom(format!(
-                "Invalid field name '{}': contains unsafe character '{}'",
-                field, c
-            )));
+        if !c.is_alphanumeric() && !matches!(c, '_' | '.' | '[' | ']') {
+            return Err(ValidationError::new(
+                ErrorCode::InvalidField,
+                format!(
+                    "Invalid field name '{}': contains unsafe character '{}'",
+                    field, c
+                ),
+            )
+            .into());
         }
     }
 
+    if field.contains(['[', ']']) && FieldPath::parse(field).is_err() {
+        return Err(ValidationError::new(
+            ErrorCode::InvalidField,
+            format!(
+                "Invalid field name '{}': array indexes must be written as [n]",
+                field
+            ),
+        )
+        .into());
+    }
+
     Ok(())
 }
@@ -1,163 +1,347 @@
 use crate::validate_field_name;
 use paginator_rs::{
-    CursorDirection, CursorValue, PaginationParams, PaginatorError, PaginatorResponse,
-    PaginatorResponseMeta,
+    count_fingerprint, escape_surrealql, CountCache, CursorValue, FilterValue, PaginationParams,
+    PaginationSpan, PaginatorError, PaginatorResponse, PlannedStatement, QueryPlan,
+    DISTANCE_COLUMN,
 };
 use serde::{de::DeserializeOwned, Serialize};
+use std::future::IntoFuture;
 use surrealdb::{Connection, Surreal};
 
-/// Safely escapes a string value for use in SurrealQL queries.
-/// This replaces single quotes and backslashes to prevent injection.
-fn escape_string_value(s: &str) -> String {
-    s.replace('\\', "\\\\").replace('\'', "\\'")
-}
-
 #[derive(Debug, serde::Deserialize)]
 pub struct CountResult {
     pub count: i64,
 }
 
-pub async fn paginate_query<T, C>(
-    db: &Surreal<C>,
-    base_query: &str,
-    params: &PaginationParams,
-) -> Result<PaginatorResponse<T>, PaginatorError>
-where
-    T: DeserializeOwned + Serialize,
-    C: Connection,
-{
-    let total = if params.disable_total_count {
-        None
-    } else {
-        let mut count_query = if base_query.trim().to_uppercase().starts_with("SELECT") {
-            let query_upper = base_query.to_uppercase();
-            if let Some(from_pos) = query_upper.find("FROM") {
-                let after_from = &base_query[from_pos..];
-                format!("SELECT count() {}", after_from)
-            } else {
-                return Err(PaginatorError::Custom(
-                    "Invalid query: missing FROM clause".to_string(),
-                ));
-            }
-        } else {
-            return Err(PaginatorError::Custom(
-                "Query must start with SELECT".to_string(),
-            ));
-        };
+/// Execution options for `paginate_query_with_options`.
+#[derive(Clone, Debug, Default)]
+pub struct PaginateOptions {
+    count_cache: Option<CountCache>,
+    cache_tags: Vec<String>,
+    concurrent: bool,
+    trace_filter_values: bool,
+    pub(crate) project_fields: bool,
+}
 
-        if let Some(where_clause) = params.to_surrealql_where() {
-            let query_upper = count_query.to_uppercase();
-            if query_upper.contains(" WHERE ") {
-                count_query.push_str(&format!(" AND {}", where_clause));
-            } else {
-                count_query.push_str(&format!(" WHERE {}", where_clause));
-            }
-        }
+impl PaginateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves `count()` out of the request when `cache` holds a total for the
+    /// same query, filters and search. See `CountCache` for `tags`.
+    pub fn count_cache<I, S>(mut self, cache: CountCache, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.count_cache = Some(cache);
+        self.cache_tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
 
-        let count_result: Vec<CountResult> = db
-            .query(&count_query)
-            .await
-            .map_err(|e| PaginatorError::Custom(format!("Count query failed: {}", e)))?
-            .take(0)
-            .map_err(|e| PaginatorError::Custom(format!("Failed to extract count: {}", e)))?;
+    /// Sends the count and data statements to the server in a single request
+    /// instead of two round trips. SurrealQL has no window functions, so there
+    /// is no `COUNT(*) OVER ()` alternative here.
+    pub fn concurrent(mut self, concurrent: bool) -> Self {
+        self.concurrent = concurrent;
+        self
+    }
 
-        count_result.first().map(|r| r.count as u32)
-    };
+    /// Records filter values in `tracing` spans instead of redacting them to
+    /// `?`. Only has an effect with the `tracing` feature.
+    pub fn trace_filter_values(mut self, trace_filter_values: bool) -> Self {
+        self.trace_filter_values = trace_filter_values;
+        self
+    }
+
+    /// Makes `QueryBuilder::paginate_with_options` select only
+    /// `PaginationParams::fields` when the builder selects `*`. The ordering
+    /// field is selected as well, since SurrealQL only orders by selected
+    /// fields; drop it with `paginator_rs::select_fields` if needed.
+    pub fn project_fields(mut self, project_fields: bool) -> Self {
+        self.project_fields = project_fields;
+        self
+    }
+}
 
-    let mut paginated_query = base_query.to_string();
+/// Appends the filter and search conditions of `params` to `base_query`.
+fn filtered_query(base_query: &str, params: &PaginationParams) -> String {
+    let mut filtered_query = base_query.to_string();
 
     if let Some(where_clause) = params.to_surrealql_where() {
-        let query_upper = paginated_query.to_uppercase();
+        let query_upper = filtered_query.to_uppercase();
         if query_upper.contains(" WHERE ") {
-            paginated_query.push_str(&format!(" AND {}", where_clause));
+            filtered_query.push_str(&format!(" AND {}", where_clause));
         } else {
-            paginated_query.push_str(&format!(" WHERE {}", where_clause));
+            filtered_query.push_str(&format!(" WHERE {}", where_clause));
+        }
+    }
+
+    filtered_query
+}
+
+/// Builds the count statement. `GROUP ALL` folds the per-record counts into
+/// one row. SurrealDB has no planner estimates, so
+/// `CountStrategy::Estimated` is answered with an exact count.
+fn count_query(
+    base_query: &str,
+    filtered_query: &str,
+    params: &PaginationParams,
+) -> Result<String, PaginatorError> {
+    match params.count_strategy.count_limit() {
+        Some(limit) => Ok(format!(
+            "SELECT count() FROM ({} LIMIT {}) GROUP ALL",
+            filtered_query, limit
+        )),
+        None => {
+            if !base_query.trim().to_uppercase().starts_with("SELECT") {
+                return Err(PaginatorError::Custom(
+                    "Query must start with SELECT".to_string(),
+                ));
+            }
+            let query_upper = filtered_query.to_uppercase();
+            match query_upper.find("FROM") {
+                Some(from_pos) => Ok(format!(
+                    "SELECT count() {} GROUP ALL",
+                    &filtered_query[from_pos..]
+                )),
+                None => Err(PaginatorError::Custom(
+                    "Invalid query: missing FROM clause".to_string(),
+                )),
+            }
         }
     }
+}
+
+/// Builds the page statement: cursor condition, ordering and limits. When
+/// sorting by distance, the filtered rows are selected again with their
+/// `geo::distance` in metres as `DISTANCE_COLUMN`.
+fn data_query(filtered_query: String, params: &PaginationParams) -> Result<String, PaginatorError> {
+    let distance = match params.distance_sort {
+        Some(ref sort) => {
+            validate_field_name(&sort.field)?;
+            Some(format!(
+                "geo::distance({}, {})",
+                sort.field,
+                sort.origin.to_surrealql()
+            ))
+        }
+        None => None,
+    };
+    let mut paginated_query = match distance {
+        Some(ref distance) => format!(
+            "SELECT *, {} AS {} FROM ({})",
+            distance, DISTANCE_COLUMN, filtered_query
+        ),
+        None => filtered_query,
+    };
 
     if let Some(ref cursor) = params.cursor {
         // Validate cursor field name to prevent injection
         validate_field_name(&cursor.field)?;
 
-        let operator = match cursor.direction {
-            CursorDirection::After => match params.sort_direction.as_ref() {
-                Some(paginator_rs::SortDirection::Desc) => "<",
-                _ => ">",
-            },
-            CursorDirection::Before => match params.sort_direction.as_ref() {
-                Some(paginator_rs::SortDirection::Desc) => ">",
-                _ => "<",
-            },
-        };
+        let operator = params.cursor_operator();
 
-        let cursor_value = match &cursor.value {
-            CursorValue::String(s) => format!("'{}'", escape_string_value(s)),
-            CursorValue::Int(i) => i.to_string(),
-            CursorValue::Float(f) => f.to_string(),
-            CursorValue::Uuid(u) => format!("<uuid> '{}'", escape_string_value(u)),
-        };
+        let cursor_value = cursor_literal(&cursor.value);
 
+        // WHERE sees the rows before projection, so the distance is
+        // compared as an expression rather than by its alias. The wrapped
+        // query's own WHERE sits inside the subquery.
+        let field = match distance {
+            Some(ref distance) if cursor.field == DISTANCE_COLUMN => distance.as_str(),
+            _ => cursor.field.as_str(),
+        };
+        let mut condition = format!("{} {} {}", field, operator, cursor_value);
+        if let (Some(key), Some(tie_value)) = (params.tie_breaker_field(), &cursor.tie_value) {
+            // Rows tying on the cursor field continue after the cursor row's
+            // key.
+            validate_field_name(key)?;
+            condition = format!(
+                "({} OR ({} = {} AND {} {} {}))",
+                condition,
+                field,
+                cursor_value,
+                key,
+                operator,
+                cursor_literal(tie_value)
+            );
+        }
         let query_upper = paginated_query.to_uppercase();
-        if query_upper.contains(" WHERE ") {
-            paginated_query.push_str(&format!(
-                " AND {} {} {}",
-                cursor.field, operator, cursor_value
-            ));
+        if distance.is_none() && query_upper.contains(" WHERE ") {
+            paginated_query.push_str(&format!(" AND {}", condition));
         } else {
-            paginated_query.push_str(&format!(
-                " WHERE {} {} {}",
-                cursor.field, operator, cursor_value
-            ));
+            paginated_query.push_str(&format!(" WHERE {}", condition));
         }
     }
 
-    if let Some(ref sort_field) = params.sort_by {
+    if let Some(sort_field) = params.order_field() {
         // Validate sort field name to prevent injection
         validate_field_name(sort_field)?;
 
-        let direction = match params.sort_direction.as_ref() {
-            Some(paginator_rs::SortDirection::Desc) => "DESC",
-            _ => "ASC",
+        // Backward cursor pages scan against the requested direction
+        let direction = match params.order_direction() {
+            paginator_rs::SortDirection::Desc => "DESC",
+            paginator_rs::SortDirection::Asc => "ASC",
         };
         paginated_query.push_str(&format!(" ORDER BY {} {}", sort_field, direction));
+        if let Some(key) = params.tie_breaker_field() {
+            validate_field_name(key)?;
+            paginated_query.push_str(&format!(", {} {}", key, direction));
+        }
     }
 
     if params.cursor.is_some() {
-        paginated_query.push_str(&format!(" LIMIT {}", params.limit() + 1));
+        paginated_query.push_str(&format!(" LIMIT {}", params.fetch_limit()));
     } else {
         paginated_query.push_str(&format!(
             " LIMIT {} START {}",
-            params.limit(),
+            params.fetch_limit(),
             params.offset()
         ));
     }
 
-    let mut data: Vec<T> = db
-        .query(&paginated_query)
-        .await
-        .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?
-        .take(0)
-        .map_err(|e| PaginatorError::Custom(format!("Failed to extract results: {}", e)))?;
-
-    let meta = if params.cursor.is_some() {
-        let has_next = data.len() > params.per_page as usize;
-        if has_next {
-            data.truncate(params.per_page as usize);
-        }
-        PaginatorResponseMeta::new_with_cursors(
-            params.page,
-            params.per_page,
-            total,
-            has_next,
-            None,
-            None,
-        )
-    } else if let Some(count) = total {
-        PaginatorResponseMeta::new(params.page, params.per_page, count)
+    Ok(paginated_query)
+}
+
+fn cursor_literal(value: &CursorValue) -> String {
+    match value {
+        CursorValue::String(s) => format!("'{}'", escape_surrealql(s)),
+        CursorValue::Int(i) => i.to_string(),
+        CursorValue::Float(f) => f.to_string(),
+        CursorValue::DateTime(dt) => FilterValue::DateTime(*dt).to_surrealql_string(),
+        CursorValue::Uuid(u) => format!("<uuid> '{}'", escape_surrealql(u)),
+    }
+}
+
+/// Returns the count and data statements `paginate_query` would run,
+/// without executing anything. Values are inlined into SurrealQL, so the
+/// statements carry no binds.
+pub fn plan(base_query: &str, params: &PaginationParams) -> Result<QueryPlan, PaginatorError> {
+    let filtered_query = filtered_query(base_query, params);
+    let count = if params.disable_total_count {
+        None
     } else {
-        let has_next = data.len() as u32 > params.per_page;
-        PaginatorResponseMeta::new_without_total(params.page, params.per_page, has_next)
+        Some(count_query(base_query, &filtered_query, params)?)
     };
+    let data = data_query(filtered_query, params)?;
+
+    Ok(QueryPlan {
+        count: count.map(|sql| PlannedStatement {
+            sql,
+            binds: Vec::new(),
+        }),
+        data: PlannedStatement {
+            sql: data,
+            binds: Vec::new(),
+        },
+    })
+}
+
+pub async fn paginate_query<T, C>(
+    db: &Surreal<C>,
+    base_query: &str,
+    params: &PaginationParams,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    T: DeserializeOwned + Serialize,
+    C: Connection,
+{
+    paginate_query_with_options(db, base_query, params, &PaginateOptions::default()).await
+}
+
+/// Like `paginate_query`, with caching behaviour taken from `options`.
+pub async fn paginate_query_with_options<T, C>(
+    db: &Surreal<C>,
+    base_query: &str,
+    params: &PaginationParams,
+    options: &PaginateOptions,
+) -> Result<PaginatorResponse<T>, PaginatorError>
+where
+    T: DeserializeOwned + Serialize,
+    C: Connection,
+{
+    let span = PaginationSpan::new("surrealdb", params, options.trace_filter_values);
+    span.run(async {
+        let filtered_query = filtered_query(base_query, params);
+
+        let fingerprint = options
+            .count_cache
+            .as_ref()
+            .map(|_| count_fingerprint(base_query, params));
+        let cached = options
+            .count_cache
+            .as_ref()
+            .zip(fingerprint)
+            .and_then(|(cache, fingerprint)| cache.get(fingerprint));
+
+        let count_statement = if params.disable_total_count || cached.is_some() {
+            None
+        } else {
+            Some(count_query(base_query, &filtered_query, params)?)
+        };
+        let paginated_query = data_query(filtered_query, params)?;
+
+        let (count_result, data): (Option<Vec<CountResult>>, Vec<T>) = match count_statement {
+            Some(count_query) if options.concurrent => {
+                let batch = db.query(&count_query).query(&paginated_query).into_future();
+                let mut response = span.phase("count+data", batch).await.map_err(|e| {
+                    PaginatorError::Custom(format!("Paginated query failed: {}", e))
+                })?;
+                let count_result = response.take(0).map_err(|e| {
+                    PaginatorError::Custom(format!("Failed to extract count: {}", e))
+                })?;
+                let data = response.take(1).map_err(|e| {
+                    PaginatorError::Custom(format!("Failed to extract results: {}", e))
+                })?;
+                (Some(count_result), data)
+            }
+            count_query => {
+                let count_result = match count_query {
+                    Some(count_query) => Some(
+                        span.phase("count", db.query(&count_query).into_future())
+                            .await
+                            .map_err(|e| {
+                                PaginatorError::Custom(format!("Count query failed: {}", e))
+                            })?
+                            .take(0)
+                            .map_err(|e| {
+                                PaginatorError::Custom(format!("Failed to extract count: {}", e))
+                            })?,
+                    ),
+                    None => None,
+                };
+                let data = span
+                    .phase("data", db.query(&paginated_query).into_future())
+                    .await
+                    .map_err(|e| PaginatorError::Custom(format!("Paginated query failed: {}", e)))?
+                    .take(0)
+                    .map_err(|e| {
+                        PaginatorError::Custom(format!("Failed to extract results: {}", e))
+                    })?;
+                (count_result, data)
+            }
+        };
+
+        let total = match count_result {
+            Some(count_result) => {
+                let total = count_result
+                    .first()
+                    .map(|r| params.count_strategy.resolve(r.count as u64));
+                if let (Some(cache), Some(fingerprint), Some(total)) =
+                    (&options.count_cache, fingerprint, total)
+                {
+                    cache.insert(fingerprint, total, &options.cache_tags);
+                }
+                total
+            }
+            None if params.disable_total_count => None,
+            None => cached,
+        };
 
-    Ok(PaginatorResponse { data, meta })
+        let response = PaginatorResponse::from_rows(data, params, total);
+        span.record_result(response.data.len(), response.meta.total);
+        Ok(response)
+    })
+    .await
 }
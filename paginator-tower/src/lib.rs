@@ -0,0 +1,154 @@
+use http::{
+    header::{HeaderName, HeaderValue, LINK},
+    Request, Response,
+};
+use paginator_rs::{
+    link_header, meta_headers, page_links, parse_query, PaginationConfig, PaginatorResponseMeta,
+    ProblemDetails, QUERY_KEYS,
+};
+use pin_project_lite::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tower::{Layer, Service};
+
+/// Applies one `PaginationConfig` to every request of a tower service.
+///
+/// Each request's query string with any of the `QUERY_KEYS` is parsed once
+/// with `parse_query`, and the `PaginationParams` and the config are stored
+/// in the request extensions. When parsing fails, in strict mode or with an
+/// invalid `tz` or `near`, the `ProblemDetails` is stored instead and the
+/// `paginator-axum` extractors reject the request with it, so routes that
+/// don't paginate are still called.
+///
+/// Responses carrying a `PaginatorResponseMeta` extension, as set by the
+/// `paginator-axum` responders, get the pagination headers and a `Link`
+/// header. Other services can insert the meta themselves.
+#[derive(Clone, Debug, Default)]
+pub struct PaginationLayer {
+    config: Arc<PaginationConfig>,
+}
+
+impl PaginationLayer {
+    pub fn new(config: PaginationConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl<S> Layer<S> for PaginationLayer {
+    type Service = PaginationService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PaginationService {
+            inner,
+            config: Arc::clone(&self.config),
+        }
+    }
+}
+
+/// The service built by `PaginationLayer`.
+#[derive(Clone, Debug)]
+pub struct PaginationService<S> {
+    inner: S,
+    config: Arc<PaginationConfig>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for PaginationService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let query = req.uri().query().map(str::to_string);
+        let pairs = form_urlencoded::parse(query.as_deref().unwrap_or_default().as_bytes());
+        let pairs: Vec<_> = pairs.collect();
+
+        // Queries without pagination keys belong to other routes and are
+        // left alone; the extractors fall back to the stored config.
+        if pairs
+            .iter()
+            .any(|(key, _)| QUERY_KEYS.contains(&key.as_ref()))
+        {
+            let parsed = parse_query(
+                pairs
+                    .iter()
+                    .map(|(key, value)| (key.as_ref(), value.as_ref())),
+                &self.config,
+            );
+            match parsed {
+                Ok(params) => {
+                    req.extensions_mut().insert(params);
+                }
+                Err(e) => {
+                    req.extensions_mut().insert(ProblemDetails::from(&e));
+                }
+            }
+        }
+        req.extensions_mut()
+            .insert(PaginationConfig::clone(&self.config));
+
+        ResponseFuture {
+            path: req.uri().path().to_string(),
+            query,
+            future: self.inner.call(req),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future of `PaginationService`.
+    pub struct ResponseFuture<F> {
+        #[pin]
+        future: F,
+        path: String,
+        query: Option<String>,
+    }
+}
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.future.poll(cx))?;
+        add_pagination_headers(&mut response, this.path, this.query.as_deref());
+        Poll::Ready(Ok(response))
+    }
+}
+
+fn add_pagination_headers<B>(response: &mut Response<B>, path: &str, query: Option<&str>) {
+    let Some(meta) = response
+        .extensions()
+        .get::<PaginatorResponseMeta>()
+        .cloned()
+    else {
+        return;
+    };
+
+    let headers = response.headers_mut();
+    for (name, value) in meta_headers(&meta) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+    let links = page_links(path, query, &meta);
+    if let Ok(value) = HeaderValue::from_str(&link_header(&links)) {
+        headers.insert(LINK, value);
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+/// How the total row count of a paginated query is computed.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CountStrategy {
+    /// Run an exact `COUNT(*)` over the filtered query.
+    #[default]
+    Exact,
+    /// Count at most `n` rows. Totals above the cap are reported as the cap
+    /// and flagged as a lower bound ("10,000+").
+    Capped(u32),
+    /// Use the planner's row estimate instead of counting. Backends without
+    /// planner estimates fall back to an exact count.
+    Estimated,
+}
+
+/// Describes how trustworthy `PaginatorResponseMeta::total` is.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalKind {
+    #[default]
+    Exact,
+    /// The real total is at least `total`.
+    LowerBound,
+    /// `total` is a planner estimate and may be off in either direction.
+    Estimate,
+}
+
+impl TotalKind {
+    pub fn is_exact(&self) -> bool {
+        matches!(self, TotalKind::Exact)
+    }
+}
+
+impl CountStrategy {
+    /// Returns `true` when the strategy yields an exact total, in which case
+    /// `has_next` can be derived from the total alone.
+    pub fn is_exact(&self) -> bool {
+        matches!(self, CountStrategy::Exact)
+    }
+
+    /// Row limit to apply to the count subquery, if any.
+    ///
+    /// One extra row is counted so a result of exactly `cap` rows can still be
+    /// reported as exact.
+    pub fn count_limit(&self) -> Option<u64> {
+        match self {
+            CountStrategy::Capped(cap) => Some(*cap as u64 + 1),
+            _ => None,
+        }
+    }
+
+    /// Turns the number produced by a capped or exact count into the reported
+    /// total and its kind.
+    pub fn resolve(&self, counted: u64) -> (u32, TotalKind) {
+        match self {
+            CountStrategy::Capped(cap) if counted > *cap as u64 => (*cap, TotalKind::LowerBound),
+            _ => (counted.min(u32::MAX as u64) as u32, TotalKind::Exact),
+        }
+    }
+}
+
+/// Extracts the planner row estimate from the first line of PostgreSQL
+/// `EXPLAIN` text output, e.g. `Seq Scan on users  (cost=0.00..35.50 rows=2550 width=4)`.
+pub fn parse_explain_rows(plan_line: &str) -> Option<u64> {
+    let start = plan_line.find("rows=")? + "rows=".len();
+    let digits: String = plan_line[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capped_count_resolves_lower_bound() {
+        let strategy = CountStrategy::Capped(10_000);
+        assert_eq!(strategy.count_limit(), Some(10_001));
+        assert_eq!(strategy.resolve(10_001), (10_000, TotalKind::LowerBound));
+        assert_eq!(strategy.resolve(10_000), (10_000, TotalKind::Exact));
+        assert_eq!(strategy.resolve(42), (42, TotalKind::Exact));
+    }
+
+    #[test]
+    fn test_parse_explain_rows() {
+        assert_eq!(
+            parse_explain_rows("Seq Scan on users  (cost=0.00..35.50 rows=2550 width=4)"),
+            Some(2550)
+        );
+        assert_eq!(
+            parse_explain_rows("Aggregate  (cost=1.00..1.01 rows=1 width=8)"),
+            Some(1)
+        );
+        assert_eq!(parse_explain_rows("Result"), None);
+    }
+}
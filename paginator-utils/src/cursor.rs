@@ -1,4 +1,5 @@
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -6,6 +7,10 @@ pub struct Cursor {
     pub field: String,
     pub value: CursorValue,
     pub direction: CursorDirection,
+    /// Value of `PaginationParams::tie_breaker` in the row the cursor points
+    /// at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tie_value: Option<CursorValue>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -18,6 +23,9 @@ pub enum CursorDirection {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum CursorValue {
+    /// A UTC timestamp, bound as a timestamp type. Listed before `String` so
+    /// encoded timestamps decode as timestamps again.
+    DateTime(DateTime<Utc>),
     String(String),
     Int(i64),
     Float(f64),
@@ -31,9 +39,15 @@ impl Cursor {
             field,
             value,
             direction,
+            tie_value: None,
         }
     }
 
+    pub fn with_tie_value(mut self, value: CursorValue) -> Self {
+        self.tie_value = Some(value);
+        self
+    }
+
     pub fn encode(&self) -> Result<String, String> {
         let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
         Ok(BASE64.encode(json.as_bytes()))
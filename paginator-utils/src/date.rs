@@ -0,0 +1,223 @@
+use chrono::{
+    DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike,
+    Utc,
+};
+pub use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Calendar unit of a relative date expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl DateUnit {
+    /// Unit of an offset such as `-7d`. `m` is minutes, `M` months.
+    fn from_suffix(suffix: char) -> Option<Self> {
+        match suffix {
+            's' => Some(DateUnit::Second),
+            'm' => Some(DateUnit::Minute),
+            'h' => Some(DateUnit::Hour),
+            'd' => Some(DateUnit::Day),
+            'w' => Some(DateUnit::Week),
+            'M' => Some(DateUnit::Month),
+            'y' => Some(DateUnit::Year),
+            _ => None,
+        }
+    }
+
+    /// Unit of an anchor such as `startOf:month`.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "minute" => Some(DateUnit::Minute),
+            "hour" => Some(DateUnit::Hour),
+            "day" => Some(DateUnit::Day),
+            "week" => Some(DateUnit::Week),
+            "month" => Some(DateUnit::Month),
+            "year" => Some(DateUnit::Year),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Anchor {
+    Now,
+    StartOf(DateUnit),
+}
+
+/// A point in time relative to the request, such as `now`, `now-7d`,
+/// `startOf:month` or `startOf:week-1w`.
+///
+/// An expression is an anchor (`now` or `startOf:<minute|hour|day|week|
+/// month|year>`) followed by any number of `+N<unit>`/`-N<unit>` offsets with
+/// units `s`, `m`, `h`, `d`, `w`, `M` (months) and `y`. Weeks start on Monday.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelativeDate {
+    anchor: Anchor,
+    offsets: Vec<(i64, DateUnit)>,
+}
+
+impl RelativeDate {
+    /// Parses `expr`, returning `None` when it is not a relative date.
+    pub fn parse(expr: &str) -> Option<Self> {
+        let (anchor, mut rest) = if let Some(rest) = expr.strip_prefix("now") {
+            (Anchor::Now, rest)
+        } else if let Some(rest) = expr.strip_prefix("startOf:") {
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            (
+                Anchor::StartOf(DateUnit::from_name(&rest[..end])?),
+                &rest[end..],
+            )
+        } else {
+            return None;
+        };
+
+        let mut offsets = Vec::new();
+        while let Some(sign) = rest.chars().next() {
+            let sign = match sign {
+                '+' => 1,
+                '-' => -1,
+                _ => return None,
+            };
+            let digits = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .filter(|&len| len > 0)?;
+            let amount: i64 = rest[1..=digits].parse().ok()?;
+            let unit = rest[digits + 1..].chars().next()?;
+            offsets.push((sign * amount, DateUnit::from_suffix(unit)?));
+            rest = &rest[digits + 1 + unit.len_utf8()..];
+        }
+
+        Some(Self { anchor, offsets })
+    }
+
+    /// Resolves the expression at `now`. Calendar units (`startOf`, days and
+    /// longer offsets) follow the wall clock of `time_zone`. Returns `None`
+    /// when the result is out of range.
+    pub fn resolve(&self, now: DateTime<Utc>, time_zone: Tz) -> Option<DateTime<Utc>> {
+        let mut at = now.with_timezone(&time_zone);
+        if let Anchor::StartOf(unit) = self.anchor {
+            at = start_of(at, unit)?;
+        }
+        for &(amount, unit) in &self.offsets {
+            at = shift(at, amount, unit)?;
+        }
+        Some(at.with_timezone(&Utc))
+    }
+}
+
+/// The unresolved text of a filter value holding relative dates, with the
+/// time zone it was resolved in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateSource {
+    pub expression: String,
+    pub time_zone: String,
+}
+
+/// Request time and time zone that relative dates resolve against.
+#[derive(Clone, Copy, Debug)]
+pub struct DateContext {
+    now: DateTime<Utc>,
+    time_zone: Tz,
+}
+
+impl Default for DateContext {
+    fn default() -> Self {
+        Self::new(Tz::UTC)
+    }
+}
+
+impl DateContext {
+    /// Context for a request arriving now in `time_zone`.
+    pub fn new(time_zone: Tz) -> Self {
+        Self::at(Utc::now(), time_zone)
+    }
+
+    /// Context with a fixed request time.
+    pub fn at(now: DateTime<Utc>, time_zone: Tz) -> Self {
+        Self { now, time_zone }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    pub fn time_zone(&self) -> Tz {
+        self.time_zone
+    }
+
+    /// Resolves `expr` if it is a relative date expression or an RFC 3339
+    /// timestamp, the form resolved dates take in generated links.
+    pub fn resolve(&self, expr: &str) -> Option<DateTime<Utc>> {
+        if let Ok(at) = DateTime::parse_from_rfc3339(expr) {
+            return Some(at.with_timezone(&Utc));
+        }
+        RelativeDate::parse(expr)?.resolve(self.now, self.time_zone)
+    }
+}
+
+/// Parses an IANA time zone name such as `Asia/Jakarta`.
+pub fn parse_time_zone(name: &str) -> Result<Tz, String> {
+    name.parse::<Tz>()
+        .map_err(|_| format!("Unknown time zone '{}'", name))
+}
+
+fn start_of(at: DateTime<Tz>, unit: DateUnit) -> Option<DateTime<Tz>> {
+    let date = at.date_naive();
+    let naive = match unit {
+        DateUnit::Second => date.and_hms_opt(at.hour(), at.minute(), at.second())?,
+        DateUnit::Minute => date.and_hms_opt(at.hour(), at.minute(), 0)?,
+        DateUnit::Hour => date.and_hms_opt(at.hour(), 0, 0)?,
+        DateUnit::Day => date.and_time(NaiveTime::MIN),
+        DateUnit::Week => {
+            let monday = date - Duration::days(date.weekday().num_days_from_monday().into());
+            monday.and_time(NaiveTime::MIN)
+        }
+        DateUnit::Month => date.with_day(1)?.and_time(NaiveTime::MIN),
+        DateUnit::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1)?.and_time(NaiveTime::MIN),
+    };
+    localize(at.timezone(), naive)
+}
+
+fn shift(at: DateTime<Tz>, amount: i64, unit: DateUnit) -> Option<DateTime<Tz>> {
+    let naive = at.naive_local();
+    let shifted = match unit {
+        DateUnit::Second => return at.checked_add_signed(Duration::try_seconds(amount)?),
+        DateUnit::Minute => return at.checked_add_signed(Duration::try_minutes(amount)?),
+        DateUnit::Hour => return at.checked_add_signed(Duration::try_hours(amount)?),
+        DateUnit::Day => naive.checked_add_signed(Duration::try_days(amount)?)?,
+        DateUnit::Week => naive.checked_add_signed(Duration::try_weeks(amount)?)?,
+        DateUnit::Month => add_months(naive, amount)?,
+        DateUnit::Year => add_months(naive, amount.checked_mul(12)?)?,
+    };
+    localize(at.timezone(), shifted)
+}
+
+fn add_months(naive: NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let delta = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months >= 0 {
+        naive.checked_add_months(delta)
+    } else {
+        naive.checked_sub_months(delta)
+    }
+}
+
+/// Maps a wall-clock time to an instant. Ambiguous times take the earlier
+/// instant; times skipped by a DST change move forward an hour.
+fn localize(time_zone: Tz, naive: NaiveDateTime) -> Option<DateTime<Tz>> {
+    time_zone
+        .from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            time_zone
+                .from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+}
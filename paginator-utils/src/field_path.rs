@@ -0,0 +1,89 @@
+/// One step into a JSON value: an object key or an array index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(u32),
+}
+
+/// A field reference descending into a JSON column, such as `attrs.color`
+/// or `attrs[0].sku`.
+///
+/// The column is a plain identifier; keys may contain alphanumerics, `_` and
+/// `-`, and indexes are unsigned integers. Backends bind the segments rather
+/// than splicing them into the statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldPath {
+    column: String,
+    segments: Vec<PathSegment>,
+}
+
+impl FieldPath {
+    pub fn parse(field: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid JSON path '{}'", field);
+        let is_key_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+
+        let end = field.find(['.', '[']).unwrap_or(field.len());
+        let column = &field[..end];
+        if column.is_empty() || !column.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(invalid());
+        }
+
+        let mut segments = Vec::new();
+        let mut rest = &field[end..];
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let len = after.find(|c| !is_key_char(c)).unwrap_or(after.len());
+                if len == 0 {
+                    return Err(invalid());
+                }
+                segments.push(PathSegment::Key(after[..len].to_string()));
+                rest = &after[len..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let (index, after) = after.split_once(']').ok_or_else(invalid)?;
+                let index = index.parse::<u32>().map_err(|_| invalid())?;
+                segments.push(PathSegment::Index(index));
+                rest = after;
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        Ok(Self {
+            column: column.to_string(),
+            segments,
+        })
+    }
+
+    /// The JSON column the path starts from.
+    pub fn column(&self) -> &str {
+        &self.column
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// The segments as text, as taken by PostgreSQL's `jsonb_extract_path`.
+    pub fn segment_texts(&self) -> Vec<String> {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => key.clone(),
+                PathSegment::Index(index) => index.to_string(),
+            })
+            .collect()
+    }
+
+    /// The SQL/JSON path of the segments (`$."color"[0]`), as taken by
+    /// MySQL's `JSON_EXTRACT` and SQLite's `json_extract`.
+    pub fn json_path(&self) -> String {
+        let mut path = String::from("$");
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Key(key) => path.push_str(&format!(".\"{}\"", key)),
+                PathSegment::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        path
+    }
+}
@@ -1,3 +1,6 @@
+use crate::date::DateSource;
+use crate::geo::{polygon_surrealql, polygon_wkt, GeoBox, GeoPoint};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -17,26 +20,161 @@ pub enum FilterOperator {
     IsNotNull,
     Between,
     Contains,
+    /// The array field holds every listed value.
+    ContainsAll,
+    /// The array field holds at least one listed value.
+    ContainsAny,
+    /// Compares the number of elements of the array field with an integer.
+    LengthEq,
+    LengthGt,
+    LengthGte,
+    LengthLt,
+    LengthLte,
+    /// Matches values beginning with the given text. `%` and `_` in the text
+    /// match literally.
+    StartsWith,
+    /// Matches values ending with the given text, taken literally.
+    EndsWith,
+    /// Case-insensitive equality.
+    IEq,
+    /// Matches values against a regular expression in the backend's syntax.
+    Regex,
+    /// The geometry lies within a distance in metres of a point. The value is
+    /// an array of the point and the radius.
+    WithinRadius,
+    /// The geometry lies inside a bounding box.
+    WithinBox,
+    /// The geometry lies inside a polygon.
+    WithinPolygon,
+}
+
+/// Escape character of the `LIKE` patterns built for `StartsWith` and
+/// `EndsWith`. It needs no escaping in any SQL dialect's string literals.
+pub const LIKE_ESCAPE: char = '!';
+
+/// Escapes the `LIKE` metacharacters `%` and `_` (and the escape character
+/// itself) with `LIKE_ESCAPE`, so `value` matches literally.
+pub fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | LIKE_ESCAPE) {
+            escaped.push(LIKE_ESCAPE);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escapes `\` and `'` for a single-quoted SurrealQL string, whose escapes
+/// use backslashes rather than doubled quotes.
+pub fn escape_surrealql(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+impl FilterOperator {
+    pub fn is_array_operator(&self) -> bool {
+        matches!(
+            self,
+            FilterOperator::ContainsAll
+                | FilterOperator::ContainsAny
+                | FilterOperator::LengthEq
+                | FilterOperator::LengthGt
+                | FilterOperator::LengthGte
+                | FilterOperator::LengthLt
+                | FilterOperator::LengthLte
+        )
+    }
+
+    /// Ordering comparisons, the only operators that read relative dates
+    /// such as `now-7d` from query strings.
+    pub fn is_range_operator(&self) -> bool {
+        matches!(
+            self,
+            FilterOperator::Gt
+                | FilterOperator::Lt
+                | FilterOperator::Gte
+                | FilterOperator::Lte
+                | FilterOperator::Between
+        )
+    }
+
+    pub fn is_geo_operator(&self) -> bool {
+        matches!(
+            self,
+            FilterOperator::WithinRadius
+                | FilterOperator::WithinBox
+                | FilterOperator::WithinPolygon
+        )
+    }
+
+    /// The comparison a `Length*` operator applies to the element count.
+    pub fn length_comparison(&self) -> Option<&'static str> {
+        match self {
+            FilterOperator::LengthEq => Some("="),
+            FilterOperator::LengthGt => Some(">"),
+            FilterOperator::LengthGte => Some(">="),
+            FilterOperator::LengthLt => Some("<"),
+            FilterOperator::LengthLte => Some("<="),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum FilterValue {
+    /// A UTC timestamp, e.g. a resolved `RelativeDate`. Bound as a timestamp
+    /// type by the database backends. Listed before `String` so serialized
+    /// timestamps deserialize as timestamps again.
+    DateTime(DateTime<Utc>),
     String(String),
     Int(i64),
     Float(f64),
     Bool(bool),
+    Point(GeoPoint),
+    BoundingBox(GeoBox),
     Array(Vec<FilterValue>),
+    /// The ring of a polygon; it is closed automatically. Deserialized
+    /// polygons arrive as an `Array` of points, which `Filter::area` accepts
+    /// as well.
+    Polygon(Vec<GeoPoint>),
     Null,
 }
 
 impl FilterValue {
+    /// The value as text for string matching: strings as they are, other
+    /// scalars in their SQL form.
+    pub fn to_text(&self) -> String {
+        match self {
+            FilterValue::String(s) => s.clone(),
+            FilterValue::DateTime(dt) => dt.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            FilterValue::Point(point) => point.to_wkt(),
+            FilterValue::BoundingBox(bbox) => polygon_wkt(&bbox.ring()),
+            FilterValue::Polygon(ring) => polygon_wkt(ring),
+            other => other.to_sql_string(),
+        }
+    }
+
+    /// The members of an array value, or the value itself as one member.
+    pub fn elements(&self) -> &[FilterValue] {
+        match self {
+            FilterValue::Array(values) => values,
+            value => std::slice::from_ref(value),
+        }
+    }
+
     pub fn to_sql_string(&self) -> String {
         match self {
             FilterValue::String(s) => format!("'{}'", s.replace('\'', "''")),
             FilterValue::Int(i) => i.to_string(),
             FilterValue::Float(f) => f.to_string(),
             FilterValue::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+            FilterValue::DateTime(dt) => {
+                format!("'{}'", dt.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+            FilterValue::Point(_) | FilterValue::BoundingBox(_) | FilterValue::Polygon(_) => {
+                format!("'{}'", self.to_text())
+            }
             FilterValue::Array(arr) => {
                 let items: Vec<String> = arr.iter().map(|v| v.to_sql_string()).collect();
                 format!("({})", items.join(", "))
@@ -44,6 +182,24 @@ impl FilterValue {
             FilterValue::Null => "NULL".to_string(),
         }
     }
+
+    /// Like `to_sql_string`, but escapes strings for SurrealQL, casts
+    /// timestamps to SurrealDB datetimes and writes geometries as SurrealDB
+    /// points and GeoJSON polygons.
+    pub fn to_surrealql_string(&self) -> String {
+        match self {
+            FilterValue::String(s) => format!("'{}'", escape_surrealql(s)),
+            FilterValue::DateTime(_) => format!("<datetime> {}", self.to_sql_string()),
+            FilterValue::Point(point) => point.to_surrealql(),
+            FilterValue::BoundingBox(bbox) => polygon_surrealql(&bbox.ring()),
+            FilterValue::Polygon(ring) => polygon_surrealql(ring),
+            FilterValue::Array(arr) => {
+                let items: Vec<String> = arr.iter().map(|v| v.to_surrealql_string()).collect();
+                format!("({})", items.join(", "))
+            }
+            _ => self.to_sql_string(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -51,6 +207,10 @@ pub struct Filter {
     pub field: String,
     pub operator: FilterOperator,
     pub value: FilterValue,
+    /// Where `value` came from when it holds resolved relative dates. Count
+    /// caches key on it, since the resolved instants move with every request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_source: Option<DateSource>,
 }
 
 impl Filter {
@@ -59,6 +219,55 @@ impl Filter {
             field: field.into(),
             operator,
             value,
+            date_source: None,
+        }
+    }
+
+    /// The centre and radius in metres of a `WithinRadius` filter.
+    pub fn radius(&self) -> Option<(GeoPoint, f64)> {
+        if self.operator != FilterOperator::WithinRadius {
+            return None;
+        }
+        match self.value.elements() {
+            [FilterValue::Point(center), FilterValue::Float(meters)] => Some((*center, *meters)),
+            [FilterValue::Point(center), FilterValue::Int(meters)] => {
+                Some((*center, *meters as f64))
+            }
+            _ => None,
+        }
+    }
+
+    /// The ring a `WithinBox` or `WithinPolygon` filter tests against: the
+    /// corners of a box, or a polygon of at least three points.
+    pub fn area(&self) -> Option<Vec<GeoPoint>> {
+        if !matches!(
+            self.operator,
+            FilterOperator::WithinBox | FilterOperator::WithinPolygon
+        ) {
+            return None;
+        }
+        let ring = match &self.value {
+            FilterValue::BoundingBox(bbox) => return Some(bbox.ring()),
+            FilterValue::Polygon(ring) => ring.clone(),
+            FilterValue::Array(values) => values
+                .iter()
+                .map(|value| match value {
+                    FilterValue::Point(point) => Some(*point),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?,
+            _ => return None,
+        };
+        (ring.len() >= 3).then_some(ring)
+    }
+
+    /// The escaped `LIKE` pattern of a `StartsWith` or `EndsWith` filter.
+    pub fn like_pattern(&self) -> Option<String> {
+        let text = escape_like(&self.value.to_text());
+        match self.operator {
+            FilterOperator::StartsWith => Some(format!("{}%", text)),
+            FilterOperator::EndsWith => Some(format!("%{}", text)),
+            _ => None,
         }
     }
 
@@ -94,23 +303,82 @@ impl Filter {
             FilterOperator::Contains => {
                 format!("{} @> {}", self.field, self.value.to_sql_string())
             }
+            FilterOperator::ContainsAll => {
+                format!("{} @> {}", self.field, sql_array(self.value.elements()))
+            }
+            FilterOperator::ContainsAny => {
+                format!("{} && {}", self.field, sql_array(self.value.elements()))
+            }
+            FilterOperator::LengthEq
+            | FilterOperator::LengthGt
+            | FilterOperator::LengthGte
+            | FilterOperator::LengthLt
+            | FilterOperator::LengthLte => format!(
+                "cardinality({}) {} {}",
+                self.field,
+                self.operator.length_comparison().unwrap_or("="),
+                self.value.to_sql_string()
+            ),
+            FilterOperator::StartsWith | FilterOperator::EndsWith => format!(
+                "{} LIKE {} ESCAPE '{}'",
+                self.field,
+                FilterValue::String(self.like_pattern().unwrap_or_default()).to_sql_string(),
+                LIKE_ESCAPE
+            ),
+            FilterOperator::IEq => {
+                format!(
+                    "LOWER({}) = LOWER({})",
+                    self.field,
+                    self.text_value().to_sql_string()
+                )
+            }
+            FilterOperator::Regex => {
+                format!("{} ~ {}", self.field, self.text_value().to_sql_string())
+            }
+            FilterOperator::WithinRadius => match self.radius() {
+                Some((center, meters)) => format!(
+                    "ST_DWithin({}::geography, ST_GeogFromText('{}'), {})",
+                    self.field,
+                    center.to_wkt(),
+                    meters
+                ),
+                None => "1=0".to_string(),
+            },
+            FilterOperator::WithinBox | FilterOperator::WithinPolygon => match self.area() {
+                Some(ring) => format!(
+                    "ST_Within({}::geometry, ST_GeomFromText('{}', 4326))",
+                    self.field,
+                    polygon_wkt(&ring)
+                ),
+                None => "1=0".to_string(),
+            },
         }
     }
 
     pub fn to_surrealql_where(&self) -> String {
         match &self.operator {
-            FilterOperator::Eq => format!("{} = {}", self.field, self.value.to_sql_string()),
-            FilterOperator::Ne => format!("{} != {}", self.field, self.value.to_sql_string()),
-            FilterOperator::Gt => format!("{} > {}", self.field, self.value.to_sql_string()),
-            FilterOperator::Lt => format!("{} < {}", self.field, self.value.to_sql_string()),
-            FilterOperator::Gte => format!("{} >= {}", self.field, self.value.to_sql_string()),
-            FilterOperator::Lte => format!("{} <= {}", self.field, self.value.to_sql_string()),
+            FilterOperator::Eq => format!("{} = {}", self.field, self.value.to_surrealql_string()),
+            FilterOperator::Ne => format!("{} != {}", self.field, self.value.to_surrealql_string()),
+            FilterOperator::Gt => format!("{} > {}", self.field, self.value.to_surrealql_string()),
+            FilterOperator::Lt => format!("{} < {}", self.field, self.value.to_surrealql_string()),
+            FilterOperator::Gte => {
+                format!("{} >= {}", self.field, self.value.to_surrealql_string())
+            }
+            FilterOperator::Lte => {
+                format!("{} <= {}", self.field, self.value.to_surrealql_string())
+            }
             FilterOperator::Like | FilterOperator::ILike => {
-                format!("{} ~ {}", self.field, self.value.to_sql_string())
+                format!("{} ~ {}", self.field, self.value.to_surrealql_string())
+            }
+            FilterOperator::In => {
+                format!("{} INSIDE {}", self.field, self.value.to_surrealql_string())
             }
-            FilterOperator::In => format!("{} INSIDE {}", self.field, self.value.to_sql_string()),
             FilterOperator::NotIn => {
-                format!("{} NOT INSIDE {}", self.field, self.value.to_sql_string())
+                format!(
+                    "{} NOT INSIDE {}",
+                    self.field,
+                    self.value.to_surrealql_string()
+                )
             }
             FilterOperator::IsNull => format!("{} IS NULL", self.field),
             FilterOperator::IsNotNull => format!("{} IS NOT NULL", self.field),
@@ -120,17 +388,93 @@ impl Filter {
                         return format!(
                             "{} >= {} AND {} <= {}",
                             self.field,
-                            arr[0].to_sql_string(),
+                            arr[0].to_surrealql_string(),
                             self.field,
-                            arr[1].to_sql_string()
+                            arr[1].to_surrealql_string()
                         );
                     }
                 }
-                format!("{} = {}", self.field, self.value.to_sql_string())
+                format!("{} = {}", self.field, self.value.to_surrealql_string())
             }
             FilterOperator::Contains => {
-                format!("{} CONTAINS {}", self.field, self.value.to_sql_string())
+                format!(
+                    "{} CONTAINS {}",
+                    self.field,
+                    self.value.to_surrealql_string()
+                )
+            }
+            FilterOperator::ContainsAll => format!(
+                "{} CONTAINSALL {}",
+                self.field,
+                surrealql_array(self.value.elements())
+            ),
+            FilterOperator::ContainsAny => format!(
+                "{} CONTAINSANY {}",
+                self.field,
+                surrealql_array(self.value.elements())
+            ),
+            FilterOperator::LengthEq
+            | FilterOperator::LengthGt
+            | FilterOperator::LengthGte
+            | FilterOperator::LengthLt
+            | FilterOperator::LengthLte => format!(
+                "array::len({}) {} {}",
+                self.field,
+                self.operator.length_comparison().unwrap_or("="),
+                self.value.to_surrealql_string()
+            ),
+            FilterOperator::StartsWith => format!(
+                "string::starts_with({}, {})",
+                self.field,
+                self.text_value().to_surrealql_string()
+            ),
+            FilterOperator::EndsWith => {
+                format!(
+                    "string::ends_with({}, {})",
+                    self.field,
+                    self.text_value().to_surrealql_string()
+                )
             }
+            FilterOperator::IEq => format!(
+                "string::lowercase({}) = string::lowercase({})",
+                self.field,
+                self.text_value().to_surrealql_string()
+            ),
+            FilterOperator::Regex => format!(
+                "string::matches({}, <regex> {})",
+                self.field,
+                self.text_value().to_surrealql_string()
+            ),
+            FilterOperator::WithinRadius => match self.radius() {
+                Some((center, meters)) => format!(
+                    "geo::distance({}, {}) <= {}",
+                    self.field,
+                    center.to_surrealql(),
+                    meters
+                ),
+                None => "false".to_string(),
+            },
+            FilterOperator::WithinBox | FilterOperator::WithinPolygon => match self.area() {
+                Some(ring) => format!("{} INSIDE {}", self.field, polygon_surrealql(&ring)),
+                None => "false".to_string(),
+            },
         }
     }
+
+    /// The value as a string, for string matching.
+    fn text_value(&self) -> FilterValue {
+        FilterValue::String(self.value.to_text())
+    }
+}
+
+/// PostgreSQL array literal, e.g. `ARRAY['a', 'b']`.
+fn sql_array(values: &[FilterValue]) -> String {
+    let items: Vec<String> = values.iter().map(|v| v.to_sql_string()).collect();
+    format!("ARRAY[{}]", items.join(", "))
+}
+
+/// SurrealQL array literal, e.g. `['a', 'b']`.
+fn surrealql_array(values: &[FilterValue]) -> String {
+    let items: Vec<String> = values.iter().map(|v| v.to_surrealql_string()).collect();
+    format!("[{}]", items.join(", "))
 }
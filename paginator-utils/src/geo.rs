@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// Column the computed distance is returned in when sorting by distance.
+pub const DISTANCE_COLUMN: &str = "distance";
+
+/// A WGS 84 position, longitude first as in WKT and GeoJSON.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GeoPoint {
+    pub lon: f64,
+    pub lat: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lon: f64, lat: f64) -> Self {
+        Self { lon, lat }
+    }
+
+    /// Checks that the coordinates are finite and within WGS 84 bounds.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-180.0..=180.0).contains(&self.lon) || !(-90.0..=90.0).contains(&self.lat) {
+            return Err(format!(
+                "Invalid coordinates ({}, {}): longitude must be within ±180 and latitude within ±90",
+                self.lon, self.lat
+            ));
+        }
+        Ok(())
+    }
+
+    /// Well-known text, e.g. `POINT(13.4 52.52)`.
+    pub fn to_wkt(&self) -> String {
+        format!("POINT({} {})", self.lon, self.lat)
+    }
+
+    /// SurrealQL point literal, e.g. `(13.4, 52.52)`.
+    pub fn to_surrealql(&self) -> String {
+        format!("({}, {})", self.lon, self.lat)
+    }
+}
+
+/// An axis-aligned box between a south-west and a north-east corner.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GeoBox {
+    pub min: GeoPoint,
+    pub max: GeoPoint,
+}
+
+impl GeoBox {
+    pub fn new(min: GeoPoint, max: GeoPoint) -> Self {
+        Self { min, max }
+    }
+
+    /// The corners as a closed ring, counter-clockwise from `min`.
+    pub fn ring(&self) -> Vec<GeoPoint> {
+        vec![
+            self.min,
+            GeoPoint::new(self.max.lon, self.min.lat),
+            self.max,
+            GeoPoint::new(self.min.lon, self.max.lat),
+            self.min,
+        ]
+    }
+}
+
+/// Well-known text of the polygon bounded by `ring`, closing the ring if
+/// its last point differs from the first.
+pub fn polygon_wkt(ring: &[GeoPoint]) -> String {
+    let points: Vec<String> = closed(ring)
+        .iter()
+        .map(|p| format!("{} {}", p.lon, p.lat))
+        .collect();
+    format!("POLYGON(({}))", points.join(", "))
+}
+
+/// SurrealQL GeoJSON polygon bounded by `ring`, closed like `polygon_wkt`.
+pub fn polygon_surrealql(ring: &[GeoPoint]) -> String {
+    let points: Vec<String> = closed(ring)
+        .iter()
+        .map(|p| format!("[{}, {}]", p.lon, p.lat))
+        .collect();
+    format!(
+        "{{ type: 'Polygon', coordinates: [[{}]] }}",
+        points.join(", ")
+    )
+}
+
+fn closed(ring: &[GeoPoint]) -> Vec<GeoPoint> {
+    let mut points = ring.to_vec();
+    if let (Some(first), Some(last)) = (ring.first(), ring.last()) {
+        if first != last {
+            points.push(*first);
+        }
+    }
+    points
+}
+
+/// Orders rows by their distance in metres from `origin`, nearest first
+/// unless the sort direction says otherwise.
+///
+/// The distance is selected as `DISTANCE_COLUMN`, which `sort_by` and
+/// cursors refer to; row types that declare a field of that name receive it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DistanceSort {
+    /// The geometry column measured from.
+    pub field: String,
+    pub origin: GeoPoint,
+}
+
+impl DistanceSort {
+    pub fn new(field: impl Into<String>, origin: GeoPoint) -> Self {
+        Self {
+            field: field.into(),
+            origin,
+        }
+    }
+}
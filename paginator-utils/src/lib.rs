@@ -1,13 +1,24 @@
+mod count;
 mod cursor;
+mod date;
+mod field_path;
 mod filter;
+mod geo;
 mod params;
+mod plan;
 mod response;
 mod search;
 
+pub use chrono;
+pub use count::{parse_explain_rows, CountStrategy, TotalKind};
 pub use cursor::{Cursor, CursorDirection, CursorValue};
-pub use filter::{Filter, FilterOperator, FilterValue};
-pub use params::{PaginationParams, SortDirection};
-pub use response::{PaginatorResponse, PaginatorResponseMeta};
+pub use date::{parse_time_zone, DateContext, DateSource, DateUnit, RelativeDate, Tz};
+pub use field_path::{FieldPath, PathSegment};
+pub use filter::{escape_like, escape_surrealql, Filter, FilterOperator, FilterValue, LIKE_ESCAPE};
+pub use geo::{polygon_surrealql, polygon_wkt, DistanceSort, GeoBox, GeoPoint, DISTANCE_COLUMN};
+pub use params::{parse_fields, PaginationParams, SortDirection};
+pub use plan::{BindValue, PlannedStatement, QueryPlan};
+pub use response::{PaginationLinks, PaginatorResponse, PaginatorResponseMeta};
 pub use search::SearchParams;
 
 /// Trait for types that can be converted to PaginationParams
@@ -1,5 +1,7 @@
-use crate::cursor::Cursor;
-use crate::filter::Filter;
+use crate::count::CountStrategy;
+use crate::cursor::{Cursor, CursorDirection};
+use crate::filter::{escape_surrealql, Filter};
+use crate::geo::{DistanceSort, DISTANCE_COLUMN};
 use crate::search::SearchParams;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,16 @@ pub enum SortDirection {
     Desc,
 }
 
+/// Splits a comma-separated `fields` query value into field names, dropping
+/// blanks.
+pub fn parse_fields(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PaginationParams {
     pub page: u32,
@@ -21,7 +33,19 @@ pub struct PaginationParams {
     pub search: Option<SearchParams>,
     #[serde(default)]
     pub disable_total_count: bool,
+    #[serde(default)]
+    pub count_strategy: CountStrategy,
     pub cursor: Option<Cursor>,
+    /// Fields to return for each row; `None` returns every field.
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+    /// Adds the distance from a point as `DISTANCE_COLUMN` to every row.
+    #[serde(default)]
+    pub distance_sort: Option<DistanceSort>,
+    /// Unique field ordering rows that tie on the sort field, so keyset pages
+    /// neither skip nor repeat them.
+    #[serde(default)]
+    pub tie_breaker: Option<String>,
 }
 
 impl Default for PaginationParams {
@@ -34,7 +58,11 @@ impl Default for PaginationParams {
             filters: Vec::new(),
             search: None,
             disable_total_count: false,
+            count_strategy: CountStrategy::Exact,
             cursor: None,
+            fields: None,
+            distance_sort: None,
+            tie_breaker: None,
         }
     }
 }
@@ -49,7 +77,11 @@ impl PaginationParams {
             filters: Vec::new(),
             search: None,
             disable_total_count: false,
+            count_strategy: CountStrategy::Exact,
             cursor: None,
+            fields: None,
+            distance_sort: None,
+            tie_breaker: None,
         }
     }
 
@@ -78,6 +110,62 @@ impl PaginationParams {
         self
     }
 
+    pub fn with_count_strategy(mut self, strategy: CountStrategy) -> Self {
+        self.count_strategy = strategy;
+        self
+    }
+
+    pub fn with_fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.fields = Some(fields.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Orders by distance from `sort.origin`, nearest first.
+    pub fn with_distance_sort(mut self, sort: DistanceSort) -> Self {
+        self.distance_sort = Some(sort);
+        self.sort_by = Some(DISTANCE_COLUMN.to_string());
+        self.sort_direction.get_or_insert(SortDirection::Asc);
+        self
+    }
+
+    /// Orders rows that tie on the sort field by `field`, which must be
+    /// unique. Cursors then carry its value in `Cursor::tie_value`.
+    pub fn with_tie_breaker(mut self, field: impl Into<String>) -> Self {
+        self.tie_breaker = Some(field.into());
+        self
+    }
+
+    /// Checks the requested `fields` against `allowed`, rejecting unknown or
+    /// empty selections. Passes when no fields were requested.
+    pub fn validate_fields(&self, allowed: &[&str]) -> Result<(), String> {
+        let Some(ref fields) = self.fields else {
+            return Ok(());
+        };
+        if fields.is_empty() {
+            return Err("Field selection must name at least one field".to_string());
+        }
+        match fields.iter().find(|f| !allowed.contains(&f.as_str())) {
+            Some(field) => Err(format!(
+                "Unknown field '{}'; allowed fields: {}",
+                field,
+                allowed.join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `true` when `field` should appear in the response.
+    pub fn includes_field(&self, field: &str) -> bool {
+        match self.fields {
+            Some(ref fields) => fields.iter().any(|f| f == field),
+            None => true,
+        }
+    }
+
     pub fn offset(&self) -> u32 {
         (self.page - 1) * self.per_page
     }
@@ -86,6 +174,67 @@ impl PaginationParams {
         self.per_page
     }
 
+    /// Returns `true` when the data query has to fetch one row beyond
+    /// `per_page` to detect a next page, because no exact total is available.
+    pub fn needs_lookahead(&self) -> bool {
+        self.cursor.is_some() || self.disable_total_count || !self.count_strategy.is_exact()
+    }
+
+    /// Number of rows the data query should fetch, including the lookahead row.
+    pub fn fetch_limit(&self) -> u32 {
+        if self.needs_lookahead() {
+            self.per_page + 1
+        } else {
+            self.per_page
+        }
+    }
+
+    /// Returns `true` for a `CursorDirection::Before` cursor, which pages
+    /// towards the start of the list.
+    pub fn is_backward(&self) -> bool {
+        matches!(
+            self.cursor.as_ref().map(|c| &c.direction),
+            Some(CursorDirection::Before)
+        )
+    }
+
+    /// Field the data query orders by: `sort_by`, or the cursor field when
+    /// paging by cursor without an explicit sort.
+    pub fn order_field(&self) -> Option<&str> {
+        self.sort_by
+            .as_deref()
+            .or_else(|| self.cursor.as_ref().map(|c| c.field.as_str()))
+    }
+
+    /// Secondary order field: `tie_breaker`, unless the query already orders
+    /// by it.
+    pub fn tie_breaker_field(&self) -> Option<&str> {
+        self.tie_breaker
+            .as_deref()
+            .filter(|field| Some(*field) != self.order_field())
+    }
+
+    /// Direction the data query orders in. Backward cursor pages scan away
+    /// from the cursor, i.e. against `sort_direction`; their rows are put
+    /// back in `sort_direction` order by `PaginatorResponse::from_rows`.
+    pub fn order_direction(&self) -> SortDirection {
+        let requested = self.sort_direction.clone().unwrap_or(SortDirection::Asc);
+        match (self.is_backward(), requested) {
+            (false, direction) => direction,
+            (true, SortDirection::Asc) => SortDirection::Desc,
+            (true, SortDirection::Desc) => SortDirection::Asc,
+        }
+    }
+
+    /// Comparison operator selecting rows past the cursor in the scan
+    /// direction of `order_direction`.
+    pub fn cursor_operator(&self) -> &'static str {
+        match self.order_direction() {
+            SortDirection::Asc => ">",
+            SortDirection::Desc => "<",
+        }
+    }
+
     pub fn to_sql_where(&self) -> Option<String> {
         let mut conditions = Vec::new();
 
@@ -117,9 +266,9 @@ impl PaginationParams {
                 .iter()
                 .map(|field| {
                     let pattern = if search.exact_match {
-                        format!("'{}'", search.query.replace('\'', "''"))
+                        format!("'{}'", escape_surrealql(&search.query))
                     } else {
-                        format!("'%{}%'", search.query.replace('\'', "''"))
+                        format!("'%{}%'", escape_surrealql(&search.query))
                     };
                     format!("{} ~ {}", field, pattern)
                 })
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A value bound to a statement placeholder, in bind order.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum BindValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    DateTime(DateTime<Utc>),
+}
+
+impl From<bool> for BindValue {
+    fn from(value: bool) -> Self {
+        BindValue::Bool(value)
+    }
+}
+
+impl From<i64> for BindValue {
+    fn from(value: i64) -> Self {
+        BindValue::Int(value)
+    }
+}
+
+impl From<f64> for BindValue {
+    fn from(value: f64) -> Self {
+        BindValue::Float(value)
+    }
+}
+
+impl From<String> for BindValue {
+    fn from(value: String) -> Self {
+        BindValue::String(value)
+    }
+}
+
+impl From<DateTime<Utc>> for BindValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        BindValue::DateTime(value)
+    }
+}
+
+impl From<&str> for BindValue {
+    fn from(value: &str) -> Self {
+        BindValue::String(value.to_string())
+    }
+}
+
+/// A statement exactly as a backend would send it, with its bind values.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PlannedStatement {
+    pub sql: String,
+    pub binds: Vec<BindValue>,
+}
+
+/// The statements a paginated query would run, produced without executing
+/// anything.
+///
+/// `count` is `None` when no separate count statement runs, e.g. with
+/// `disable_total_count` or when the total comes from the data statement.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct QueryPlan {
+    pub count: Option<PlannedStatement>,
+    pub data: PlannedStatement,
+}
@@ -1,12 +1,109 @@
+use crate::count::TotalKind;
+use crate::params::PaginationParams;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PaginatorResponse<T> {
     pub data: Vec<T>,
     pub meta: PaginatorResponseMeta,
+    /// Navigation links for clients that cannot read the `Link` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<PaginationLinks>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl<T> PaginatorResponse<T> {
+    /// Assembles a response from rows fetched with `params.fetch_limit()`,
+    /// dropping the lookahead row and deriving `has_next` from it when needed.
+    ///
+    /// Rows of a backward cursor page arrive in reverse order (see
+    /// `PaginationParams::order_direction`) and are flipped back here.
+    pub fn from_rows(
+        mut data: Vec<T>,
+        params: &PaginationParams,
+        total: Option<(u32, TotalKind)>,
+    ) -> Self {
+        let has_more = if params.needs_lookahead() {
+            let has_more = data.len() > params.per_page as usize;
+            data.truncate(params.per_page as usize);
+            has_more
+        } else {
+            false
+        };
+
+        let meta = match total {
+            _ if params.cursor.is_some() => {
+                // The side being scanned has more rows when the lookahead row
+                // came back. The side we came from holds the cursor row, so it
+                // has rows when this page does. An exact total also settles
+                // it when the scan reached the end: whatever this page did not
+                // return lies behind the cursor.
+                let came_from = match total {
+                    Some((total, TotalKind::Exact)) if !has_more => total as usize > data.len(),
+                    _ => !data.is_empty(),
+                };
+                let (has_prev, has_next) = if params.is_backward() {
+                    data.reverse();
+                    (has_more, came_from)
+                } else {
+                    (came_from, has_more)
+                };
+                let mut meta = PaginatorResponseMeta::new_with_cursors(
+                    params.page,
+                    params.per_page,
+                    total.map(|(t, _)| t),
+                    has_next,
+                    None,
+                    None,
+                )
+                .with_total_kind(total.map(|(_, kind)| kind).unwrap_or_default());
+                meta.has_prev = has_prev;
+                meta
+            }
+            Some((total, kind)) if params.needs_lookahead() => {
+                PaginatorResponseMeta::new_with_total_kind(
+                    params.page,
+                    params.per_page,
+                    total,
+                    kind,
+                    has_more,
+                )
+            }
+            Some((total, _)) => PaginatorResponseMeta::new(params.page, params.per_page, total),
+            None => {
+                PaginatorResponseMeta::new_without_total(params.page, params.per_page, has_more)
+            }
+        };
+
+        Self {
+            data,
+            meta,
+            links: None,
+        }
+    }
+
+    pub fn with_links(mut self, links: PaginationLinks) -> Self {
+        self.links = Some(links);
+        self
+    }
+}
+
+/// Links to the current, first, previous, next and last pages of a response,
+/// relative to the server.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PaginationLinks {
+    #[serde(rename = "self")]
+    pub self_link: String,
+    pub first: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    /// Missing when the total is unknown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct PaginatorResponseMeta {
     pub page: u32,
     pub per_page: u32,
@@ -14,6 +111,10 @@ pub struct PaginatorResponseMeta {
     pub total: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_pages: Option<u32>,
+    /// Whether `total` is exact, a lower bound or an estimate. Omitted from
+    /// the serialized output when exact.
+    #[serde(default, skip_serializing_if = "TotalKind::is_exact")]
+    pub total_kind: TotalKind,
     pub has_next: bool,
     pub has_prev: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -30,6 +131,7 @@ impl PaginatorResponseMeta {
             per_page,
             total: Some(total),
             total_pages: Some(total_pages),
+            total_kind: TotalKind::Exact,
             has_next: page < total_pages,
             has_prev: page > 1,
             next_cursor: None,
@@ -43,6 +145,7 @@ impl PaginatorResponseMeta {
             per_page,
             total: None,
             total_pages: None,
+            total_kind: TotalKind::Exact,
             has_next,
             has_prev: page > 1,
             next_cursor: None,
@@ -64,10 +167,39 @@ impl PaginatorResponseMeta {
             per_page,
             total,
             total_pages,
+            total_kind: TotalKind::Exact,
             has_next,
             has_prev: page > 1 || prev_cursor.is_some(),
             next_cursor,
             prev_cursor,
         }
     }
+
+    /// Builds metadata for a total that may be capped or estimated. `has_next`
+    /// must come from the data query, since the total cannot be trusted for it.
+    pub fn new_with_total_kind(
+        page: u32,
+        per_page: u32,
+        total: u32,
+        total_kind: TotalKind,
+        has_next: bool,
+    ) -> Self {
+        let total_pages = (total as f32 / per_page as f32).ceil() as u32;
+        Self {
+            page,
+            per_page,
+            total: Some(total),
+            total_pages: Some(total_pages),
+            total_kind,
+            has_next,
+            has_prev: page > 1,
+            next_cursor: None,
+            prev_cursor: None,
+        }
+    }
+
+    pub fn with_total_kind(mut self, total_kind: TotalKind) -> Self {
+        self.total_kind = total_kind;
+        self
+    }
 }